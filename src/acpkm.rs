@@ -0,0 +1,379 @@
+//! Internal re-keying of R 1323565.1.017-2018 (RFC 8645): CTR-ACPKM and
+//! OMAC-ACPKM change the key after every section of `N` bytes, so that no
+//! key processes more data than its leakage bounds allow.
+
+use crate::error::Error;
+use crate::mac::{check_tag_len, constant_time_eq, double};
+use crate::modes::{increment, BlockCipher, Keystream};
+use zeroize::Zeroize;
+
+/// CTR-ACPKM section sizes of RFC 9337, used for CMS content and PBES2
+/// private key encryption.
+pub const KUZNYECHIK_SECTION: usize = 4096;
+pub const MAGMA_SECTION: usize = 1024;
+
+/// The next section key: the first 256 bits of the encryption of the
+/// constant `0x80 || 0x81 || .. || 0x9f` under the current one.
+pub fn acpkm<C: BlockCipher>(cipher: &C) -> C {
+    let mut key: [u8; 32] = std::array::from_fn(|i| 0x80 + i as u8);
+    for block in key.chunks_mut(C::BLOCK_SIZE) {
+        cipher.encrypt_block(block);
+    }
+
+    let next = cipher.with_key(&key);
+    key.zeroize();
+
+    next
+}
+
+/// Counter mode with `s = n` whose key changes by `acpkm` after every
+/// `section` bytes; the counter runs on across sections.
+pub struct CtrAcpkm<C> {
+    cipher: C,
+    counter: Vec<u8>,
+    section: usize,
+    /// Blocks of gamma produced under the current key.
+    blocks: usize,
+    keystream: Keystream,
+}
+
+/// The MAC of GOST R 34.13-2015 whose section keys and `K1` subkeys are
+/// drawn from CTR-ACPKM over zeros (ACPKM-Master): each section of
+/// `section` bytes is chained under its own key, and the last block is
+/// masked with the subkeys of its section.
+pub struct OmacAcpkm<C> {
+    master: CtrAcpkm<C>,
+    section: usize,
+    cipher: Option<C>,
+    k1: Vec<u8>,
+    state: Vec<u8>,
+    buffer: Vec<u8>,
+    /// Bytes chained into `state` so far.
+    processed: usize,
+}
+
+impl<C: BlockCipher> CtrAcpkm<C> {
+    /// `iv` is half a block and `section` a whole number of blocks.
+    pub fn new(cipher: C, iv: &[u8], section: usize) -> Result<Self, Error> {
+        if iv.len() != C::BLOCK_SIZE / 2 {
+            return Err(Error::InvalidLength {
+                expected: C::BLOCK_SIZE / 2,
+                actual: iv.len(),
+            });
+        }
+        check_section::<C>(section)?;
+        let mut counter = iv.to_vec();
+        counter.resize(C::BLOCK_SIZE, 0);
+
+        Ok(Self {
+            cipher,
+            counter,
+            section,
+            blocks: 0,
+            keystream: Keystream::new::<C>(C::BLOCK_SIZE)?,
+        })
+    }
+
+    /// Encrypts or decrypts the next part of the data in place.
+    pub fn apply(&mut self, data: &mut [u8]) {
+        let Self {
+            cipher,
+            counter,
+            section,
+            blocks,
+            keystream,
+        } = self;
+        keystream.apply(data, |gamma| {
+            if *blocks == *section / C::BLOCK_SIZE {
+                *cipher = acpkm(cipher);
+                *blocks = 0;
+            }
+            gamma.copy_from_slice(counter);
+            cipher.encrypt_block(gamma);
+            increment(counter);
+            *blocks += 1;
+        });
+    }
+}
+
+impl<C: BlockCipher> OmacAcpkm<C> {
+    /// `section` is `N` and `master_section` is the section `T*` of
+    /// ACPKM-Master, both whole numbers of blocks.
+    pub fn new(cipher: C, section: usize, master_section: usize) -> Result<Self, Error> {
+        check_section::<C>(section)?;
+        let iv = vec![0xff; C::BLOCK_SIZE / 2];
+
+        Ok(Self {
+            master: CtrAcpkm::new(cipher, &iv, master_section)?,
+            section,
+            cipher: None,
+            k1: vec![],
+            state: vec![0; C::BLOCK_SIZE],
+            buffer: Vec::with_capacity(C::BLOCK_SIZE),
+            processed: 0,
+        })
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.buffer.len() == C::BLOCK_SIZE {
+                self.start_block();
+                xor(&mut self.state, &self.buffer);
+                let cipher = self.cipher.as_ref().unwrap();
+                cipher.encrypt_block(&mut self.state);
+                self.processed += C::BLOCK_SIZE;
+                self.buffer.clear();
+            }
+
+            let take = (C::BLOCK_SIZE - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+        }
+    }
+
+    /// The first `len` bytes of the MAC, where `0 < len <= n`.
+    pub fn finalize(mut self, len: usize) -> Result<Vec<u8>, Error> {
+        check_tag_len::<C>(1, len)?;
+
+        self.start_block();
+        let mut subkey = if self.buffer.len() == C::BLOCK_SIZE {
+            self.k1.clone()
+        } else {
+            self.buffer.push(0x80);
+            self.buffer.resize(C::BLOCK_SIZE, 0);
+            double(&self.k1)
+        };
+        xor(&mut self.state, &subkey);
+        xor(&mut self.state, &self.buffer);
+        subkey.zeroize();
+
+        let mut state = std::mem::take(&mut self.state);
+        self.cipher.as_ref().unwrap().encrypt_block(&mut state);
+        state.truncate(len);
+
+        Ok(state)
+    }
+
+    /// Checks a MAC truncated to the length of `tag`, in constant time.
+    pub fn verify(self, tag: &[u8]) -> Result<(), Error> {
+        if tag.is_empty() || tag.len() > C::BLOCK_SIZE {
+            return Err(Error::InvalidTag);
+        }
+
+        if constant_time_eq(&self.finalize(tag.len())?, tag) {
+            Ok(())
+        } else {
+            Err(Error::InvalidTag)
+        }
+    }
+
+    /// Takes the next key and `K1` from ACPKM-Master when the next block
+    /// starts a section.
+    fn start_block(&mut self) {
+        if !self.processed.is_multiple_of(self.section) {
+            return;
+        }
+
+        let mut material = vec![0u8; 32 + C::BLOCK_SIZE];
+        self.master.apply(&mut material);
+        let key = material[..32].try_into().unwrap();
+        self.cipher = Some(self.master.cipher.with_key(key));
+        self.k1.zeroize();
+        self.k1 = material[32..].to_vec();
+        material.zeroize();
+    }
+}
+
+impl<C> Drop for CtrAcpkm<C> {
+    fn drop(&mut self) {
+        self.keystream.zeroize();
+    }
+}
+
+impl<C> Drop for OmacAcpkm<C> {
+    fn drop(&mut self) {
+        self.k1.zeroize();
+        self.state.zeroize();
+        self.buffer.zeroize();
+    }
+}
+
+fn check_section<C: BlockCipher>(section: usize) -> Result<(), Error> {
+    if section == 0 || !section.is_multiple_of(C::BLOCK_SIZE) {
+        return Err(Error::Unaligned {
+            block: C::BLOCK_SIZE,
+            actual: section,
+        });
+    }
+
+    Ok(())
+}
+
+fn xor(data: &mut [u8], other: &[u8]) {
+    for (a, b) in data.iter_mut().zip(other) {
+        *a ^= b;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{acpkm, CtrAcpkm, OmacAcpkm};
+    use crate::error::Error;
+    use crate::kuznyechik::Kuznyechik;
+    use crate::magma::Magma;
+    use crate::modes::{BlockCipher, Ctr};
+    use crate::ByteParse;
+
+    fn kuznyechik() -> Kuznyechik {
+        let key = "8899aabbccddeeff0011223344556677fedcba98765432100123456789abcdef".parse_bytes();
+        Kuznyechik::new(&key.try_into().unwrap())
+    }
+
+    fn magma() -> Magma {
+        let key = "ffeeddccbbaa99887766554433221100f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff".parse_bytes();
+        Magma::new(&key.try_into().unwrap())
+    }
+
+    /// RFC 8645, A.1: the key changes after the first two blocks.
+    #[test]
+    fn ctr() {
+        let next = "2666ed40ae687811745ca0b448f57a7b390adb5780307e8e9659ac403ae60c60".parse_bytes();
+        let mut block = [0u8; 16];
+        let mut expected = block;
+        acpkm(&kuznyechik()).encrypt_block(&mut block);
+        Kuznyechik::new(&next.try_into().unwrap()).encrypt_block(&mut expected);
+        assert_eq!(block, expected);
+
+        let iv = "1234567890abcef0".parse_bytes();
+        let mut data = "1122334455667700ffeeddccbbaa9988\
+                        00112233445566778899aabbcceeff0a\
+                        112233445566778899aabbcceeff0a00"
+            .parse_bytes();
+        CtrAcpkm::new(kuznyechik(), &iv, 32)
+            .unwrap()
+            .apply(&mut data);
+        assert_eq!(
+            data,
+            "f195d8bec10ed1dbd57b5fa240bda1b8\
+             85eee733f6a13e5df33ce4b33c45dee4\
+             4bceeb8f646f4c55001706275e85e800"
+                .parse_bytes()
+        );
+    }
+
+    /// RFC 8645, A.1 for Magma: `N = 128` bits, so every second block
+    /// is under a new key.
+    #[test]
+    fn ctr_magma() {
+        let key = "8899aabbccddeeff0011223344556677fedcba98765432100123456789abcdef".parse_bytes();
+        let mut data = "1122334455667700ffeeddccbbaa9988\
+                        00112233445566778899aabbcceeff0a\
+                        112233445566778899aabbcceeff0a00\
+                        2233445566778899aabbcceeff0a0011\
+                        33445566778899aabbcceeff0a001122"
+            .parse_bytes();
+        CtrAcpkm::new(
+            Magma::new(&key.try_into().unwrap()),
+            &"12345678".parse_bytes(),
+            16,
+        )
+        .unwrap()
+        .apply(&mut data);
+        assert_eq!(
+            data,
+            "2ab81deeeb1e4cab68e104c4bd6b94ea\
+             c72c67af6c2e5b6b0eafb61770f1b32e\
+             a1ae71149eed1382abd467180672ec6f\
+             84a2f15b3fca72c15559fbd38c4c7c5d\
+             a90d5adbbd3d22f92b2283b686439fb4"
+                .parse_bytes()
+        );
+    }
+
+    /// Within a section CTR-ACPKM is CTR; after it, CTR under the next key
+    /// with the counter running on.
+    #[test]
+    fn sections() {
+        let iv = "12345678".parse_bytes();
+        let plaintext: Vec<u8> = (0..45).collect();
+
+        let mut data = plaintext.clone();
+        let mut ctr = CtrAcpkm::new(magma(), &iv, 16).unwrap();
+        for part in data.chunks_mut(7) {
+            ctr.apply(part);
+        }
+
+        let mut expected = plaintext.clone();
+        Ctr::new(magma(), &iv, 8)
+            .unwrap()
+            .apply(&mut expected[..16]);
+        let mut counter = vec![0u8; 8];
+        counter[..4].copy_from_slice(&iv);
+        counter[7] = 2;
+        let mut cipher = magma();
+        for block in expected[16..].chunks_mut(8) {
+            if counter[7].is_multiple_of(2) {
+                cipher = acpkm(&cipher);
+            }
+            let mut gamma = counter.clone();
+            BlockCipher::encrypt_block(&cipher, &mut gamma);
+            for (byte, gamma) in block.iter_mut().zip(gamma) {
+                *byte ^= gamma;
+            }
+            counter[7] += 1;
+        }
+        assert_eq!(data, expected);
+
+        assert!(CtrAcpkm::new(magma(), &iv, 12).is_err());
+        assert!(CtrAcpkm::new(magma(), &iv[..2], 16).is_err());
+    }
+
+    /// RFC 8645, A.2: `N = 256` and `T* = 768` bits, so the chain is
+    /// re-keyed twice and ACPKM-Master itself once.
+    #[test]
+    fn omac() {
+        let data = "1122334455667700ffeeddccbbaa9988\
+                    00112233445566778899aabbcceeff0a\
+                    112233445566778899aabbcceeff0a00\
+                    2233445566778899aabbcceeff0a0011\
+                    33445566778899aabbcceeff0a001122"
+            .parse_bytes();
+        let expected = "fbb8dcee45bea67c35f58c5700898e5d".parse_bytes();
+
+        let mut mac = OmacAcpkm::new(kuznyechik(), 32, 96).unwrap();
+        mac.update(&data);
+        assert_eq!(mac.finalize(16).unwrap(), expected);
+
+        let mut mac = OmacAcpkm::new(kuznyechik(), 32, 96).unwrap();
+        for part in data.chunks(5) {
+            mac.update(part);
+        }
+        assert_eq!(mac.verify(&expected[..8]), Ok(()));
+
+        let mut mac = OmacAcpkm::new(kuznyechik(), 64, 96).unwrap();
+        mac.update(&data);
+        assert_eq!(mac.verify(&expected), Err(Error::InvalidTag));
+
+        for len in [0, 17] {
+            let mac = OmacAcpkm::new(kuznyechik(), 32, 96).unwrap();
+            assert_eq!(
+                mac.finalize(len),
+                Err(Error::InvalidTagLength {
+                    min: 1,
+                    max: 16,
+                    len
+                })
+            );
+        }
+    }
+
+    /// Magma with the key of RFC 8645 and a message shorter than a block
+    /// and a half: the MAC under the first ACPKM-Master key and `K2`.
+    #[test]
+    fn omac_magma() {
+        let key = "8899aabbccddeeff0011223344556677fedcba98765432100123456789abcdef".parse_bytes();
+        let mut mac = OmacAcpkm::new(Magma::new(&key.try_into().unwrap()), 16, 80).unwrap();
+        mac.update(&"1122334455667700ffeeddcc".parse_bytes());
+        assert_eq!(mac.finalize(8).unwrap(), "a0540e3730acbcf3".parse_bytes());
+    }
+}
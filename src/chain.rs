@@ -0,0 +1,603 @@
+//! Path building and validation of certificate chains against a set of
+//! trusted roots loaded from local files.
+
+use crate::crl::Crl;
+use crate::error::Error;
+use crate::oid;
+use crate::pem::{io_error, CERTIFICATE};
+use crate::x509::{Certificate, KeyUsage};
+use std::fmt;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Longest chain tried, end-entity and root included.
+const MAX_DEPTH: usize = 16;
+
+/// Extensions whose critical flag `validate` honours.
+const HANDLED_EXTENSIONS: [&str; 5] = [
+    oid::SUBJECT_KEY_IDENTIFIER,
+    oid::KEY_USAGE,
+    oid::SUBJECT_ALT_NAME,
+    oid::BASIC_CONSTRAINTS,
+    oid::AUTHORITY_KEY_IDENTIFIER,
+];
+
+/// CRL extensions whose critical flag `check_revocation` honours.
+const HANDLED_CRL_EXTENSIONS: [&str; 2] = [oid::CRL_NUMBER, oid::AUTHORITY_KEY_IDENTIFIER];
+
+/// CRL entry extensions whose critical flag `check_revocation` honours.
+const HANDLED_CRL_ENTRY_EXTENSIONS: [&str; 1] = [oid::CRL_REASON];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    NotYetValid,
+    Expired,
+    IssuerNotFound,
+    Signature(Error),
+    NotCa,
+    PathLengthExceeded,
+    KeyCertSignNotAllowed,
+    UnhandledCriticalExtension(String),
+    Malformed(Error),
+    TooLong,
+    CrlNotFound,
+    CrlExpired,
+    CrlSignNotAllowed,
+    /// With the revocation date.
+    Revoked(SystemTime),
+}
+
+/// The first link of a chain that failed, counting from the end-entity
+/// certificate at depth 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainError {
+    pub depth: usize,
+    pub subject: String,
+    pub failure: Failure,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    roots: Vec<Certificate>,
+    skipped: Vec<Error>,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::NotYetValid => write!(f, "certificate is not yet valid"),
+            Failure::Expired => write!(f, "certificate has expired"),
+            Failure::IssuerNotFound => write!(f, "issuer certificate not found"),
+            Failure::Signature(err) => write!(f, "bad signature: {}", err),
+            Failure::NotCa => write!(f, "issuer is not a CA"),
+            Failure::PathLengthExceeded => write!(f, "path length constraint exceeded"),
+            Failure::KeyCertSignNotAllowed => write!(f, "key usage does not allow keyCertSign"),
+            Failure::UnhandledCriticalExtension(oid) => {
+                write!(f, "unhandled critical extension {}", oid)
+            }
+            Failure::Malformed(err) => write!(f, "{}", err),
+            Failure::TooLong => write!(f, "chain is longer than {} certificates", MAX_DEPTH),
+            Failure::CrlNotFound => write!(f, "no CRL from the issuer"),
+            Failure::CrlExpired => write!(f, "the issuer's CRL is out of date"),
+            Failure::CrlSignNotAllowed => write!(f, "issuer key usage does not allow cRLSign"),
+            Failure::Revoked(_) => write!(f, "certificate has been revoked"),
+        }
+    }
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "depth {} ({}): {}",
+            self.depth, self.subject, self.failure
+        )
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+impl TrustStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every regular file of `dir`, each a DER certificate or PEM with one
+    /// or more certificates. Hidden files and subdirectories are skipped, and
+    /// so are files and certificates that do not load, such as the RSA and
+    /// ECDSA roots of a system store; `skipped` tells why.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut store = Self::new();
+        let entries = std::fs::read_dir(&dir).map_err(|err| io_error(dir.as_ref(), err))?;
+
+        let mut paths = vec![];
+        for entry in entries {
+            let path = entry.map_err(|err| io_error(dir.as_ref(), err))?.path();
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if path.is_file() && !hidden {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        for path in paths {
+            store.load_supported(&path);
+        }
+
+        Ok(store)
+    }
+
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let certificates =
+            crate::pem::load_file(path.as_ref(), CERTIFICATE, Certificate::from_der)?;
+        self.roots.extend(certificates);
+
+        Ok(())
+    }
+
+    /// The files and certificates `from_dir` left out, each as an
+    /// `Error::File` naming the file.
+    pub fn skipped(&self) -> &[Error] {
+        &self.skipped
+    }
+
+    fn load_supported(&mut self, path: &Path) {
+        let loaded = crate::pem::load_file(path, CERTIFICATE, |der| Ok(Certificate::from_der(der)));
+        let certificates = match loaded {
+            Ok(certificates) => certificates,
+            Err(err) => return self.skipped.push(err),
+        };
+
+        for certificate in certificates {
+            match certificate {
+                Ok(certificate) => self.roots.push(certificate),
+                Err(err) => self.skipped.push(Error::File {
+                    path: path.display().to_string(),
+                    reason: Box::new(err),
+                }),
+            }
+        }
+    }
+
+    pub fn add(&mut self, root: Certificate) {
+        self.roots.push(root);
+    }
+
+    pub fn roots(&self) -> &[Certificate] {
+        &self.roots
+    }
+
+    fn is_trusted(&self, certificate: &Certificate) -> bool {
+        self.roots
+            .iter()
+            .any(|root| root.to_der() == certificate.to_der())
+    }
+
+    /// Builds a path from `leaf` through `intermediates` to a trusted root
+    /// and checks every link at `time`. Returns the chain, leaf first.
+    pub fn validate(
+        &self,
+        leaf: &Certificate,
+        intermediates: &[Certificate],
+        time: SystemTime,
+    ) -> Result<Vec<Certificate>, ChainError> {
+        let mut chain = vec![leaf.clone()];
+        let mut broken = None;
+
+        while !self.is_trusted(chain.last().unwrap()) {
+            let current = chain.last().unwrap();
+            if chain.len() == MAX_DEPTH {
+                broken = Some(Failure::TooLong);
+                break;
+            }
+
+            let mut failure = Failure::IssuerNotFound;
+            let issuer = self
+                .roots
+                .iter()
+                .chain(intermediates)
+                .filter(|candidate| candidate.subject() == current.issuer())
+                .filter(|candidate| chain.iter().all(|c| c.to_der() != candidate.to_der()))
+                .find(
+                    |candidate| match current.verify_signature(candidate.public_key()) {
+                        Ok(()) => true,
+                        Err(err) => {
+                            failure = Failure::Signature(err);
+                            false
+                        }
+                    },
+                );
+
+            match issuer {
+                Some(issuer) => chain.push(issuer.clone()),
+                None => {
+                    broken = Some(failure);
+                    break;
+                }
+            }
+        }
+
+        for (depth, certificate) in chain.iter().enumerate() {
+            let fail = |failure| ChainError {
+                depth,
+                subject: certificate.subject().to_string(),
+                failure,
+            };
+
+            check_certificate(certificate, &chain[1..depth.max(1)], depth > 0, time)
+                .map_err(fail)?;
+            if depth + 1 == chain.len() {
+                if let Some(failure) = broken.take() {
+                    return Err(fail(failure));
+                }
+            }
+        }
+
+        Ok(chain)
+    }
+}
+
+/// Checks every certificate of `chain`, as returned by `validate`, except
+/// the root against the newest CRL in `crls` that its issuer signed and
+/// that was issued by `time`. A certificate without such a CRL fails, and
+/// so does one revoked by `time`.
+pub fn check_revocation(
+    chain: &[Certificate],
+    crls: &[Crl],
+    time: SystemTime,
+) -> Result<(), ChainError> {
+    for (depth, pair) in chain.windows(2).enumerate() {
+        let (certificate, issuer) = (&pair[0], &pair[1]);
+        let fail = |failure| ChainError {
+            depth,
+            subject: certificate.subject().to_string(),
+            failure,
+        };
+
+        let crl = crls
+            .iter()
+            .filter(|crl| crl.this_update() <= time)
+            .filter(|crl| crl.verify_issued_by(issuer).is_ok())
+            .max_by_key(|crl| crl.this_update())
+            .ok_or_else(|| fail(Failure::CrlNotFound))?;
+        check_crl(crl, issuer, time).map_err(fail)?;
+
+        if let Some(entry) = crl
+            .revocation(certificate)
+            .filter(|entry| entry.date <= time)
+        {
+            return Err(fail(Failure::Revoked(entry.date)));
+        }
+    }
+
+    Ok(())
+}
+
+fn check_crl(crl: &Crl, issuer: &Certificate, time: SystemTime) -> Result<(), Failure> {
+    if crl.next_update().is_some_and(|next| next < time) {
+        return Err(Failure::CrlExpired);
+    }
+    if let Some(extension) = crl
+        .extensions()
+        .iter()
+        .find(|extension| extension.critical && !HANDLED_CRL_EXTENSIONS.contains(&&*extension.oid))
+    {
+        return Err(Failure::UnhandledCriticalExtension(extension.oid.clone()));
+    }
+    // RFC 5280, section 5.3: a CRL with an entry extension that cannot be
+    // processed must not be used for any certificate.
+    if let Some(extension) = crl
+        .revoked()
+        .iter()
+        .flat_map(|entry| &entry.extensions)
+        .find(|extension| {
+            extension.critical && !HANDLED_CRL_ENTRY_EXTENSIONS.contains(&&*extension.oid)
+        })
+    {
+        return Err(Failure::UnhandledCriticalExtension(extension.oid.clone()));
+    }
+
+    let usage = issuer.key_usage().map_err(Failure::Malformed)?;
+    if usage.is_some_and(|usage| !usage.contains(KeyUsage::CRL_SIGN)) {
+        return Err(Failure::CrlSignNotAllowed);
+    }
+
+    Ok(())
+}
+
+/// Checks of a single link. `below` holds the intermediate certificates
+/// between an issuer and the end-entity certificate.
+fn check_certificate(
+    certificate: &Certificate,
+    below: &[Certificate],
+    issuer: bool,
+    time: SystemTime,
+) -> Result<(), Failure> {
+    if time < certificate.not_before() {
+        return Err(Failure::NotYetValid);
+    }
+    if time > certificate.not_after() {
+        return Err(Failure::Expired);
+    }
+
+    if let Some(extension) = certificate
+        .extensions()
+        .iter()
+        .find(|extension| extension.critical && !HANDLED_EXTENSIONS.contains(&&*extension.oid))
+    {
+        return Err(Failure::UnhandledCriticalExtension(extension.oid.clone()));
+    }
+
+    let constraints = certificate
+        .basic_constraints()
+        .map_err(Failure::Malformed)?;
+    let usage = certificate.key_usage().map_err(Failure::Malformed)?;
+    if !issuer {
+        return Ok(());
+    }
+
+    let constraints = constraints
+        .filter(|constraints| constraints.ca)
+        .ok_or(Failure::NotCa)?;
+    if let Some(len) = constraints.path_len {
+        let intermediates = below.iter().filter(|c| !c.is_self_issued()).count();
+        if intermediates > len as usize {
+            return Err(Failure::PathLengthExceeded);
+        }
+    }
+    if usage.is_some_and(|usage| !usage.contains(KeyUsage::KEY_CERT_SIGN)) {
+        return Err(Failure::KeyCertSignNotAllowed);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChainError, Failure, TrustStore};
+    use crate::der;
+    use crate::error::Error;
+    use crate::oid;
+    use crate::testing::{at, ca, certificate, extension, key, usage};
+    use crate::x509::{Certificate, KeyUsage};
+
+    fn failure(result: Result<Vec<Certificate>, ChainError>) -> (usize, Failure) {
+        let err = result.unwrap_err();
+        (err.depth, err.failure)
+    }
+
+    #[test]
+    fn validate() {
+        let (root_key, ca_key, leaf_key) = (
+            key(oid::CRYPTOPRO_A, 1),
+            key(oid::CRYPTOPRO_A, 2),
+            key(oid::CRYPTOPRO_A, 3),
+        );
+        let root = certificate(
+            "Root",
+            &root_key.public_key(),
+            "Root",
+            &root_key,
+            1,
+            &[ca(None)],
+        );
+        let ca_certificate = certificate(
+            "CA",
+            &ca_key.public_key(),
+            "Root",
+            &root_key,
+            1,
+            &[
+                ca(Some(0)),
+                usage(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN),
+            ],
+        );
+        let leaf = certificate(
+            "Leaf",
+            &leaf_key.public_key(),
+            "CA",
+            &ca_key,
+            1,
+            &[usage(KeyUsage::DIGITAL_SIGNATURE)],
+        );
+
+        let mut store = TrustStore::new();
+        store.add(root.clone());
+        let now = at(1_750_000_000);
+
+        let chain = store
+            .validate(&leaf, std::slice::from_ref(&ca_certificate), now)
+            .unwrap();
+        assert_eq!(
+            chain
+                .iter()
+                .map(|c| c.subject().to_string())
+                .collect::<Vec<_>>(),
+            ["C=RU, CN=Leaf", "C=RU, CN=CA", "C=RU, CN=Root"]
+        );
+        assert_eq!(store.validate(&root, &[], now).unwrap().len(), 1);
+
+        let intermediates = [ca_certificate.clone()];
+        assert_eq!(
+            failure(store.validate(&leaf, &intermediates, at(1_800_000_001))),
+            (0, Failure::Expired)
+        );
+        assert_eq!(
+            failure(store.validate(&leaf, &intermediates, at(1_600_000_000))),
+            (0, Failure::NotYetValid)
+        );
+        assert_eq!(
+            failure(store.validate(&leaf, &[], now)),
+            (0, Failure::IssuerNotFound)
+        );
+
+        let err = store.validate(&leaf, &[], now).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "depth 0 (C=RU, CN=Leaf): issuer certificate not found"
+        );
+
+        // Same name, different key.
+        let impostor = certificate(
+            "CA",
+            &key(oid::CRYPTOPRO_A, 4).public_key(),
+            "Root",
+            &root_key,
+            1,
+            &[ca(None)],
+        );
+        assert_eq!(
+            failure(store.validate(&leaf, &[impostor], now)),
+            (0, Failure::Signature(Error::InvalidSignature))
+        );
+    }
+
+    #[test]
+    fn constraints() {
+        let (root_key, ca_key, leaf_key) = (
+            key(oid::CRYPTOPRO_A, 1),
+            key(oid::CRYPTOPRO_A, 2),
+            key(oid::CRYPTOPRO_A, 3),
+        );
+        let mut store = TrustStore::new();
+        store.add(certificate(
+            "Root",
+            &root_key.public_key(),
+            "Root",
+            &root_key,
+            1,
+            &[ca(Some(0))],
+        ));
+        let now = at(1_750_000_000);
+
+        let leaf = certificate("Leaf", &leaf_key.public_key(), "CA", &ca_key, 1, &[]);
+        let check = |extensions: &[Vec<u8>]| {
+            let ca_certificate =
+                certificate("CA", &ca_key.public_key(), "Root", &root_key, 1, extensions);
+            failure(store.validate(&leaf, &[ca_certificate], now))
+        };
+
+        assert_eq!(check(&[]), (1, Failure::NotCa));
+        assert_eq!(
+            check(&[ca(None), usage(KeyUsage::DIGITAL_SIGNATURE)]),
+            (1, Failure::KeyCertSignNotAllowed)
+        );
+
+        let ca_certificate = certificate(
+            "CA",
+            &ca_key.public_key(),
+            "Root",
+            &root_key,
+            1,
+            &[ca(None)],
+        );
+        let below = certificate(
+            "Below",
+            &key(oid::CRYPTOPRO_A, 5).public_key(),
+            "Leaf",
+            &leaf_key,
+            1,
+            &[],
+        );
+        let leaf_as_ca = certificate(
+            "Leaf",
+            &leaf_key.public_key(),
+            "CA",
+            &ca_key,
+            1,
+            &[ca(None)],
+        );
+        assert_eq!(
+            failure(store.validate(&below, &[leaf_as_ca, ca_certificate.clone()], now)),
+            (3, Failure::PathLengthExceeded)
+        );
+
+        let critical = extension("1.2.3.4", true, der::null());
+        let leaf = certificate(
+            "Leaf",
+            &leaf_key.public_key(),
+            "CA",
+            &ca_key,
+            1,
+            &[critical],
+        );
+        assert_eq!(
+            failure(store.validate(&leaf, &[ca_certificate], now)),
+            (
+                0,
+                Failure::UnhandledCriticalExtension("1.2.3.4".to_string())
+            )
+        );
+    }
+
+    /// A self-signed ECDSA P-256 root made by OpenSSL.
+    const P256_ROOT: &str = "-----BEGIN CERTIFICATE-----
+MIIBgTCCASegAwIBAgIUQILj3AEzf+cWB8rFpG6GZ3K4nMQwCgYIKoZIzj0EAwIw
+FTETMBEGA1UEAwwKUC0yNTYgUm9vdDAgFw0yNjEwMTkwMTA0NDdaGA8yMTI2MDky
+NTAxMDQ0N1owFTETMBEGA1UEAwwKUC0yNTYgUm9vdDBZMBMGByqGSM49AgEGCCqG
+SM49AwEHA0IABPWgGo2woNp+WXm40/uk9HGncImP9cW2tesVJfe3C/28RUvJ5aYm
+2Ty80jBPEw7tpjobN8Bs/sUGduWrmRXmpMCjUzBRMB0GA1UdDgQWBBQ3EcWeH4Vd
+sIew6V6dJiNkle4uhjAfBgNVHSMEGDAWgBQ3EcWeH4VdsIew6V6dJiNkle4uhjAP
+BgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0gAMEUCIQDtJvVc5IAaIihcx6AN
+OQ4zQJuyP2WGZS2Ja52iO5ReEwIgWMQNH4IWjca0SNelejZmzZcr4u9XBhmSzCu0
+Ap0ipPc=
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn from_dir() {
+        let root_key = key(oid::CRYPTOPRO_A, 1);
+        let root = certificate(
+            "Root",
+            &root_key.public_key(),
+            "Root",
+            &root_key,
+            1,
+            &[ca(None)],
+        );
+
+        let dir = std::env::temp_dir().join(format!("trust-store-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("root.pem"), root.to_pem()).unwrap();
+        std::fs::write(dir.join("root.der"), root.to_der()).unwrap();
+        std::fs::write(dir.join(".hidden"), "not a certificate").unwrap();
+
+        let store = TrustStore::from_dir(&dir).unwrap();
+        assert_eq!(store.roots().len(), 2);
+        assert!(store.skipped().is_empty());
+
+        // A system store mixes in roots of other algorithms, and bundles
+        // hold several; neither keeps the GOST roots from loading.
+        std::fs::write(dir.join("p256.pem"), P256_ROOT).unwrap();
+        std::fs::write(
+            dir.join("bundle.pem"),
+            P256_ROOT.to_string() + &root.to_pem(),
+        )
+        .unwrap();
+        std::fs::write(dir.join("broken.pem"), "-----BEGIN CERTIFICATE-----\n").unwrap();
+        let store = TrustStore::from_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(store.roots().len(), 3);
+        assert_eq!(
+            store.skipped(),
+            [
+                Error::File {
+                    path: dir.join("broken.pem").display().to_string(),
+                    reason: Box::new(Error::Pem("missing END line")),
+                },
+                Error::File {
+                    path: dir.join("bundle.pem").display().to_string(),
+                    reason: Box::new(Error::UnsupportedAlgorithm("1.2.840.10045.2.1".to_string())),
+                },
+                Error::File {
+                    path: dir.join("p256.pem").display().to_string(),
+                    reason: Box::new(Error::UnsupportedAlgorithm("1.2.840.10045.2.1".to_string())),
+                },
+            ]
+        );
+        assert!(TrustStore::from_dir(&dir).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{streebog_256, streebog_512, to_hex, ByteParse};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Digest {
+    Streebog256,
+    Streebog512,
+}
+
+impl Digest {
+    pub fn compute(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Digest::Streebog256 => streebog_256(data).to_vec(),
+            Digest::Streebog512 => streebog_512(data).to_vec(),
+        }
+    }
+
+    fn from_hex_len(len: usize) -> Option<Self> {
+        match len {
+            64 => Some(Digest::Streebog256),
+            128 => Some(Digest::Streebog512),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub digest: Digest,
+    pub expected: Vec<u8>,
+    pub filename: String,
+}
+
+impl Entry {
+    pub fn matches(&self, data: &[u8]) -> bool {
+        self.digest.compute(data) == self.expected
+    }
+}
+
+/// Formats a `gost12sum` line: the hex digest, two spaces and the file name.
+pub fn format_line(digest: Digest, data: &[u8], filename: &str) -> String {
+    format!("{}  {}", to_hex(&digest.compute(data)), filename)
+}
+
+/// Parses a `gost12sum` line, accepting both the text (`"  "`) and the
+/// binary (`" *"`) separators. The digest size is taken from the hex length.
+pub fn parse_line(line: &str) -> Option<Entry> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let (hex, rest) = line.split_once(' ')?;
+    let filename = rest.strip_prefix(' ').or_else(|| rest.strip_prefix('*'))?;
+
+    let digest = Digest::from_hex_len(hex.len())?;
+    if filename.is_empty() {
+        return None;
+    }
+
+    Some(Entry {
+        digest,
+        expected: hex.try_parse_bytes()?,
+        filename: filename.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_line, parse_line, Digest};
+
+    const M1: &[u8] = b"012345678901234567890123456789012345678901234567890123456789012";
+
+    #[test]
+    fn sum_line() {
+        assert_eq!(
+            format_line(Digest::Streebog256, M1, "m1.txt"),
+            "9d151eefd8590b89daa6ba6cb74af9275dd051026bb149a452fd84e5e57b5500  m1.txt"
+        );
+        assert_eq!(
+            format_line(Digest::Streebog512, M1, "m1.txt"),
+            "1b54d01a4af5b9d5cc3d86d68d285462b19abc2475222f35c085122be4ba1ffa\
+             00ad30f8767b3a82384c6574f024c311e2a481332b08ef7f41797891c1646f48  m1.txt"
+        );
+    }
+
+    #[test]
+    fn check_line() {
+        let line = format_line(Digest::Streebog512, M1, "dir/with space.bin");
+        let entry = parse_line(&line).unwrap();
+        assert_eq!(entry.digest, Digest::Streebog512);
+        assert_eq!(entry.filename, "dir/with space.bin");
+        assert!(entry.matches(M1));
+        assert!(!entry.matches(b"0123"));
+
+        let binary = line.replacen("  ", " *", 1);
+        assert_eq!(parse_line(&binary), Some(entry));
+
+        assert_eq!(parse_line("9d151eef  m1.txt"), None);
+        assert_eq!(parse_line(&line.replacen("  ", " ", 1)), None);
+        assert_eq!(parse_line(&line.replacen('1', "g", 1)), None);
+    }
+}
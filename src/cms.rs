@@ -0,0 +1,1265 @@
+//! CMS SignedData (RFC 5652) with GOST R 34.10-2012 signers, as profiled
+//! by RFC 4490: Streebog digests, the public key algorithm OID as the
+//! signature algorithm and `s || r` signature values. EnvelopedData follows
+//! RFC 9337: VKO and KExp15 protect the content key, which encrypts the
+//! content in CTR-ACPKM-OMAC mode. Input must be DER; BER with indefinite
+//! lengths is rejected.
+
+use crate::acpkm::{CtrAcpkm, KUZNYECHIK_SECTION, MAGMA_SECTION};
+use crate::curve::Curve;
+use crate::der::{self, Tlv};
+use crate::error::Error;
+use crate::hmac::kdf_tree_streebog_256;
+use crate::kexp::{kexp15, kimp15};
+use crate::key::{PrivateKey, PublicKey};
+use crate::kuznyechik::{Kuznyechik, KUZNYECHIK_BLOCK};
+use crate::mac::Mac;
+use crate::magma::{Magma, MAGMA_BLOCK};
+use crate::modes::BlockCipher;
+use crate::oid;
+use crate::pem::{Pem, CMS, PKCS7};
+use crate::signature::Signature;
+use crate::vko::{vko_gostr3410_2012_256, vko_gostr3410_2012_512};
+use crate::x509::{parse_algorithm, verify_signed, Certificate, Name};
+use num_bigint_dig::BigInt;
+use rand::{CryptoRng, RngCore};
+use std::time::SystemTime;
+use zeroize::{Zeroize, Zeroizing};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerId {
+    IssuerAndSerial(Name, BigInt),
+    SubjectKeyIdentifier(Vec<u8>),
+}
+
+impl SignerId {
+    /// Whether `certificate` is the one named, also for recipients.
+    pub fn matches(&self, certificate: &Certificate) -> bool {
+        match self {
+            SignerId::IssuerAndSerial(issuer, serial) => {
+                certificate.issuer() == issuer && certificate.serial() == serial
+            }
+            SignerId::SubjectKeyIdentifier(id) => {
+                certificate.subject_key_identifier().ok().flatten().as_ref() == Some(id)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SignerInfo {
+    id: SignerId,
+    digest_algorithm: String,
+    /// The `signedAttrs` encoded as the SET OF that is signed.
+    signed_attributes: Option<Vec<u8>>,
+    content_type: Option<String>,
+    message_digest: Option<Vec<u8>>,
+    signing_time: Option<SystemTime>,
+    signature_algorithm: String,
+    signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SignedData {
+    raw: Vec<u8>,
+    content_type: String,
+    content: Option<Vec<u8>>,
+    certificates: Vec<Certificate>,
+    signers: Vec<SignerInfo>,
+}
+
+/// Signs `id-data` content, with the content-type, message-digest and
+/// signing-time attributes, by every signer added.
+/// Keys are borrowed rather than copied, so they outlive the builder.
+#[derive(Debug, Clone)]
+pub struct SignedDataBuilder<'a> {
+    content: Vec<u8>,
+    detached: bool,
+    signing_time: Option<SystemTime>,
+    signers: Vec<(Certificate, &'a PrivateKey)>,
+    certificates: Vec<Certificate>,
+}
+
+impl SignerInfo {
+    fn parse(info: Tlv) -> Result<Self, Error> {
+        let mut fields = info.expect(der::SEQUENCE)?.reader();
+        let version = fields.expect(der::INTEGER)?.integer()?;
+
+        let sid = fields.read()?;
+        let id = match sid.tag {
+            der::SEQUENCE if version == 1.into() => {
+                let mut reader = sid.reader();
+                let issuer = Name::parse(reader.read()?)?;
+                let serial = reader.expect(der::INTEGER)?.integer()?;
+                reader.finish()?;
+                SignerId::IssuerAndSerial(issuer, serial)
+            }
+            0x80 if version == 3.into() => SignerId::SubjectKeyIdentifier(sid.content.to_vec()),
+            _ => return Err(Error::Cms("unsupported signer identifier")),
+        };
+
+        let digest_algorithm = parse_algorithm(fields.read()?)?;
+
+        let mut info = Self {
+            id,
+            digest_algorithm,
+            signed_attributes: None,
+            content_type: None,
+            message_digest: None,
+            signing_time: None,
+            signature_algorithm: String::new(),
+            signature: vec![],
+        };
+
+        if let Some(attributes) = fields.optional(der::context(0))? {
+            let mut signed = attributes.raw.to_vec();
+            signed[0] = der::SET;
+            info.signed_attributes = Some(signed);
+
+            let mut reader = attributes.reader();
+            while !reader.is_empty() {
+                let mut attribute = reader.expect(der::SEQUENCE)?.reader();
+                let kind = attribute.expect(der::OID)?.oid()?;
+                let mut values = attribute.expect(der::SET)?.reader();
+                attribute.finish()?;
+
+                let value = values.read()?;
+                match kind.as_str() {
+                    oid::CONTENT_TYPE => info.content_type = Some(value.oid()?),
+                    oid::MESSAGE_DIGEST => {
+                        info.message_digest = Some(value.octet_string()?.to_vec())
+                    }
+                    oid::SIGNING_TIME => info.signing_time = Some(value.time()?),
+                    _ => continue,
+                }
+                values.finish()?;
+            }
+        }
+
+        info.signature_algorithm = parse_algorithm(fields.read()?)?;
+        info.signature = fields.expect(der::OCTET_STRING)?.content.to_vec();
+        fields.optional(der::context(1))?;
+        fields.finish()?;
+
+        Ok(info)
+    }
+
+    pub fn id(&self) -> &SignerId {
+        &self.id
+    }
+
+    pub fn digest_algorithm(&self) -> &str {
+        &self.digest_algorithm
+    }
+
+    pub fn signature_algorithm(&self) -> &str {
+        &self.signature_algorithm
+    }
+
+    pub fn signing_time(&self) -> Option<SystemTime> {
+        self.signing_time
+    }
+
+    /// Whether `certificate` is the one this signer names.
+    pub fn matches(&self, certificate: &Certificate) -> bool {
+        self.id.matches(certificate)
+    }
+
+    /// Checks the signature over `content` with the key of `certificate`.
+    /// With signed attributes, the message digest and content type are
+    /// checked too.
+    pub fn verify(
+        &self,
+        content_type: &str,
+        content: &[u8],
+        certificate: &Certificate,
+    ) -> Result<(), Error> {
+        let key = certificate.public_key();
+        let (digest, algorithm) = match key.curve().size() {
+            32 => (oid::GOST3411_12_256, oid::SIGNWITHDIGEST_GOST3410_12_256),
+            _ => (oid::GOST3411_12_512, oid::SIGNWITHDIGEST_GOST3410_12_512),
+        };
+        if self.digest_algorithm != digest {
+            return Err(Error::UnsupportedAlgorithm(self.digest_algorithm.clone()));
+        }
+        match self.signature_algorithm.as_str() {
+            oid::GOST3410_12_256 | oid::GOST3410_12_512 => {}
+            oid::SIGNWITHDIGEST_GOST3410_12_256 | oid::SIGNWITHDIGEST_GOST3410_12_512 => {}
+            other => return Err(Error::UnsupportedAlgorithm(other.to_string())),
+        }
+
+        let Some(signed) = &self.signed_attributes else {
+            return verify_signed(algorithm, content, &self.signature, key);
+        };
+        if self.content_type.as_deref() != Some(content_type) {
+            return Err(Error::Cms("content type attribute mismatch"));
+        }
+        match &self.message_digest {
+            Some(message_digest) if *message_digest == key.curve().digest(content) => {}
+            Some(_) => return Err(Error::InvalidSignature),
+            None => return Err(Error::Cms("missing message digest attribute")),
+        }
+
+        verify_signed(algorithm, signed, &self.signature, key)
+    }
+}
+
+impl SignedData {
+    pub fn from_der(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = der::parse(bytes)?.expect(der::SEQUENCE)?.reader();
+        if reader.expect(der::OID)?.oid()? != oid::SIGNED_DATA {
+            return Err(Error::Cms("not a SignedData"));
+        }
+        let mut wrapper = reader.expect(der::context(0))?.reader();
+        let signed_data = wrapper.expect(der::SEQUENCE)?;
+        wrapper.finish()?;
+        reader.finish()?;
+
+        let mut fields = signed_data.reader();
+        fields.expect(der::INTEGER)?;
+        fields.expect(der::SET)?;
+
+        let mut encapsulated = fields.expect(der::SEQUENCE)?.reader();
+        let content_type = encapsulated.expect(der::OID)?.oid()?;
+        let content = match encapsulated.optional(der::context(0))? {
+            Some(content) => {
+                let mut reader = content.reader();
+                let octets = reader.expect(der::OCTET_STRING)?.content.to_vec();
+                reader.finish()?;
+                Some(octets)
+            }
+            None => None,
+        };
+        encapsulated.finish()?;
+
+        let mut certificates = vec![];
+        if let Some(list) = fields.optional(der::context(0))? {
+            let mut reader = list.reader();
+            while !reader.is_empty() {
+                let choice = reader.read()?;
+                // Attribute certificates and other formats are skipped.
+                if choice.tag == der::SEQUENCE {
+                    certificates.push(Certificate::from_der(choice.raw)?);
+                }
+            }
+        }
+        fields.optional(der::context(1))?;
+
+        let mut signers = vec![];
+        let mut reader = fields.expect(der::SET)?.reader();
+        while !reader.is_empty() {
+            signers.push(SignerInfo::parse(reader.read()?)?);
+        }
+        fields.finish()?;
+
+        Ok(Self {
+            raw: bytes.to_vec(),
+            content_type,
+            content,
+            certificates,
+            signers,
+        })
+    }
+
+    /// The first `CMS` or `PKCS7` object in `text`.
+    pub fn from_pem(text: &str) -> Result<Self, Error> {
+        Self::from_der(&find_pem(text)?)
+    }
+
+    pub fn to_der(&self) -> &[u8] {
+        &self.raw
+    }
+
+    pub fn to_pem(&self) -> String {
+        Pem::new(CMS, self.raw.clone()).encode()
+    }
+
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    /// The encapsulated content; `None` for a detached signature.
+    pub fn content(&self) -> Option<&[u8]> {
+        self.content.as_deref()
+    }
+
+    pub fn certificates(&self) -> &[Certificate] {
+        &self.certificates
+    }
+
+    pub fn signers(&self) -> &[SignerInfo] {
+        &self.signers
+    }
+
+    /// Verifies every signer against the certificates carried in the
+    /// message and returns those certificates, in signer order. `detached`
+    /// is the content when it is not encapsulated. Whether the signers are
+    /// trusted is left to the caller, e.g. `TrustStore::validate`.
+    pub fn verify(&self, detached: Option<&[u8]>) -> Result<Vec<Certificate>, Error> {
+        let content = match (&self.content, detached) {
+            (Some(content), None) => content.as_slice(),
+            (None, Some(content)) => content,
+            (Some(_), Some(_)) => return Err(Error::Cms("content is both attached and detached")),
+            (None, None) => return Err(Error::Cms("no content to verify")),
+        };
+        if self.signers.is_empty() {
+            return Err(Error::Cms("no signers"));
+        }
+
+        self.signers
+            .iter()
+            .map(|signer| {
+                let certificate = self
+                    .certificates
+                    .iter()
+                    .find(|certificate| signer.matches(certificate))
+                    .ok_or(Error::Cms("signer certificate not found"))?;
+                signer.verify(&self.content_type, content, certificate)?;
+                Ok(certificate.clone())
+            })
+            .collect()
+    }
+}
+
+impl<'a> SignedDataBuilder<'a> {
+    pub fn new(content: &[u8]) -> Self {
+        Self {
+            content: content.to_vec(),
+            detached: false,
+            signing_time: None,
+            signers: vec![],
+            certificates: vec![],
+        }
+    }
+
+    /// Leaves the content out of the message.
+    pub fn detached(mut self) -> Self {
+        self.detached = true;
+        self
+    }
+
+    /// The signing-time attribute; the current time by default.
+    pub fn signing_time(mut self, time: SystemTime) -> Self {
+        self.signing_time = Some(time);
+        self
+    }
+
+    /// A signer, identified by issuer and serial number of `certificate`.
+    pub fn signer(mut self, certificate: &Certificate, key: &'a PrivateKey) -> Self {
+        self.signers.push((certificate.clone(), key));
+        self
+    }
+
+    /// An extra certificate to carry, such as an intermediate CA.
+    pub fn certificate(mut self, certificate: &Certificate) -> Self {
+        self.certificates.push(certificate.clone());
+        self
+    }
+
+    pub fn sign(&self) -> Result<SignedData, Error> {
+        if self.signers.is_empty() {
+            return Err(Error::Cms("no signers"));
+        }
+        let signing_time = self.signing_time.unwrap_or_else(SystemTime::now);
+
+        let mut digest_algorithms = vec![];
+        let mut signer_infos = vec![];
+        for (certificate, key) in &self.signers {
+            if certificate.public_key() != &key.public_key() {
+                return Err(Error::KeyMismatch);
+            }
+            let (digest, algorithm) = match key.curve().size() {
+                32 => (oid::GOST3411_12_256, oid::GOST3410_12_256),
+                _ => (oid::GOST3411_12_512, oid::GOST3410_12_512),
+            };
+            let digest = der::sequence(&[der::oid(digest)?]);
+
+            let signed = der::set_of(&[
+                attribute(oid::CONTENT_TYPE, der::oid(oid::DATA)?)?,
+                attribute(
+                    oid::MESSAGE_DIGEST,
+                    der::octet_string(&key.curve().digest(&self.content)),
+                )?,
+                attribute(oid::SIGNING_TIME, der::time(signing_time))?,
+            ]);
+            let signature = Signature::sign(&signed, key);
+
+            let mut attributes = signed;
+            attributes[0] = der::context(0);
+            signer_infos.push(der::sequence(&[
+                der::small_integer(1),
+                der::sequence(&[
+                    certificate.issuer().to_der().to_vec(),
+                    der::integer(certificate.serial()),
+                ]),
+                digest.clone(),
+                attributes,
+                der::sequence(&[der::oid(algorithm)?]),
+                der::octet_string(&signature.to_bytes(key.curve())),
+            ]));
+            if !digest_algorithms.contains(&digest) {
+                digest_algorithms.push(digest);
+            }
+        }
+
+        let mut encapsulated = vec![der::oid(oid::DATA)?];
+        if !self.detached {
+            encapsulated.push(der::encode(
+                der::context(0),
+                &der::octet_string(&self.content),
+            ));
+        }
+
+        let mut certificates = vec![];
+        for certificate in self
+            .signers
+            .iter()
+            .map(|(c, _)| c)
+            .chain(&self.certificates)
+        {
+            let certificate = certificate.to_der().to_vec();
+            if !certificates.contains(&certificate) {
+                certificates.push(certificate);
+            }
+        }
+
+        let mut certificates = der::set_of(&certificates);
+        certificates[0] = der::context(0);
+
+        let signed_data = der::sequence(&[
+            der::small_integer(1),
+            der::set_of(&digest_algorithms),
+            der::sequence(&encapsulated),
+            certificates,
+            der::set_of(&signer_infos),
+        ]);
+
+        SignedData::from_der(&der::sequence(&[
+            der::oid(oid::SIGNED_DATA)?,
+            der::encode(der::context(0), &signed_data),
+        ]))
+    }
+}
+
+/// Content encryption of RFC 9337: CTR-ACPKM with an OMAC of the plaintext
+/// in the `id-cms-mac-attr` unprotected attribute. Both keys come from
+/// KDF_TREE over the content key, seeded by the second part of the UKM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentCipher {
+    Kuznyechik,
+    Magma,
+}
+
+/// How a recipient's copy of the content key is protected. Both use an
+/// ephemeral key, VKO and KExp15; they differ only in the encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecipientKind {
+    /// `KeyTransRecipientInfo` with a `GostR3410-KeyTransport` structure.
+    KeyTransport,
+    /// `KeyAgreeRecipientInfo` with the ephemeral key as originator key.
+    KeyAgreement,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecipientInfo {
+    id: SignerId,
+    kind: RecipientKind,
+    ephemeral_key: PublicKey,
+    /// 32 bytes: the VKO UKM, the KDF_TREE seed and the KExp15 IV.
+    ukm: Vec<u8>,
+    encrypted_key: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvelopedData {
+    raw: Vec<u8>,
+    recipients: Vec<RecipientInfo>,
+    content_type: String,
+    cipher: ContentCipher,
+    ukm: Vec<u8>,
+    encrypted_content: Vec<u8>,
+    mac: Vec<u8>,
+}
+
+/// Encrypts `id-data` content under a fresh content key for every
+/// recipient added.
+#[derive(Debug, Clone)]
+pub struct EnvelopedDataBuilder {
+    content: Vec<u8>,
+    cipher: ContentCipher,
+    recipients: Vec<(Certificate, RecipientKind)>,
+}
+
+impl ContentCipher {
+    pub fn oid(self) -> &'static str {
+        match self {
+            ContentCipher::Kuznyechik => oid::KUZNYECHIK_CTR_ACPKM_OMAC,
+            ContentCipher::Magma => oid::MAGMA_CTR_ACPKM_OMAC,
+        }
+    }
+
+    pub fn from_oid(oid: &str) -> Result<Self, Error> {
+        match oid {
+            oid::KUZNYECHIK_CTR_ACPKM_OMAC => Ok(ContentCipher::Kuznyechik),
+            oid::MAGMA_CTR_ACPKM_OMAC => Ok(ContentCipher::Magma),
+            other => Err(Error::UnsupportedAlgorithm(other.to_string())),
+        }
+    }
+
+    fn wrap_oid(self) -> &'static str {
+        match self {
+            ContentCipher::Kuznyechik => oid::KUZNYECHIK_KEXP15,
+            ContentCipher::Magma => oid::MAGMA_KEXP15,
+        }
+    }
+
+    /// The length of the content encryption UKM: the CTR IV, then the
+    /// eight-byte KDF_TREE seed.
+    fn ukm_len(self) -> usize {
+        match self {
+            ContentCipher::Kuznyechik => KUZNYECHIK_BLOCK / 2 + 8,
+            ContentCipher::Magma => MAGMA_BLOCK / 2 + 8,
+        }
+    }
+
+    /// Encrypts `data` in place and returns its MAC.
+    fn seal(self, cek: &[u8], ukm: &[u8], data: &mut [u8]) -> Vec<u8> {
+        match self {
+            ContentCipher::Kuznyechik => seal(Kuznyechik::new, KUZNYECHIK_SECTION, cek, ukm, data),
+            ContentCipher::Magma => seal(Magma::new, MAGMA_SECTION, cek, ukm, data),
+        }
+    }
+
+    /// Decrypts `data` in place and checks its MAC.
+    fn open(self, cek: &[u8], ukm: &[u8], data: &mut [u8], mac: &[u8]) -> Result<(), Error> {
+        match self {
+            ContentCipher::Kuznyechik => {
+                open(Kuznyechik::new, KUZNYECHIK_SECTION, cek, ukm, data, mac)
+            }
+            ContentCipher::Magma => open(Magma::new, MAGMA_SECTION, cek, ukm, data, mac),
+        }
+    }
+
+    /// KExp15 under the MAC and encryption keys `keys`, with the IV taken
+    /// from the end of the recipient's `ukm`.
+    fn wrap(self, keys: &[u8], ukm: &[u8], cek: &[u8]) -> Result<Vec<u8>, Error> {
+        let (mac, enc) = split_keys(keys);
+        match self {
+            ContentCipher::Kuznyechik => kexp15(
+                cek,
+                Kuznyechik::new(&mac),
+                Kuznyechik::new(&enc),
+                &ukm[24..24 + KUZNYECHIK_BLOCK / 2],
+            ),
+            ContentCipher::Magma => kexp15(
+                cek,
+                Magma::new(&mac),
+                Magma::new(&enc),
+                &ukm[24..24 + MAGMA_BLOCK / 2],
+            ),
+        }
+    }
+
+    fn unwrap(self, keys: &[u8], ukm: &[u8], wrapped: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+        let (mac, enc) = split_keys(keys);
+        match self {
+            ContentCipher::Kuznyechik => kimp15(
+                wrapped,
+                Kuznyechik::new(&mac),
+                Kuznyechik::new(&enc),
+                &ukm[24..24 + KUZNYECHIK_BLOCK / 2],
+            ),
+            ContentCipher::Magma => kimp15(
+                wrapped,
+                Magma::new(&mac),
+                Magma::new(&enc),
+                &ukm[24..24 + MAGMA_BLOCK / 2],
+            ),
+        }
+    }
+}
+
+impl RecipientInfo {
+    fn parse(info: Tlv, recipients: &mut Vec<Self>) -> Result<(), Error> {
+        match info.tag {
+            der::SEQUENCE => recipients.push(Self::parse_transport(info)?),
+            tag if tag == der::context(1) => Self::parse_agreement(info, recipients)?,
+            // Password, KEK and other recipients cannot use GOST keys.
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn parse_transport(info: Tlv) -> Result<Self, Error> {
+        let mut fields = info.reader();
+        fields.expect(der::INTEGER)?;
+        let id = parse_recipient_id(fields.read()?)?;
+        parse_algorithm(fields.read()?)?;
+        let transport = fields.expect(der::OCTET_STRING)?.content;
+        fields.finish()?;
+
+        let mut reader = der::parse(transport)?.expect(der::SEQUENCE)?.reader();
+        let encrypted_key = reader.expect(der::OCTET_STRING)?.content.to_vec();
+        let ephemeral_key = reader
+            .optional(der::context(0))?
+            .ok_or(Error::Cms("missing ephemeral key"))?;
+        let ephemeral_key =
+            PublicKey::from_spki_der(&der::encode(der::SEQUENCE, ephemeral_key.content))?;
+        let ukm = reader
+            .optional(der::OCTET_STRING)?
+            .ok_or(Error::Cms("missing user keying material"))?
+            .content
+            .to_vec();
+        reader.finish()?;
+
+        Self::new(
+            id,
+            RecipientKind::KeyTransport,
+            ephemeral_key,
+            ukm,
+            encrypted_key,
+        )
+    }
+
+    /// One recipient for every encrypted key of the structure.
+    fn parse_agreement(info: Tlv, recipients: &mut Vec<Self>) -> Result<(), Error> {
+        let mut fields = info.reader();
+        if fields.expect(der::INTEGER)?.integer()? != 3.into() {
+            return Err(Error::Cms("unsupported KeyAgreeRecipientInfo version"));
+        }
+
+        let mut originator = fields.expect(der::context(0))?.reader();
+        let key = originator
+            .optional(der::context(1))?
+            .ok_or(Error::Cms("originator is not an ephemeral key"))?;
+        originator.finish()?;
+        let ephemeral_key = PublicKey::from_spki_der(&der::encode(der::SEQUENCE, key.content))?;
+
+        let mut ukm = fields
+            .optional(der::context(1))?
+            .ok_or(Error::Cms("missing user keying material"))?
+            .reader();
+        let ukm_bytes = ukm.expect(der::OCTET_STRING)?.content.to_vec();
+        ukm.finish()?;
+
+        fields.expect(der::SEQUENCE)?;
+        let mut keys = fields.expect(der::SEQUENCE)?.reader();
+        fields.finish()?;
+        while !keys.is_empty() {
+            let mut key = keys.expect(der::SEQUENCE)?.reader();
+            let rid = key.read()?;
+            let id = if rid.tag == der::context(0) {
+                let mut identifier = rid.reader();
+                let ski = identifier.expect(der::OCTET_STRING)?.content.to_vec();
+                SignerId::SubjectKeyIdentifier(ski)
+            } else {
+                parse_recipient_id(rid)?
+            };
+            let encrypted_key = key.expect(der::OCTET_STRING)?.content.to_vec();
+            key.finish()?;
+
+            recipients.push(Self::new(
+                id,
+                RecipientKind::KeyAgreement,
+                ephemeral_key.clone(),
+                ukm_bytes.clone(),
+                encrypted_key,
+            )?);
+        }
+
+        Ok(())
+    }
+
+    fn new(
+        id: SignerId,
+        kind: RecipientKind,
+        ephemeral_key: PublicKey,
+        ukm: Vec<u8>,
+        encrypted_key: Vec<u8>,
+    ) -> Result<Self, Error> {
+        if ukm.len() != 32 {
+            return Err(Error::Cms("user keying material is not 32 bytes"));
+        }
+
+        Ok(Self {
+            id,
+            kind,
+            ephemeral_key,
+            ukm,
+            encrypted_key,
+        })
+    }
+
+    pub fn id(&self) -> &SignerId {
+        &self.id
+    }
+
+    pub fn kind(&self) -> RecipientKind {
+        self.kind
+    }
+
+    fn to_der(&self, cipher: ContentCipher, certificate: &Certificate) -> Result<Vec<u8>, Error> {
+        let rid = der::sequence(&[
+            certificate.issuer().to_der().to_vec(),
+            der::integer(certificate.serial()),
+        ]);
+        let mut ephemeral_key = self.ephemeral_key.to_spki_der()?;
+        let curve = certificate.public_key().curve();
+
+        match self.kind {
+            RecipientKind::KeyTransport => {
+                ephemeral_key[0] = der::context(0);
+                let transport = der::sequence(&[
+                    der::octet_string(&self.encrypted_key),
+                    ephemeral_key,
+                    der::octet_string(&self.ukm),
+                ]);
+
+                Ok(der::sequence(&[
+                    der::small_integer(0),
+                    rid,
+                    der::sequence(&[der::oid(key_algorithm_oid(curve))?]),
+                    der::octet_string(&transport),
+                ]))
+            }
+            RecipientKind::KeyAgreement => {
+                ephemeral_key[0] = der::context(1);
+                let agreement = match curve.size() {
+                    32 => oid::AGREEMENT_GOST3410_12_256,
+                    _ => oid::AGREEMENT_GOST3410_12_512,
+                };
+
+                Ok(der::encode(
+                    der::context(1),
+                    &[
+                        der::small_integer(3),
+                        der::encode(der::context(0), &ephemeral_key),
+                        der::encode(der::context(1), &der::octet_string(&self.ukm)),
+                        der::sequence(&[
+                            der::oid(agreement)?,
+                            der::sequence(&[der::oid(cipher.wrap_oid())?]),
+                        ]),
+                        der::sequence(&[der::sequence(&[
+                            rid,
+                            der::octet_string(&self.encrypted_key),
+                        ])]),
+                    ]
+                    .concat(),
+                ))
+            }
+        }
+    }
+}
+
+impl EnvelopedData {
+    pub fn from_der(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = der::parse(bytes)?.expect(der::SEQUENCE)?.reader();
+        if reader.expect(der::OID)?.oid()? != oid::ENVELOPED_DATA {
+            return Err(Error::Cms("not an EnvelopedData"));
+        }
+        let mut wrapper = reader.expect(der::context(0))?.reader();
+        let enveloped_data = wrapper.expect(der::SEQUENCE)?;
+        wrapper.finish()?;
+        reader.finish()?;
+
+        let mut fields = enveloped_data.reader();
+        fields.expect(der::INTEGER)?;
+        fields.optional(der::context(0))?;
+
+        let mut recipients = vec![];
+        let mut reader = fields.expect(der::SET)?.reader();
+        while !reader.is_empty() {
+            RecipientInfo::parse(reader.read()?, &mut recipients)?;
+        }
+
+        let mut encrypted = fields.expect(der::SEQUENCE)?.reader();
+        let content_type = encrypted.expect(der::OID)?.oid()?;
+        let mut algorithm = encrypted.expect(der::SEQUENCE)?.reader();
+        let cipher = ContentCipher::from_oid(&algorithm.expect(der::OID)?.oid()?)?;
+        let mut parameters = algorithm.expect(der::SEQUENCE)?.reader();
+        let ukm = parameters.expect(der::OCTET_STRING)?.content.to_vec();
+        parameters.finish()?;
+        algorithm.finish()?;
+        if ukm.len() != cipher.ukm_len() {
+            return Err(Error::Cms("malformed content encryption parameters"));
+        }
+        let encrypted_content = encrypted
+            .optional(der::context_primitive(0))?
+            .ok_or(Error::Cms("detached encrypted content is not supported"))?
+            .content
+            .to_vec();
+        encrypted.finish()?;
+
+        let mut mac = None;
+        if let Some(attributes) = fields.optional(der::context(1))? {
+            let mut reader = attributes.reader();
+            while !reader.is_empty() {
+                let mut attribute = reader.expect(der::SEQUENCE)?.reader();
+                let kind = attribute.expect(der::OID)?.oid()?;
+                let mut values = attribute.expect(der::SET)?.reader();
+                attribute.finish()?;
+                if kind == oid::CMS_MAC_ATTR {
+                    mac = Some(values.expect(der::OCTET_STRING)?.content.to_vec());
+                    values.finish()?;
+                }
+            }
+        }
+        fields.finish()?;
+
+        Ok(Self {
+            raw: bytes.to_vec(),
+            recipients,
+            content_type,
+            cipher,
+            ukm,
+            encrypted_content,
+            mac: mac.ok_or(Error::Cms("missing MAC attribute"))?,
+        })
+    }
+
+    /// The first `CMS` or `PKCS7` object in `text`.
+    pub fn from_pem(text: &str) -> Result<Self, Error> {
+        Self::from_der(&find_pem(text)?)
+    }
+
+    pub fn to_der(&self) -> &[u8] {
+        &self.raw
+    }
+
+    pub fn to_pem(&self) -> String {
+        Pem::new(CMS, self.raw.clone()).encode()
+    }
+
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    pub fn cipher(&self) -> ContentCipher {
+        self.cipher
+    }
+
+    pub fn recipients(&self) -> &[RecipientInfo] {
+        &self.recipients
+    }
+
+    /// The content, decrypted by the recipient holding `certificate` and
+    /// its private `key`.
+    pub fn decrypt(
+        &self,
+        certificate: &Certificate,
+        key: &PrivateKey,
+    ) -> Result<Zeroizing<Vec<u8>>, Error> {
+        if certificate.public_key() != &key.public_key() {
+            return Err(Error::KeyMismatch);
+        }
+        let recipient = self
+            .recipients
+            .iter()
+            .find(|recipient| recipient.id.matches(certificate))
+            .ok_or(Error::Cms("not a recipient"))?;
+
+        let keys = key_encryption_keys(key, &recipient.ephemeral_key, &recipient.ukm)?;
+        let cek = self
+            .cipher
+            .unwrap(&keys, &recipient.ukm, &recipient.encrypted_key)?;
+        if cek.len() != 32 {
+            return Err(Error::Cms("content key is not 256 bits"));
+        }
+
+        let mut content = Zeroizing::new(self.encrypted_content.clone());
+        self.cipher.open(&cek, &self.ukm, &mut content, &self.mac)?;
+
+        Ok(content)
+    }
+}
+
+impl EnvelopedDataBuilder {
+    /// Kuznyechik content encryption unless `cipher` says otherwise.
+    pub fn new(content: &[u8]) -> Self {
+        Self {
+            content: content.to_vec(),
+            cipher: ContentCipher::Kuznyechik,
+            recipients: vec![],
+        }
+    }
+
+    pub fn cipher(mut self, cipher: ContentCipher) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// A recipient, identified by issuer and serial number of
+    /// `certificate`.
+    pub fn recipient(mut self, certificate: &Certificate, kind: RecipientKind) -> Self {
+        self.recipients.push((certificate.clone(), kind));
+        self
+    }
+
+    pub fn encrypt(&self) -> Result<EnvelopedData, Error> {
+        self.encrypt_with_rng(&mut rand::thread_rng())
+    }
+
+    pub fn encrypt_with_rng<R: CryptoRng + RngCore>(
+        &self,
+        rng: &mut R,
+    ) -> Result<EnvelopedData, Error> {
+        if self.recipients.is_empty() {
+            return Err(Error::Cms("no recipients"));
+        }
+
+        let mut cek = Zeroizing::new([0u8; 32]);
+        rng.fill_bytes(&mut *cek);
+        let mut ukm = vec![0u8; self.cipher.ukm_len()];
+        rng.fill_bytes(&mut ukm);
+
+        let mut recipient_infos = vec![];
+        for (certificate, kind) in &self.recipients {
+            let peer = certificate.public_key();
+            let ephemeral = PrivateKey::generate_with_rng(rng, peer.curve().clone());
+            let mut recipient_ukm = vec![0u8; 32];
+            rng.fill_bytes(&mut recipient_ukm);
+
+            let keys = key_encryption_keys(&ephemeral, peer, &recipient_ukm)?;
+            let recipient = RecipientInfo {
+                id: SignerId::IssuerAndSerial(
+                    certificate.issuer().clone(),
+                    certificate.serial().clone(),
+                ),
+                kind: *kind,
+                ephemeral_key: ephemeral.public_key(),
+                encrypted_key: self.cipher.wrap(&keys, &recipient_ukm, &*cek)?,
+                ukm: recipient_ukm,
+            };
+            recipient_infos.push(recipient.to_der(self.cipher, certificate)?);
+        }
+
+        let mut content = self.content.clone();
+        let mac = self.cipher.seal(&*cek, &ukm, &mut content);
+
+        let encrypted = der::sequence(&[
+            der::oid(oid::DATA)?,
+            der::sequence(&[
+                der::oid(self.cipher.oid())?,
+                der::sequence(&[der::octet_string(&ukm)]),
+            ]),
+            der::encode(der::context_primitive(0), &content),
+        ]);
+        let mut attributes = der::set_of(&[attribute(oid::CMS_MAC_ATTR, der::octet_string(&mac))?]);
+        attributes[0] = der::context(1);
+
+        let enveloped_data = der::sequence(&[
+            der::small_integer(2),
+            der::set_of(&recipient_infos),
+            encrypted,
+            attributes,
+        ]);
+
+        EnvelopedData::from_der(&der::sequence(&[
+            der::oid(oid::ENVELOPED_DATA)?,
+            der::encode(der::context(0), &enveloped_data),
+        ]))
+    }
+}
+
+/// The KExp15 MAC and encryption keys shared by `key` and `peer`, the KEG
+/// of R 1323565.1.020-2018: VKO-512 for 512-bit keys, otherwise KDF_TREE
+/// over VKO-256. The first 16 bytes of `ukm` are the VKO UKM and the next
+/// eight the KDF_TREE seed.
+fn key_encryption_keys(
+    key: &PrivateKey,
+    peer: &PublicKey,
+    ukm: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, Error> {
+    if key.curve().size() == 64 {
+        let keys = vko_gostr3410_2012_512(key, peer, &ukm[..16])?;
+        return Ok(Zeroizing::new(keys.to_vec()));
+    }
+
+    let kek = vko_gostr3410_2012_256(key, peer, &ukm[..16])?;
+    Ok(kdf_tree_streebog_256(
+        &*kek,
+        b"kdf tree",
+        &ukm[16..24],
+        1,
+        64,
+    ))
+}
+
+fn split_keys(keys: &[u8]) -> (Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>) {
+    (
+        Zeroizing::new(keys[..32].try_into().unwrap()),
+        Zeroizing::new(keys[32..64].try_into().unwrap()),
+    )
+}
+
+/// The encryption and MAC keys of CTR-ACPKM-OMAC.
+fn content_keys(cek: &[u8], seed: &[u8]) -> (Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>) {
+    split_keys(&kdf_tree_streebog_256(cek, b"kdf tree", seed, 1, 64))
+}
+
+fn seal<C: BlockCipher>(
+    new: fn(&[u8; 32]) -> C,
+    section: usize,
+    cek: &[u8],
+    ukm: &[u8],
+    data: &mut [u8],
+) -> Vec<u8> {
+    let half = C::BLOCK_SIZE / 2;
+    let (enc, mac) = content_keys(cek, &ukm[half..]);
+
+    let mut omac = Mac::new(new(&mac));
+    omac.update(data);
+    CtrAcpkm::new(new(&enc), &ukm[..half], section)
+        .unwrap()
+        .apply(data);
+
+    omac.finalize(C::BLOCK_SIZE).unwrap()
+}
+
+fn open<C: BlockCipher>(
+    new: fn(&[u8; 32]) -> C,
+    section: usize,
+    cek: &[u8],
+    ukm: &[u8],
+    data: &mut [u8],
+    mac: &[u8],
+) -> Result<(), Error> {
+    let half = C::BLOCK_SIZE / 2;
+    let (enc, mac_key) = content_keys(cek, &ukm[half..]);
+
+    CtrAcpkm::new(new(&enc), &ukm[..half], section)
+        .unwrap()
+        .apply(data);
+    let mut omac = Mac::new(new(&mac_key));
+    omac.update(data);
+    if let Err(error) = omac.verify(mac) {
+        data.zeroize();
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+fn parse_recipient_id(rid: Tlv) -> Result<SignerId, Error> {
+    match rid.tag {
+        der::SEQUENCE => {
+            let mut reader = rid.reader();
+            let issuer = Name::parse(reader.read()?)?;
+            let serial = reader.expect(der::INTEGER)?.integer()?;
+            reader.finish()?;
+            Ok(SignerId::IssuerAndSerial(issuer, serial))
+        }
+        0x80 => Ok(SignerId::SubjectKeyIdentifier(rid.content.to_vec())),
+        _ => Err(Error::Cms("unsupported recipient identifier")),
+    }
+}
+
+fn key_algorithm_oid(curve: &Curve) -> &'static str {
+    match curve.size() {
+        32 => oid::GOST3410_12_256,
+        _ => oid::GOST3410_12_512,
+    }
+}
+
+fn find_pem(text: &str) -> Result<Vec<u8>, Error> {
+    Pem::parse_all(text)?
+        .into_iter()
+        .find(|pem| pem.label == CMS || pem.label == PKCS7)
+        .map(|pem| pem.contents)
+        .ok_or(Error::Pem("no object with the expected label"))
+}
+
+fn attribute(kind: &str, value: Vec<u8>) -> Result<Vec<u8>, Error> {
+    Ok(der::sequence(&[der::oid(kind)?, der::set_of(&[value])]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ContentCipher, EnvelopedData, EnvelopedDataBuilder, RecipientKind, SignedData,
+        SignedDataBuilder, SignerId,
+    };
+    use crate::error::Error;
+    use crate::oid;
+    use crate::testing::{certificate, key};
+    use num_bigint_dig::BigInt;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn attached() {
+        let key = key(oid::CRYPTOPRO_A, 1);
+        let certificate = certificate("Signer", &key.public_key(), "Signer", &key, 1, &[]);
+        let time = UNIX_EPOCH + Duration::from_secs(1_750_000_000);
+
+        let signed = SignedDataBuilder::new(b"contract")
+            .signing_time(time)
+            .signer(&certificate, &key)
+            .sign()
+            .unwrap();
+        let parsed = SignedData::from_pem(&signed.to_pem()).unwrap();
+
+        assert_eq!(parsed.content_type(), oid::DATA);
+        assert_eq!(parsed.content(), Some(&b"contract"[..]));
+        assert_eq!(parsed.certificates().len(), 1);
+
+        let signer = &parsed.signers()[0];
+        assert_eq!(signer.digest_algorithm(), oid::GOST3411_12_256);
+        assert_eq!(signer.signature_algorithm(), oid::GOST3410_12_256);
+        assert_eq!(signer.signing_time(), Some(time));
+        assert_eq!(
+            signer.id(),
+            &SignerId::IssuerAndSerial(certificate.issuer().clone(), BigInt::from(1))
+        );
+
+        let signers = parsed.verify(None).unwrap();
+        assert_eq!(signers[0].to_der(), certificate.to_der());
+        assert_eq!(
+            parsed.verify(Some(b"contract")).err(),
+            Some(Error::Cms("content is both attached and detached"))
+        );
+
+        let mut tampered = signed.to_der().to_vec();
+        let content = tampered
+            .windows(8)
+            .position(|window| window == b"contract")
+            .unwrap();
+        tampered[content] ^= 1;
+        assert_eq!(
+            SignedData::from_der(&tampered).unwrap().verify(None).err(),
+            Some(Error::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn detached() {
+        let first_key = key(oid::CRYPTOPRO_A, 2);
+        let first = certificate(
+            "First",
+            &first_key.public_key(),
+            "First",
+            &first_key,
+            2,
+            &[],
+        );
+        let second_key = key(oid::TC26_512_A, 3);
+        let second = certificate(
+            "Second",
+            &second_key.public_key(),
+            "Second",
+            &second_key,
+            3,
+            &[],
+        );
+
+        let signed = SignedDataBuilder::new(b"contract")
+            .detached()
+            .signer(&first, &first_key)
+            .signer(&second, &second_key)
+            .sign()
+            .unwrap();
+        assert_eq!(signed.content(), None);
+        assert_eq!(signed.certificates().len(), 2);
+        assert_eq!(signed.signers()[1].digest_algorithm(), oid::GOST3411_12_512);
+
+        let signers = signed.verify(Some(b"contract")).unwrap();
+        assert_eq!(signers[0].subject().common_name(), Some("First"));
+        assert_eq!(signers[1].subject().common_name(), Some("Second"));
+
+        assert_eq!(
+            signed.verify(Some(b"contrast")).err(),
+            Some(Error::InvalidSignature)
+        );
+        assert_eq!(
+            signed.verify(None).err(),
+            Some(Error::Cms("no content to verify"))
+        );
+        assert_eq!(
+            SignedDataBuilder::new(b"contract")
+                .signer(&first, &second_key)
+                .sign()
+                .err(),
+            Some(Error::KeyMismatch)
+        );
+    }
+
+    #[test]
+    fn enveloped() {
+        let first_key = key(oid::CRYPTOPRO_A, 4);
+        let first = certificate(
+            "First",
+            &first_key.public_key(),
+            "First",
+            &first_key,
+            4,
+            &[],
+        );
+        let second_key = key(oid::TC26_512_A, 5);
+        let second = certificate(
+            "Second",
+            &second_key.public_key(),
+            "Second",
+            &second_key,
+            5,
+            &[],
+        );
+        let other_key = key(oid::CRYPTOPRO_A, 6);
+        let other = certificate(
+            "Other",
+            &other_key.public_key(),
+            "Other",
+            &other_key,
+            6,
+            &[],
+        );
+        let content: Vec<u8> = (0..5000).map(|i| i as u8).collect();
+
+        for (cipher, kind) in [
+            (ContentCipher::Kuznyechik, RecipientKind::KeyTransport),
+            (ContentCipher::Magma, RecipientKind::KeyAgreement),
+        ] {
+            let enveloped = EnvelopedDataBuilder::new(&content)
+                .cipher(cipher)
+                .recipient(&first, kind)
+                .recipient(&second, kind)
+                .encrypt_with_rng(&mut StdRng::seed_from_u64(7))
+                .unwrap();
+            let parsed = EnvelopedData::from_pem(&enveloped.to_pem()).unwrap();
+
+            assert_eq!(parsed.content_type(), oid::DATA);
+            assert_eq!(parsed.cipher(), cipher);
+            assert_eq!(parsed.recipients().len(), 2);
+            assert_eq!(parsed.recipients()[1].kind(), kind);
+            assert!(parsed.recipients()[1].id().matches(&second));
+
+            assert_eq!(*parsed.decrypt(&first, &first_key).unwrap(), content);
+            assert_eq!(*parsed.decrypt(&second, &second_key).unwrap(), content);
+            assert_eq!(
+                parsed.decrypt(&other, &other_key).err(),
+                Some(Error::Cms("not a recipient"))
+            );
+            assert_eq!(
+                parsed.decrypt(&first, &second_key).err(),
+                Some(Error::KeyMismatch)
+            );
+
+            let mut tampered = enveloped.to_der().to_vec();
+            let last = tampered.len() - 40;
+            tampered[last] ^= 1;
+            assert_eq!(
+                EnvelopedData::from_der(&tampered)
+                    .unwrap()
+                    .decrypt(&first, &first_key)
+                    .err(),
+                Some(Error::InvalidTag)
+            );
+        }
+
+        assert_eq!(
+            EnvelopedDataBuilder::new(b"secret").encrypt().err(),
+            Some(Error::Cms("no recipients"))
+        );
+    }
+}
@@ -0,0 +1,469 @@
+//! X.509 certificate revocation lists (RFC 5280, section 5) signed with
+//! GOST R 34.10-2012.
+
+use crate::der::{self, Reader};
+use crate::error::Error;
+use crate::key::PublicKey;
+use crate::oid;
+use crate::pem::{Pem, CRL};
+use crate::x509::{parse_algorithm, parse_extensions, verify_signed, Certificate, Extension, Name};
+use num_bigint_dig::BigInt;
+use std::path::Path;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevokedCertificate {
+    pub serial: BigInt,
+    pub date: SystemTime,
+    /// The `CRLReason` code, if the entry has one.
+    pub reason: Option<u8>,
+    /// All entry extensions, `reasonCode` included.
+    pub extensions: Vec<Extension>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Crl {
+    raw: Vec<u8>,
+    tbs: Vec<u8>,
+    signature_algorithm: String,
+    issuer: Name,
+    this_update: SystemTime,
+    next_update: Option<SystemTime>,
+    revoked: Vec<RevokedCertificate>,
+    extensions: Vec<Extension>,
+    signature: Vec<u8>,
+}
+
+impl Crl {
+    pub fn from_der(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = der::parse(bytes)?.expect(der::SEQUENCE)?.reader();
+        let tbs = reader.expect(der::SEQUENCE)?;
+        let signature_algorithm = parse_algorithm(reader.read()?)?;
+        let signature = reader.expect(der::BIT_STRING)?.bit_string()?.to_vec();
+        reader.finish()?;
+
+        let mut fields = tbs.reader();
+        if let Some(version) = fields.optional(der::INTEGER)? {
+            if version.integer()? != 1.into() {
+                return Err(Error::Asn1("unsupported CRL version"));
+            }
+        }
+        if parse_algorithm(fields.read()?)? != signature_algorithm {
+            return Err(Error::Asn1("signature algorithm mismatch"));
+        }
+        let issuer = Name::parse(fields.read()?)?;
+        let this_update = fields.read()?.time()?;
+        let next_update = match fields.peek_tag() {
+            Some(der::UTC_TIME | der::GENERALIZED_TIME) => Some(fields.read()?.time()?),
+            _ => None,
+        };
+
+        let revoked = match fields.optional(der::SEQUENCE)? {
+            Some(list) => parse_revoked(list.reader())?,
+            None => vec![],
+        };
+        let extensions = match fields.optional(der::context(0))? {
+            Some(extensions) => {
+                let mut wrapper = extensions.reader();
+                let list = parse_extensions(wrapper.expect(der::SEQUENCE)?)?;
+                wrapper.finish()?;
+                list
+            }
+            None => vec![],
+        };
+        fields.finish()?;
+
+        Ok(Self {
+            raw: bytes.to_vec(),
+            tbs: tbs.raw.to_vec(),
+            signature_algorithm,
+            issuer,
+            this_update,
+            next_update,
+            revoked,
+            extensions,
+            signature,
+        })
+    }
+
+    pub fn from_pem(text: &str) -> Result<Self, Error> {
+        Self::from_der(&Pem::find(text, CRL)?.contents)
+    }
+
+    /// Every CRL of a PEM file, or the one CRL of a DER file.
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Vec<Self>, Error> {
+        crate::pem::load_file(path.as_ref(), CRL, Self::from_der)
+    }
+
+    pub fn to_der(&self) -> &[u8] {
+        &self.raw
+    }
+
+    pub fn to_pem(&self) -> String {
+        Pem::new(CRL, self.raw.clone()).encode()
+    }
+
+    pub fn signature_algorithm(&self) -> &str {
+        &self.signature_algorithm
+    }
+
+    pub fn issuer(&self) -> &Name {
+        &self.issuer
+    }
+
+    pub fn this_update(&self) -> SystemTime {
+        self.this_update
+    }
+
+    pub fn next_update(&self) -> Option<SystemTime> {
+        self.next_update
+    }
+
+    pub fn revoked(&self) -> &[RevokedCertificate] {
+        &self.revoked
+    }
+
+    pub fn extensions(&self) -> &[Extension] {
+        &self.extensions
+    }
+
+    pub fn extension(&self, oid: &str) -> Option<&Extension> {
+        self.extensions
+            .iter()
+            .find(|extension| extension.oid == oid)
+    }
+
+    pub fn verify_signature(&self, key: &PublicKey) -> Result<(), Error> {
+        verify_signed(&self.signature_algorithm, &self.tbs, &self.signature, key)
+    }
+
+    /// Checks that `issuer` names this CRL's issuer and signed it.
+    pub fn verify_issued_by(&self, issuer: &Certificate) -> Result<(), Error> {
+        if &self.issuer != issuer.subject() {
+            return Err(Error::IssuerMismatch);
+        }
+
+        self.verify_signature(issuer.public_key())
+    }
+
+    /// The entry for `certificate`, if this CRL is from its issuer and
+    /// lists its serial number.
+    pub fn revocation(&self, certificate: &Certificate) -> Option<&RevokedCertificate> {
+        if certificate.issuer() != &self.issuer {
+            return None;
+        }
+
+        self.revoked
+            .iter()
+            .find(|entry| &entry.serial == certificate.serial())
+    }
+}
+
+fn parse_revoked(mut reader: Reader) -> Result<Vec<RevokedCertificate>, Error> {
+    let mut revoked = vec![];
+    while !reader.is_empty() {
+        let mut entry = reader.expect(der::SEQUENCE)?.reader();
+        let serial = entry.expect(der::INTEGER)?.integer()?;
+        let date = entry.read()?.time()?;
+        let extensions = match entry.optional(der::SEQUENCE)? {
+            Some(list) => parse_extensions(list)?,
+            None => vec![],
+        };
+        entry.finish()?;
+
+        let reason = match extensions
+            .iter()
+            .find(|extension| extension.oid == oid::CRL_REASON)
+        {
+            Some(extension) => match der::parse(&extension.value)? {
+                der::Tlv {
+                    tag: der::ENUMERATED,
+                    content: [code],
+                    ..
+                } => Some(*code),
+                _ => return Err(Error::Asn1("invalid CRL reason")),
+            },
+            None => None,
+        };
+
+        revoked.push(RevokedCertificate {
+            serial,
+            date,
+            reason,
+            extensions,
+        });
+    }
+
+    Ok(revoked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Crl;
+    use crate::chain::{check_revocation, Failure};
+    use crate::der;
+    use crate::error::Error;
+    use crate::key::PrivateKey;
+    use crate::oid;
+    use crate::signature::Signature;
+    use crate::testing::{at, ca, certificate, key, usage};
+    use crate::x509::{signature_algorithm, Certificate, Extension, KeyUsage};
+    use num_bigint_dig::BigInt;
+
+    fn reason(code: u8) -> Extension {
+        Extension::new(
+            oid::CRL_REASON,
+            false,
+            der::encode(der::ENUMERATED, &[code]),
+        )
+    }
+
+    /// A v2 CRL from `issuer` revoking `(serial, entry extensions)` at
+    /// `at(1_750_000_000)`.
+    fn crl(
+        issuer: &Certificate,
+        signer: &PrivateKey,
+        this_update: u64,
+        next_update: u64,
+        revoked: &[(u64, &[Extension])],
+        extensions: &[Extension],
+    ) -> Vec<u8> {
+        let entries = revoked
+            .iter()
+            .map(|(serial, extensions)| {
+                let mut fields = vec![der::small_integer(*serial), der::time(at(1_750_000_000))];
+                if !extensions.is_empty() {
+                    fields.push(der::sequence(
+                        &extensions
+                            .iter()
+                            .map(Extension::to_der)
+                            .collect::<Result<Vec<_>, _>>()
+                            .unwrap(),
+                    ));
+                }
+                der::sequence(&fields)
+            })
+            .collect::<Vec<_>>();
+
+        let algorithm = signature_algorithm(signer.curve()).unwrap();
+        let mut fields = vec![
+            der::small_integer(1),
+            algorithm.clone(),
+            issuer.subject().to_der().to_vec(),
+            der::time(at(this_update)),
+            der::time(at(next_update)),
+        ];
+        if !entries.is_empty() {
+            fields.push(der::sequence(&entries));
+        }
+        fields.push(der::encode(
+            der::context(0),
+            &der::sequence(
+                &extensions
+                    .iter()
+                    .map(Extension::to_der)
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap(),
+            ),
+        ));
+        let tbs = der::sequence(&fields);
+        let signature = Signature::sign(&tbs, signer);
+
+        der::sequence(&[
+            tbs,
+            algorithm,
+            der::bit_string(&signature.to_bytes(signer.curve())),
+        ])
+    }
+
+    #[test]
+    fn parse() {
+        let root_key = key(oid::CRYPTOPRO_A, 1);
+        let root = certificate(
+            "Root",
+            &root_key.public_key(),
+            "Root",
+            &root_key,
+            1,
+            &[
+                ca(None),
+                usage(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN),
+            ],
+        );
+        let leaf = certificate(
+            "Leaf",
+            &key(oid::CRYPTOPRO_A, 2).public_key(),
+            "Root",
+            &root_key,
+            7,
+            &[],
+        );
+        let number = Extension::new(oid::CRL_NUMBER, false, der::small_integer(3));
+
+        let der = crl(
+            &root,
+            &root_key,
+            1_750_000_000,
+            1_760_000_000,
+            &[(5, &[]), (7, &[reason(1)])],
+            std::slice::from_ref(&number),
+        );
+        let crl = Crl::from_pem(&Crl::from_der(&der).unwrap().to_pem()).unwrap();
+
+        assert_eq!(crl.issuer(), root.subject());
+        assert_eq!(
+            crl.signature_algorithm(),
+            oid::SIGNWITHDIGEST_GOST3410_12_256
+        );
+        assert_eq!(crl.this_update(), at(1_750_000_000));
+        assert_eq!(crl.next_update(), Some(at(1_760_000_000)));
+        assert_eq!(crl.extension(oid::CRL_NUMBER), Some(&number));
+        assert_eq!(crl.revoked().len(), 2);
+        assert_eq!(crl.revoked()[0].reason, None);
+
+        let entry = crl.revocation(&leaf).unwrap();
+        assert_eq!(entry.serial, BigInt::from(7));
+        assert_eq!(entry.reason, Some(1));
+        assert_eq!(entry.extensions, [reason(1)]);
+        assert!(crl.revocation(&root).is_none());
+
+        assert_eq!(crl.verify_issued_by(&root), Ok(()));
+        assert_eq!(crl.verify_issued_by(&leaf), Err(Error::IssuerMismatch));
+        assert_eq!(
+            crl.verify_signature(leaf.public_key()),
+            Err(Error::InvalidSignature)
+        );
+
+        let path = std::env::temp_dir().join(format!("crl-{}.pem", std::process::id()));
+        std::fs::write(&path, format!("{}{}", crl.to_pem(), crl.to_pem())).unwrap();
+        let loaded = Crl::load_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn revocation() {
+        let root_key = key(oid::CRYPTOPRO_A, 1);
+        let leaf_key = key(oid::CRYPTOPRO_A, 2);
+        let root = certificate(
+            "Root",
+            &root_key.public_key(),
+            "Root",
+            &root_key,
+            1,
+            &[
+                ca(None),
+                usage(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN),
+            ],
+        );
+        let leaf = certificate("Leaf", &leaf_key.public_key(), "Root", &root_key, 7, &[]);
+        let chain = [leaf.clone(), root.clone()];
+        let now = at(1_755_000_000);
+
+        let current = Crl::from_der(&crl(
+            &root,
+            &root_key,
+            1_750_000_000,
+            1_760_000_000,
+            &[(5, &[])],
+            &[],
+        ))
+        .unwrap();
+        assert_eq!(
+            check_revocation(&chain, std::slice::from_ref(&current), now),
+            Ok(())
+        );
+        assert_eq!(check_revocation(&chain[1..], &[], now), Ok(()));
+
+        let check = |crls: &[Crl]| check_revocation(&chain, crls, now).unwrap_err().failure;
+        assert_eq!(check(&[]), Failure::CrlNotFound);
+
+        let revoking = Crl::from_der(&crl(
+            &root,
+            &root_key,
+            1_752_000_000,
+            1_760_000_000,
+            &[(7, &[reason(1)])],
+            &[],
+        ))
+        .unwrap();
+        assert_eq!(
+            check(&[current.clone(), revoking.clone()]),
+            Failure::Revoked(at(1_750_000_000))
+        );
+        // Listed, but with a revocation date after the time of the check.
+        let scheduled = Crl::from_der(&crl(
+            &root,
+            &root_key,
+            1_745_000_000,
+            1_760_000_000,
+            &[(7, &[reason(1)])],
+            &[],
+        ))
+        .unwrap();
+        assert_eq!(
+            check_revocation(&chain, &[scheduled], at(1_748_000_000)),
+            Ok(())
+        );
+        let err = check_revocation(&chain, &[revoking], now).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "depth 0 (C=RU, CN=Leaf): certificate has been revoked"
+        );
+
+        let stale = Crl::from_der(&crl(
+            &root,
+            &root_key,
+            1_740_000_000,
+            1_745_000_000,
+            &[],
+            &[],
+        ))
+        .unwrap();
+        assert_eq!(check(&[stale]), Failure::CrlExpired);
+
+        // Signed by the leaf key, but naming the root.
+        let forged = Crl::from_der(&crl(
+            &root,
+            &leaf_key,
+            1_750_000_000,
+            1_760_000_000,
+            &[],
+            &[],
+        ))
+        .unwrap();
+        assert_eq!(check(&[forged]), Failure::CrlNotFound);
+
+        let delta = Extension::new("2.5.29.27", true, der::small_integer(2));
+        let delta = Crl::from_der(&crl(
+            &root,
+            &root_key,
+            1_750_000_000,
+            1_760_000_000,
+            &[],
+            &[delta],
+        ))
+        .unwrap();
+        assert_eq!(
+            check(&[delta]),
+            Failure::UnhandledCriticalExtension("2.5.29.27".to_string())
+        );
+
+        // A critical certificateIssuer entry would move the entry to
+        // another issuer, so the whole CRL is unusable.
+        let issuer = Extension::new("2.5.29.29", true, der::sequence(&[]));
+        let indirect = Crl::from_der(&crl(
+            &root,
+            &root_key,
+            1_750_000_000,
+            1_760_000_000,
+            &[(5, &[issuer])],
+            &[],
+        ))
+        .unwrap();
+        assert_eq!(
+            check(&[indirect]),
+            Failure::UnhandledCriticalExtension("2.5.29.29".to_string())
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! PKCS#10 certificate signing requests (RFC 2986) for GOST keys.
+//! Requested extensions travel in an `extensionRequest` attribute.
+
+use crate::der::{self, Tlv};
+use crate::error::Error;
+use crate::key::{PrivateKey, PublicKey};
+use crate::oid;
+use crate::pem::{Pem, CERTIFICATE_REQUEST};
+use crate::signature::Signature;
+use crate::x509::{
+    parse_algorithm, parse_extensions, signature_algorithm, verify_signed, AltName, Extension,
+    KeyUsage, Name,
+};
+
+#[derive(Debug, Clone)]
+pub struct CertificateRequest {
+    raw: Vec<u8>,
+    info: Vec<u8>,
+    subject: Name,
+    public_key: PublicKey,
+    extensions: Vec<Extension>,
+    signature_algorithm: String,
+    signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestBuilder {
+    subject: Name,
+    key_usage: Option<KeyUsage>,
+    alt_names: Vec<AltName>,
+    extensions: Vec<Extension>,
+}
+
+impl CertificateRequest {
+    pub fn from_der(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = der::parse(bytes)?.expect(der::SEQUENCE)?.reader();
+        let info = reader.expect(der::SEQUENCE)?;
+        let signature_algorithm = parse_algorithm(reader.read()?)?;
+        let signature = reader.expect(der::BIT_STRING)?.bit_string()?.to_vec();
+        reader.finish()?;
+
+        let mut fields = info.reader();
+        if fields.expect(der::INTEGER)?.integer()? != 0.into() {
+            return Err(Error::Asn1("unsupported request version"));
+        }
+        let subject = Name::parse(fields.read()?)?;
+        let public_key = PublicKey::from_spki(fields.read()?)?;
+        let extensions = parse_attributes(fields.expect(der::context(0))?)?;
+        fields.finish()?;
+
+        Ok(Self {
+            raw: bytes.to_vec(),
+            info: info.raw.to_vec(),
+            subject,
+            public_key,
+            extensions,
+            signature_algorithm,
+            signature,
+        })
+    }
+
+    pub fn from_pem(text: &str) -> Result<Self, Error> {
+        Self::from_der(&Pem::find(text, CERTIFICATE_REQUEST)?.contents)
+    }
+
+    pub fn to_der(&self) -> &[u8] {
+        &self.raw
+    }
+
+    pub fn to_pem(&self) -> String {
+        Pem::new(CERTIFICATE_REQUEST, self.raw.clone()).encode()
+    }
+
+    pub fn subject(&self) -> &Name {
+        &self.subject
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    pub fn signature_algorithm(&self) -> &str {
+        &self.signature_algorithm
+    }
+
+    /// The extensions of the `extensionRequest` attribute.
+    pub fn extensions(&self) -> &[Extension] {
+        &self.extensions
+    }
+
+    pub fn extension(&self, oid: &str) -> Option<&Extension> {
+        self.extensions
+            .iter()
+            .find(|extension| extension.oid == oid)
+    }
+
+    /// Proof of possession: the request is signed with its own key.
+    pub fn verify_signature(&self) -> Result<(), Error> {
+        verify_signed(
+            &self.signature_algorithm,
+            &self.info,
+            &self.signature,
+            &self.public_key,
+        )
+    }
+}
+
+impl RequestBuilder {
+    pub fn new(subject: Name) -> Self {
+        Self {
+            subject,
+            key_usage: None,
+            alt_names: vec![],
+            extensions: vec![],
+        }
+    }
+
+    pub fn key_usage(mut self, key_usage: KeyUsage) -> Self {
+        self.key_usage = Some(key_usage);
+        self
+    }
+
+    pub fn alt_name(mut self, name: AltName) -> Self {
+        self.alt_names.push(name);
+        self
+    }
+
+    pub fn extension(mut self, extension: Extension) -> Self {
+        self.extensions.push(extension);
+        self
+    }
+
+    pub fn sign(&self, key: &PrivateKey) -> Result<CertificateRequest, Error> {
+        let mut extensions = vec![];
+        extensions.extend(self.key_usage.map(KeyUsage::to_extension));
+        if !self.alt_names.is_empty() {
+            extensions.push(AltName::to_extension(&self.alt_names));
+        }
+        extensions.extend(self.extensions.iter().cloned());
+
+        let attributes = if extensions.is_empty() {
+            vec![]
+        } else {
+            der::sequence(&[
+                der::oid(oid::EXTENSION_REQUEST)?,
+                der::set_of(&[der::sequence(
+                    &extensions
+                        .iter()
+                        .map(Extension::to_der)
+                        .collect::<Result<Vec<_>, _>>()?,
+                )]),
+            ])
+        };
+
+        let algorithm = signature_algorithm(key.curve())?;
+        let info = der::sequence(&[
+            der::small_integer(0),
+            self.subject.to_der().to_vec(),
+            key.public_key().to_spki_der()?,
+            der::encode(der::context(0), &attributes),
+        ]);
+        let signature = Signature::sign(&info, key);
+
+        CertificateRequest::from_der(&der::sequence(&[
+            info,
+            algorithm,
+            der::bit_string(&signature.to_bytes(key.curve())),
+        ]))
+    }
+}
+
+/// The extensions requested among `attributes`; other attributes, such as
+/// a challenge password, are skipped.
+fn parse_attributes(attributes: Tlv) -> Result<Vec<Extension>, Error> {
+    let mut extensions = vec![];
+    let mut reader = attributes.reader();
+    while !reader.is_empty() {
+        let mut attribute = reader.expect(der::SEQUENCE)?.reader();
+        let kind = attribute.expect(der::OID)?.oid()?;
+        let mut values = attribute.expect(der::SET)?.reader();
+        attribute.finish()?;
+
+        if kind == oid::EXTENSION_REQUEST {
+            if !extensions.is_empty() {
+                return Err(Error::Asn1("duplicate extension request"));
+            }
+            extensions = parse_extensions(values.expect(der::SEQUENCE)?)?;
+            values.finish()?;
+        }
+    }
+
+    Ok(extensions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CertificateRequest, RequestBuilder};
+    use crate::curve::Curve;
+    use crate::error::Error;
+    use crate::key::PrivateKey;
+    use crate::oid;
+    use crate::x509::{AltName, KeyUsage, Name};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn request() {
+        let curve = Curve::from_oid(oid::CRYPTOPRO_A).unwrap();
+        let key = PrivateKey::generate_with_rng(&mut StdRng::seed_from_u64(1), curve);
+        let subject = Name::new(&[(oid::COUNTRY, "RU"), (oid::COMMON_NAME, "Иван")]).unwrap();
+
+        let request = RequestBuilder::new(subject.clone())
+            .key_usage(KeyUsage::DIGITAL_SIGNATURE | KeyUsage::NON_REPUDIATION)
+            .alt_name(AltName::Email("ivan@example.ru".to_string()))
+            .sign(&key)
+            .unwrap();
+        assert_eq!(request.verify_signature(), Ok(()));
+
+        let parsed = CertificateRequest::from_pem(&request.to_pem()).unwrap();
+        assert_eq!(parsed.subject(), &subject);
+        assert_eq!(parsed.subject().to_string(), "C=RU, CN=Иван");
+        assert_eq!(parsed.public_key(), &key.public_key());
+        assert_eq!(
+            parsed.signature_algorithm(),
+            oid::SIGNWITHDIGEST_GOST3410_12_256
+        );
+        assert_eq!(parsed.extensions().len(), 2);
+        assert_eq!(
+            AltName::parse_extension(&parsed.extension(oid::SUBJECT_ALT_NAME).unwrap().value),
+            Ok(vec![AltName::Email("ivan@example.ru".to_string())])
+        );
+
+        let bare = RequestBuilder::new(subject).sign(&key).unwrap();
+        assert!(bare.extensions().is_empty());
+        assert_eq!(bare.verify_signature(), Ok(()));
+
+        let mut tampered = request.to_der().to_vec();
+        let name = tampered
+            .windows(2)
+            .position(|window| window == b"RU")
+            .unwrap();
+        tampered[name] = b'U';
+        assert_eq!(
+            CertificateRequest::from_der(&tampered)
+                .unwrap()
+                .verify_signature(),
+            Err(Error::InvalidSignature)
+        );
+    }
+}
@@ -1,16 +1,267 @@
+use crate::oid;
+use crate::point::Point;
+use crate::Streebog;
 use num_bigint_dig::BigInt;
+use num_traits::Zero;
 
-#[derive(Clone)]
+/// Named parameter sets as `(oid, [p, a, b, q, x, y], cofactor)`, with the
+/// numbers in hex and the group order `m = cofactor * q`. The twisted
+/// Edwards sets of TC26 are given in their Weierstrass form. The aliases
+/// under the TC26 and key exchange arcs are resolved in `Curve::from_oid`.
+const NAMED_CURVES: [(&str, [&str; 6], u32); 9] = [
+    (
+        oid::GOST3410_2001_TEST,
+        [
+            "8000000000000000000000000000000000000000000000000000000000000431",
+            "7",
+            "5FBFF498AA938CE739B8E022FBAFEF40563F6E6A3472FC2A514C0CE9DAE23B7E",
+            "8000000000000000000000000000000150FE8A1892976154C59CFC193ACCF5B3",
+            "2",
+            "8E2A8A0E65147D4BD6316030E16D19C85C97F0A9CA267122B96ABBCEA7E8FC8",
+        ],
+        1,
+    ),
+    (
+        oid::CRYPTOPRO_A,
+        [
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFD97",
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFD94",
+            "A6",
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF6C611070995AD10045841B09B761B893",
+            "1",
+            "8D91E471E0989CDA27DF505A453F2B7635294F2DDF23E3B122ACC99C9E9F1E14",
+        ],
+        1,
+    ),
+    (
+        oid::CRYPTOPRO_B,
+        [
+            "8000000000000000000000000000000000000000000000000000000000000C99",
+            "8000000000000000000000000000000000000000000000000000000000000C96",
+            "3E1AF419A269A5F866A7D3C25C3DF80AE979259373FF2B182F49D4CE7E1BBC8B",
+            "800000000000000000000000000000015F700CFFF1A624E5E497161BCC8A198F",
+            "1",
+            "3FA8124359F96680B83D1C3EB2C070E5C545C9858D03ECFB744BF8D717717EFC",
+        ],
+        1,
+    ),
+    (
+        oid::CRYPTOPRO_C,
+        [
+            "9B9F605F5A858107AB1EC85E6B41C8AACF846E86789051D37998F7B9022D759B",
+            "9B9F605F5A858107AB1EC85E6B41C8AACF846E86789051D37998F7B9022D7598",
+            "805A",
+            "9B9F605F5A858107AB1EC85E6B41C8AA582CA3511EDDFB74F02F3A6598980BB9",
+            "0",
+            "41ECE55743711A8C3CBF3783CD08C0EE4D4DC440D4641A8F366E550DFDB3BB67",
+        ],
+        1,
+    ),
+    (
+        oid::TC26_256_A,
+        [
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFD97",
+            "C2173F1513981673AF4892C23035A27CE25E2013BF95AA33B22C656F277E7335",
+            "295F9BAE7428ED9CCC20E7C359A9D41A22FCCD9108E17BF7BA9337A6F8AE9513",
+            "400000000000000000000000000000000FD8CDDFC87B6635C115AF556C360C67",
+            "91E38443A5E82C0D880923425712B2BB658B9196932E02C78B2582FE742DAA28",
+            "32879423AB1A0375895786C4BB46E9565FDE0B5344766740AF268ADB32322E5C",
+        ],
+        4,
+    ),
+    (
+        oid::TC26_512_TEST,
+        [
+            "4531ACD1FE0023C7550D267B6B2FEE80922B14B2FFB90F04D4EB7C09B5D2D15D\
+             F1D852741AF4704A0458047E80E4546D35B8336FAC224DD81664BBF528BE6373",
+            "7",
+            "1CFF0806A31116DA29D8CFA54E57EB748BC5F377E49400FDD788B649ECA1AC43\
+             61834013B2AD7322480A89CA58E0CF74BC9E540C2ADD6897FAD0A3084F302ADC",
+            "4531ACD1FE0023C7550D267B6B2FEE80922B14B2FFB90F04D4EB7C09B5D2D15D\
+             A82F2D7ECB1DBAC719905C5EECC423F1D86E25EDBE23C595D644AAF187E6E6DF",
+            "24D19CC64572EE30F396BF6EBBFD7A6C5213B3B3D7057CC825F91093A68CD762\
+             FD60611262CD838DC6B60AA7EEE804E28BC849977FAC33B4B530F1B120248A9A",
+            "2BB312A43BD2CE6E0D020613C857ACDDCFBF061E91E5F2C3F32447C259F39B2C\
+             83AB156D77F1496BF7EB3351E1EE4E43DC1A18B91B24640B6DBB92CB1ADD371E",
+        ],
+        1,
+    ),
+    (
+        oid::TC26_512_A,
+        [
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF\
+             FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFDC7",
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF\
+             FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFDC4",
+            "E8C2505DEDFC86DDC1BD0B2B6667F1DA34B82574761CB0E879BD081CFD0B6265\
+             EE3CB090F30D27614CB4574010DA90DD862EF9D4EBEE4761503190785A71C760",
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF\
+             27E69532F48D89116FF22B8D4E0560609B4B38ABFAD2B85DCACDB1411F10B275",
+            "3",
+            "7503CFE87A836AE3A61B8816E25450E6CE5E1C93ACF1ABC1778064FDCBEFA921\
+             DF1626BE4FD036E93D75E6A50E3A41E98028FE5FC235F5B889A589CB5215F2A4",
+        ],
+        1,
+    ),
+    (
+        oid::TC26_512_B,
+        [
+            "8000000000000000000000000000000000000000000000000000000000000000\
+             000000000000000000000000000000000000000000000000000000000000006F",
+            "8000000000000000000000000000000000000000000000000000000000000000\
+             000000000000000000000000000000000000000000000000000000000000006C",
+            "687D1B459DC841457E3E06CF6F5E2517B97C7D614AF138BCBF85DC806C4B289F\
+             3E965D2DB1416D217F8B276FAD1AB69C50F78BEE1FA3106EFB8CCBC7C5140116",
+            "8000000000000000000000000000000000000000000000000000000000000001\
+             49A1EC142565A545ACFDB77BD9D40CFA8B996712101BEA0EC6346C54374F25BD",
+            "2",
+            "1A8F7EDA389B094C2C071E3647A8940F3C123B697578C213BE6DD9E6C8EC7335\
+             DCB228FD1EDF4A39152CBCAAF8C0398828041055F94CEEEC7E21340780FE41BD",
+        ],
+        1,
+    ),
+    (
+        oid::TC26_512_C,
+        [
+            "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF\
+             FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFDC7",
+            "DC9203E514A721875485A529D2C722FB187BC8980EB866644DE41C68E1430645\
+             46E861C0E2C9EDD92ADE71F46FCF50FF2AD97F951FDA9F2A2EB6546F39689BD3",
+            "B4C4EE28CEBC6C2C8AC12952CF37F16AC7EFB6A9F69F4B57FFDA2E4F0DE5ADE0\
+             38CBC2FFF719D2C18DE0284B8BFEF3B52B8CC7A5F5BF0A3C8D2319A5312557E1",
+            "3FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF\
+             C98CDBA46506AB004C33A9FF5147502CC8EDA9E7A769A12694623CEF47F023ED",
+            "E2E31EDFC23DE7BDEBE241CE593EF5DE2295B7A9CBAEF021D385F7074CEA043A\
+             A27272A7AE602BF2A7B9033DB9ED3610C6FB85487EAE97AAC5BC7928C1950148",
+            "F5CE40D95B5EB899ABBCCFF5911CB8577939804D6527378B8C108C3D2090FF9B\
+             E18E2D33E3021ED2EF32D85822423B6304F726AA854BAE07D0396E9A9ADDC40F",
+        ],
+        4,
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Curve {
     pub a: BigInt,
     pub b: BigInt,
     pub p: BigInt,
     pub m: BigInt,
     pub q: BigInt,
+    pub point: Point,
 }
 
 impl Curve {
-    pub fn new(a: BigInt, b: BigInt, p: BigInt, m: BigInt, q: BigInt) -> Self {
-        Self { a, b, p, m, q }
+    pub fn new(a: BigInt, b: BigInt, p: BigInt, m: BigInt, q: BigInt, point: Point) -> Self {
+        Self {
+            a,
+            b,
+            p,
+            m,
+            q,
+            point,
+        }
+    }
+
+    pub fn from_oid(oid: &str) -> Option<Self> {
+        let oid = match oid {
+            oid::TC26_256_B | oid::CRYPTOPRO_XCH_A => oid::CRYPTOPRO_A,
+            oid::TC26_256_C => oid::CRYPTOPRO_B,
+            oid::TC26_256_D | oid::CRYPTOPRO_XCH_B => oid::CRYPTOPRO_C,
+            oid => oid,
+        };
+
+        let (_, [p, a, b, q, x, y], cofactor) =
+            NAMED_CURVES.iter().find(|(name, ..)| *name == oid)?;
+        let hex = |value: &str| {
+            let digits = value.split_whitespace().collect::<String>();
+            BigInt::parse_bytes(digits.as_bytes(), 16).unwrap()
+        };
+
+        Some(Self::new(
+            hex(a),
+            hex(b),
+            hex(p),
+            hex(q) * cofactor,
+            hex(q),
+            Point::new(hex(x), hex(y)),
+        ))
+    }
+
+    /// The OID of the named parameter set equal to this curve. Aliased sets
+    /// report the CryptoPro identifier.
+    pub fn oid(&self) -> Option<&'static str> {
+        NAMED_CURVES
+            .iter()
+            .map(|(name, ..)| *name)
+            .find(|name| Self::from_oid(name).as_ref() == Some(self))
+    }
+
+    /// Length in bytes of a coordinate or a scalar: 32 or 64.
+    pub fn size(&self) -> usize {
+        self.p.bits().div_ceil(8)
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        if point.x < BigInt::zero()
+            || point.x >= self.p
+            || point.y < BigInt::zero()
+            || point.y >= self.p
+        {
+            return false;
+        }
+
+        let left = &point.y * &point.y % &self.p;
+        let right = (&point.x * &point.x * &point.x + &self.a * &point.x + &self.b) % &self.p;
+
+        left == right
+    }
+
+    /// Streebog-256 for curves with a 256-bit subgroup order, Streebog-512
+    /// otherwise, as section 6.1 of the standard prescribes.
+    pub fn hasher(&self) -> Streebog {
+        if self.q.bits() > 256 {
+            Streebog::new_512()
+        } else {
+            Streebog::new_256()
+        }
+    }
+
+    /// The message digest signatures on this curve are computed over.
+    pub fn digest(&self, message: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(message);
+        hasher.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Curve, NAMED_CURVES};
+    use crate::oid;
+    use num_bigint_dig::BigInt;
+
+    #[test]
+    fn named_curves() {
+        for (name, _, cofactor) in NAMED_CURVES {
+            let curve = Curve::from_oid(name).unwrap();
+            assert!(curve.contains(&curve.point), "{}", name);
+            assert!(
+                curve
+                    .point
+                    .clone()
+                    .multiply(curve.q.clone(), &curve.p, &curve.a)
+                    .is_identity(),
+                "{}",
+                name
+            );
+            assert_eq!(&curve.m / &curve.q, BigInt::from(cofactor), "{}", name);
+            assert_eq!(curve.oid(), Some(name));
+        }
+
+        assert_eq!(
+            Curve::from_oid(oid::TC26_256_B),
+            Curve::from_oid(oid::CRYPTOPRO_A)
+        );
+        assert_eq!(Curve::from_oid("1.2.3"), None);
     }
 }
@@ -0,0 +1,563 @@
+//! The subset of DER needed for keys, certificates and CMS: definite
+//! lengths, single-byte tags, and OIDs kept as dotted strings.
+
+use crate::error::Error;
+use num_bigint_dig::{BigInt, Sign};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const BOOLEAN: u8 = 0x01;
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const NULL: u8 = 0x05;
+pub const OID: u8 = 0x06;
+pub const ENUMERATED: u8 = 0x0a;
+pub const UTF8_STRING: u8 = 0x0c;
+pub const NUMERIC_STRING: u8 = 0x12;
+pub const PRINTABLE_STRING: u8 = 0x13;
+pub const IA5_STRING: u8 = 0x16;
+pub const UTC_TIME: u8 = 0x17;
+pub const GENERALIZED_TIME: u8 = 0x18;
+pub const SEQUENCE: u8 = 0x30;
+pub const SET: u8 = 0x31;
+
+pub fn context(number: u8) -> u8 {
+    0xa0 | number
+}
+
+pub fn context_primitive(number: u8) -> u8 {
+    0x80 | number
+}
+
+pub fn encode(tag: u8, content: &[u8]) -> Vec<u8> {
+    // Sized up front so secret content is never left behind by a regrowth.
+    let mut output = Vec::with_capacity(content.len() + 10);
+    output.push(tag);
+    let len = content.len();
+    if len < 0x80 {
+        output.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        output.push(0x80 | (bytes.len() - skip) as u8);
+        output.extend_from_slice(&bytes[skip..]);
+    }
+    output.extend_from_slice(content);
+
+    output
+}
+
+pub fn sequence(items: &[Vec<u8>]) -> Vec<u8> {
+    encode(SEQUENCE, &items.concat())
+}
+
+/// SET OF with its elements sorted as DER requires.
+pub fn set_of(items: &[Vec<u8>]) -> Vec<u8> {
+    let mut items = items.to_vec();
+    items.sort();
+
+    encode(SET, &items.concat())
+}
+
+pub fn integer(value: &BigInt) -> Vec<u8> {
+    encode(INTEGER, &value.to_signed_bytes_be())
+}
+
+pub fn small_integer(value: u64) -> Vec<u8> {
+    integer(&BigInt::from(value))
+}
+
+pub fn boolean(value: bool) -> Vec<u8> {
+    encode(BOOLEAN, &[if value { 0xff } else { 0x00 }])
+}
+
+pub fn null() -> Vec<u8> {
+    encode(NULL, &[])
+}
+
+pub fn octet_string(value: &[u8]) -> Vec<u8> {
+    encode(OCTET_STRING, value)
+}
+
+pub fn bit_string(value: &[u8]) -> Vec<u8> {
+    encode(BIT_STRING, &[&[0u8][..], value].concat())
+}
+
+/// A named bit list such as `KeyUsage`, bit `i` of `flags` being bit `i`
+/// of the string, with the trailing zero bits dropped.
+pub fn named_bits(flags: u16) -> Vec<u8> {
+    if flags == 0 {
+        return encode(BIT_STRING, &[0]);
+    }
+
+    let last = 15 - flags.leading_zeros() as usize;
+    let bytes = flags.reverse_bits().to_be_bytes();
+    let len = last / 8 + 1;
+
+    encode(
+        BIT_STRING,
+        &[&[(7 - last % 8) as u8][..], &bytes[..len]].concat(),
+    )
+}
+
+/// UTCTime up to 2049 and GeneralizedTime from 2050, as RFC 5280 requires.
+pub fn time(value: SystemTime) -> Vec<u8> {
+    let seconds = match value.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs_f64().ceil() as i64),
+    };
+    let days = seconds.div_euclid(86400);
+    let rest = seconds.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+
+    let text = format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest / 60 % 60,
+        rest % 60
+    );
+    if (1950..2050).contains(&year) {
+        encode(UTC_TIME, &text.as_bytes()[2..])
+    } else {
+        encode(GENERALIZED_TIME, text.as_bytes())
+    }
+}
+
+/// Dotted decimal to an OBJECT IDENTIFIER, rejecting strings that do not
+/// name one: at least two arcs of decimal digits, the first 0, 1 or 2 and
+/// the second below 40 under 0 and 1.
+pub fn oid(value: &str) -> Result<Vec<u8>, Error> {
+    let arcs = value
+        .split('.')
+        .map(|arc| match arc.bytes().all(|byte| byte.is_ascii_digit()) {
+            true => arc.parse::<u64>().ok(),
+            false => None,
+        })
+        .collect::<Option<Vec<u64>>>()
+        .ok_or(Error::Asn1("invalid OBJECT IDENTIFIER"))?;
+
+    let first = match arcs[..] {
+        [root @ 0..=1, second @ 0..=39, ..] => root * 40 + second,
+        [2, second, ..] => second
+            .checked_add(80)
+            .ok_or(Error::Asn1("OBJECT IDENTIFIER arc too large"))?,
+        _ => return Err(Error::Asn1("invalid OBJECT IDENTIFIER")),
+    };
+
+    let mut content = vec![];
+    for arc in std::iter::once(first).chain(arcs[2..].iter().copied()) {
+        let mut groups = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            groups.push(0x80 | (rest & 0x7f) as u8);
+            rest >>= 7;
+        }
+        groups.reverse();
+        content.extend(groups);
+    }
+
+    Ok(encode(OID, &content))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tlv<'a> {
+    pub tag: u8,
+    pub content: &'a [u8],
+    /// The whole encoding, header included, e.g. for hashing a TBS part.
+    pub raw: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    pub fn reader(&self) -> Reader<'a> {
+        Reader::new(self.content)
+    }
+
+    pub fn expect(self, tag: u8) -> Result<Self, Error> {
+        if self.tag != tag {
+            return Err(Error::Asn1("unexpected tag"));
+        }
+
+        Ok(self)
+    }
+
+    pub fn integer(&self) -> Result<BigInt, Error> {
+        if self.tag != INTEGER || self.content.is_empty() {
+            return Err(Error::Asn1("expected INTEGER"));
+        }
+
+        Ok(BigInt::from_signed_bytes_be(self.content))
+    }
+
+    pub fn unsigned(&self) -> Result<BigInt, Error> {
+        let value = self.integer()?;
+        if value.sign() == Sign::Minus {
+            return Err(Error::Asn1("negative INTEGER"));
+        }
+
+        Ok(value)
+    }
+
+    pub fn boolean(&self) -> Result<bool, Error> {
+        match (self.tag, self.content) {
+            (BOOLEAN, [0x00]) => Ok(false),
+            (BOOLEAN, [0xff]) => Ok(true),
+            _ => Err(Error::Asn1("expected BOOLEAN")),
+        }
+    }
+
+    pub fn octet_string(&self) -> Result<&'a [u8], Error> {
+        if self.tag != OCTET_STRING {
+            return Err(Error::Asn1("expected OCTET STRING"));
+        }
+
+        Ok(self.content)
+    }
+
+    /// The content of a BIT STRING without unused bits.
+    pub fn bit_string(&self) -> Result<&'a [u8], Error> {
+        match (self.tag, self.content.split_first()) {
+            (BIT_STRING, Some((0, rest))) => Ok(rest),
+            _ => Err(Error::Asn1("expected BIT STRING")),
+        }
+    }
+
+    pub fn named_bits(&self) -> Result<u16, Error> {
+        match (self.tag, self.content.split_first()) {
+            (BIT_STRING, Some((&unused, bytes))) if unused < 8 && bytes.len() <= 2 => {
+                if bytes.is_empty() && unused != 0 {
+                    return Err(Error::Asn1("invalid BIT STRING"));
+                }
+                let mut padded = [0u8; 2];
+                padded[..bytes.len()].copy_from_slice(bytes);
+                Ok(u16::from_be_bytes(padded).reverse_bits())
+            }
+            _ => Err(Error::Asn1("expected a named bit list")),
+        }
+    }
+
+    pub fn oid(&self) -> Result<String, Error> {
+        if self.tag != OID || self.content.is_empty() {
+            return Err(Error::Asn1("expected OBJECT IDENTIFIER"));
+        }
+
+        let mut arcs = vec![];
+        let mut value = 0u64;
+        for byte in self.content {
+            if value > u64::MAX >> 7 {
+                return Err(Error::Asn1("OBJECT IDENTIFIER arc too large"));
+            }
+            value = (value << 7) | (byte & 0x7f) as u64;
+            if byte & 0x80 == 0 {
+                arcs.push(value);
+                value = 0;
+            }
+        }
+        if self.content[self.content.len() - 1] & 0x80 != 0 {
+            return Err(Error::Asn1("truncated OBJECT IDENTIFIER"));
+        }
+
+        let first = arcs[0];
+        let (root, second) = match first {
+            0..=39 => (0, first),
+            40..=79 => (1, first - 40),
+            _ => (2, first - 80),
+        };
+
+        let mut output = format!("{}.{}", root, second);
+        for arc in &arcs[1..] {
+            output.push_str(&format!(".{}", arc));
+        }
+
+        Ok(output)
+    }
+
+    /// Text of the string types used in names.
+    pub fn string(&self) -> Result<String, Error> {
+        match self.tag {
+            UTF8_STRING | NUMERIC_STRING | PRINTABLE_STRING | IA5_STRING => {
+                String::from_utf8(self.content.to_vec()).map_err(|_| Error::Asn1("invalid string"))
+            }
+            _ => Err(Error::Asn1("expected a string")),
+        }
+    }
+
+    /// UTCTime (years 1950 to 2049) or GeneralizedTime, in UTC with whole
+    /// seconds as DER requires.
+    pub fn time(&self) -> Result<SystemTime, Error> {
+        let text = std::str::from_utf8(self.content).map_err(|_| Error::Asn1("invalid time"))?;
+        // Only ASCII digits make the byte offsets below char boundaries.
+        let digits = text
+            .strip_suffix('Z')
+            .filter(|digits| digits.bytes().all(|c| c.is_ascii_digit()))
+            .ok_or(Error::Asn1("invalid time"))?;
+        let digits = match (self.tag, digits.len()) {
+            (UTC_TIME, 12) => {
+                let prefix = if &digits[..2] < "50" { "20" } else { "19" };
+                format!("{}{}", prefix, digits)
+            }
+            (GENERALIZED_TIME, 14) => digits.to_string(),
+            _ => return Err(Error::Asn1("invalid time")),
+        };
+
+        let field = |range: std::ops::Range<usize>| digits[range].parse::<i64>().unwrap();
+        let (year, month, day) = (field(0..4), field(4..6), field(6..8));
+        let (hour, minute, second) = (field(8..10), field(10..12), field(12..14));
+        if !(1..=12).contains(&month)
+            || !(1..=days_in_month(year, month)).contains(&day)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return Err(Error::Asn1("invalid time"));
+        }
+
+        let seconds =
+            days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+        let offset = Duration::from_secs(seconds.unsigned_abs());
+
+        Ok(if seconds >= 0 {
+            UNIX_EPOCH + offset
+        } else {
+            UNIX_EPOCH - offset
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    pub fn read(&mut self) -> Result<Tlv<'a>, Error> {
+        let data = self.data;
+        let (&tag, rest) = data.split_first().ok_or(Error::Asn1("unexpected end"))?;
+        if tag & 0x1f == 0x1f {
+            return Err(Error::Asn1("multi-byte tags are not supported"));
+        }
+
+        let (&first, rest) = rest.split_first().ok_or(Error::Asn1("unexpected end"))?;
+        let (len, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > std::mem::size_of::<usize>() || rest.len() < count {
+                return Err(Error::Asn1("invalid length"));
+            }
+            let len = rest[..count]
+                .iter()
+                .fold(0usize, |len, byte| (len << 8) | *byte as usize);
+            if len < 0x80 || rest[0] == 0 {
+                return Err(Error::Asn1("non-minimal length"));
+            }
+            (len, &rest[count..])
+        };
+
+        if rest.len() < len {
+            return Err(Error::Asn1("unexpected end"));
+        }
+
+        let header = data.len() - rest.len();
+        let tlv = Tlv {
+            tag,
+            content: &rest[..len],
+            raw: &data[..header + len],
+        };
+        self.data = &rest[len..];
+
+        Ok(tlv)
+    }
+
+    pub fn expect(&mut self, tag: u8) -> Result<Tlv<'a>, Error> {
+        self.read()?.expect(tag)
+    }
+
+    pub fn optional(&mut self, tag: u8) -> Result<Option<Tlv<'a>>, Error> {
+        if self.peek_tag() == Some(tag) {
+            Ok(Some(self.read()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn finish(&self) -> Result<(), Error> {
+        if !self.data.is_empty() {
+            return Err(Error::Asn1("trailing data"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted + 2) / 5 + 1;
+    let month = if shifted < 10 {
+        shifted + 3
+    } else {
+        shifted - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parses exactly one element spanning the whole input.
+pub fn parse(data: &[u8]) -> Result<Tlv<'_>, Error> {
+    let mut reader = Reader::new(data);
+    let tlv = reader.read()?;
+    reader.finish()?;
+
+    Ok(tlv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ByteParse;
+
+    #[test]
+    fn round_trip() {
+        let encoded = oid("1.2.643.7.1.1.1.1").unwrap();
+        assert_eq!(encoded, "06082a85030701010101".parse_bytes());
+        assert_eq!(parse(&encoded).unwrap().oid().unwrap(), "1.2.643.7.1.1.1.1");
+        assert_eq!(
+            parse(&oid("2.5.29.35").unwrap()).unwrap().oid().unwrap(),
+            "2.5.29.35"
+        );
+
+        assert_eq!(small_integer(0), [0x02, 0x01, 0x00]);
+        assert_eq!(small_integer(128), [0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(
+            parse(&integer(&BigInt::from(-129))).unwrap().integer(),
+            Ok(BigInt::from(-129))
+        );
+
+        let long = octet_string(&[7u8; 300]);
+        assert_eq!(&long[..4], &[0x04, 0x82, 0x01, 0x2c]);
+        assert_eq!(parse(&long).unwrap().octet_string().unwrap(), &[7u8; 300]);
+
+        let sequence = sequence(&[null(), boolean(true), bit_string(&[1, 2])]);
+        let mut reader = parse(&sequence).unwrap().reader();
+        assert_eq!(reader.expect(NULL).unwrap().content, &[] as &[u8]);
+        assert_eq!(reader.read().unwrap().boolean(), Ok(true));
+        assert_eq!(reader.optional(OID), Ok(None));
+        assert_eq!(reader.read().unwrap().bit_string().unwrap(), &[1, 2]);
+        assert!(reader.finish().is_ok());
+
+        assert_eq!(set_of(&[oid("2.5.4.6").unwrap(), null()])[2], NULL);
+
+        // digitalSignature and keyCertSign; then decipherOnly in bit 8.
+        assert_eq!(named_bits(0b10_0001), [0x03, 0x02, 0x02, 0x84]);
+        assert_eq!(named_bits(1 << 8), [0x03, 0x03, 0x07, 0x00, 0x80]);
+        assert_eq!(named_bits(0), [0x03, 0x01, 0x00]);
+        for flags in [0, 1, 0b10_0001, 1 << 8, 0x1ff] {
+            assert_eq!(parse(&named_bits(flags)).unwrap().named_bits(), Ok(flags));
+        }
+    }
+
+    #[test]
+    fn times() {
+        let at = |seconds: u64| UNIX_EPOCH + Duration::from_secs(seconds);
+
+        assert_eq!(time(at(0)), encode(UTC_TIME, b"700101000000Z"));
+        assert_eq!(time(at(951_782_400)), encode(UTC_TIME, b"000229000000Z"));
+        assert_eq!(
+            time(at(2_524_608_000)),
+            encode(GENERALIZED_TIME, b"20500101000000Z")
+        );
+
+        for seconds in [0, 951_782_400, 1_700_000_000, 2_524_607_999, 4_102_444_800] {
+            assert_eq!(parse(&time(at(seconds))).unwrap().time(), Ok(at(seconds)));
+        }
+        assert_eq!(
+            parse(&encode(UTC_TIME, b"491231235959Z")).unwrap().time(),
+            Ok(at(2_524_607_999))
+        );
+
+        assert!(parse(&encode(UTC_TIME, b"010229000000Z"))
+            .unwrap()
+            .time()
+            .is_err());
+        assert!(parse(&encode(UTC_TIME, b"700101000000"))
+            .unwrap()
+            .time()
+            .is_err());
+        assert!(parse(&encode(GENERALIZED_TIME, b"700101000000Z"))
+            .unwrap()
+            .time()
+            .is_err());
+        assert!(parse(&encode(UTC_TIME, "0\u{e9}000000000Z".as_bytes()))
+            .unwrap()
+            .time()
+            .is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        for value in [
+            "",
+            "1",
+            "1.",
+            "1..2",
+            "1.2.x",
+            "1.+2",
+            "3.1",
+            "1.40",
+            "2.18446744073709551600",
+        ] {
+            assert!(oid(value).is_err(), "{}", value);
+        }
+        assert_eq!(
+            parse(&oid("2.999").unwrap()).unwrap().oid().unwrap(),
+            "2.999"
+        );
+
+        assert!(parse(&[0x04, 0x02, 0x00]).is_err());
+        assert!(parse(&[0x04, 0x81, 0x01, 0x00]).is_err());
+        assert!(parse(&[0x04, 0x80]).is_err());
+        assert!(parse(&[0x05, 0x00, 0x00]).is_err());
+        assert!(parse(&[0x06, 0x01, 0x85]).unwrap().oid().is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! A hash DRBG over Streebog-512 in the manner of R 1323565.1.006-2017,
+//! which follows the Hash_DRBG construction of NIST SP 800-90A: the state
+//! is a value `V` and a constant `C` of `seedlen` bits, output blocks are
+//! `Hash(V + i)` and every request updates `V` with `Hash(0x03 || V)`, `C`
+//! and the request counter.
+
+use crate::streebog_512;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use zeroize::{Zeroize, Zeroizing};
+
+/// `seedlen` for a 512-bit hash: 888 bits.
+const SEED_LEN: usize = 111;
+/// Entropy drawn for instantiation and every reseed.
+const ENTROPY_LEN: usize = 64;
+const NONCE_LEN: usize = 32;
+/// Bytes produced by one generate request; longer fills take several.
+const MAX_REQUEST: usize = 1 << 16;
+/// Requests between automatic reseeds.
+const RESEED_INTERVAL: u64 = 1 << 48;
+
+/// Implements `RngCore`, and `CryptoRng` over a cryptographic entropy
+/// source, so it can stand in for `thread_rng` in the `*_with_rng`
+/// functions.
+pub struct StreebogDrbg<E = OsRng> {
+    entropy: E,
+    v: [u8; SEED_LEN],
+    c: [u8; SEED_LEN],
+    reseed_counter: u64,
+    prediction_resistance: bool,
+}
+
+impl StreebogDrbg {
+    /// Seeded from the operating system.
+    pub fn new(personalization: &[u8]) -> Self {
+        Self::with_entropy(OsRng, personalization)
+    }
+}
+
+impl<E: RngCore> StreebogDrbg<E> {
+    /// Seeded from `entropy`, which is also drawn on for every reseed.
+    /// `personalization` distinguishes instances seeded alike.
+    pub fn with_entropy(mut entropy: E, personalization: &[u8]) -> Self {
+        let mut seed = Zeroizing::new(vec![0u8; ENTROPY_LEN + NONCE_LEN]);
+        entropy.fill_bytes(&mut seed);
+        seed.extend_from_slice(personalization);
+
+        let mut drbg = Self {
+            entropy,
+            v: [0; SEED_LEN],
+            c: [0; SEED_LEN],
+            reseed_counter: 1,
+            prediction_resistance: false,
+        };
+        drbg.seed(&seed);
+
+        drbg
+    }
+
+    /// Reseeds before every request, so that a compromise of the state
+    /// does not reveal later output.
+    pub fn prediction_resistance(mut self, enabled: bool) -> Self {
+        self.prediction_resistance = enabled;
+        self
+    }
+
+    /// Mixes fresh entropy and `additional` input into the state.
+    pub fn reseed(&mut self, additional: &[u8]) {
+        let mut seed = Zeroizing::new(vec![0x01]);
+        seed.extend_from_slice(&self.v);
+        let start = seed.len();
+        seed.resize(start + ENTROPY_LEN, 0);
+        self.entropy.fill_bytes(&mut seed[start..]);
+        seed.extend_from_slice(additional);
+
+        self.seed(&seed);
+    }
+
+    /// Fills `output`, with `additional` input mixed into the first
+    /// request.
+    pub fn generate(&mut self, output: &mut [u8], mut additional: &[u8]) {
+        for chunk in output.chunks_mut(MAX_REQUEST) {
+            self.request(chunk, additional);
+            additional = &[];
+        }
+    }
+
+    fn seed(&mut self, seed: &[u8]) {
+        self.v.zeroize();
+        hash_df(seed, &mut self.v);
+        let input = Zeroizing::new([&[0x00][..], &self.v].concat());
+        hash_df(&input, &mut self.c);
+        self.reseed_counter = 1;
+    }
+
+    fn request(&mut self, output: &mut [u8], mut additional: &[u8]) {
+        if self.prediction_resistance || self.reseed_counter > RESEED_INTERVAL {
+            self.reseed(additional);
+            additional = &[];
+        }
+
+        if !additional.is_empty() {
+            let input = Zeroizing::new([&[0x02][..], &self.v, additional].concat());
+            add(&mut self.v, &streebog_512(&input));
+        }
+
+        let mut data = Zeroizing::new(self.v);
+        for chunk in output.chunks_mut(64) {
+            let block = Zeroizing::new(streebog_512(&*data));
+            chunk.copy_from_slice(&block[..chunk.len()]);
+            add(&mut *data, &[1]);
+        }
+
+        let input = Zeroizing::new([&[0x03][..], &self.v].concat());
+        let h = streebog_512(&input);
+        add(&mut self.v, &h);
+        let c = self.c;
+        add(&mut self.v, &c);
+        add(&mut self.v, &self.reseed_counter.to_be_bytes());
+        self.reseed_counter += 1;
+    }
+}
+
+impl<E: RngCore> RngCore for StreebogDrbg<E> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.generate(dest, &[]);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<E: CryptoRng + RngCore> CryptoRng for StreebogDrbg<E> {}
+
+impl<E> Drop for StreebogDrbg<E> {
+    fn drop(&mut self) {
+        self.v.zeroize();
+        self.c.zeroize();
+    }
+}
+
+/// Hash_df: `Hash(i || len || input)` for `i = 1, 2, ..` with `len` the
+/// output length in bits, truncated to `output`.
+fn hash_df(input: &[u8], output: &mut [u8]) {
+    let bits = (output.len() as u32 * 8).to_be_bytes();
+    for (counter, chunk) in output.chunks_mut(64).enumerate() {
+        let data = Zeroizing::new([&[counter as u8 + 1][..], &bits, input].concat());
+        let block = Zeroizing::new(streebog_512(&data));
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+}
+
+/// `value = (value + other) mod 2^(8 * value.len())`, both big-endian.
+fn add(value: &mut [u8], other: &[u8]) {
+    let mut carry = 0u16;
+    let mut other = other.iter().rev();
+    for byte in value.iter_mut().rev() {
+        let sum = u16::from(*byte) + u16::from(*other.next().unwrap_or(&0)) + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add, hash_df, StreebogDrbg, SEED_LEN};
+    use crate::curve::Curve;
+    use crate::key::PrivateKey;
+    use crate::oid;
+    use crate::streebog_512;
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+
+    fn drbg(personalization: &[u8]) -> StreebogDrbg<StdRng> {
+        StreebogDrbg::with_entropy(StdRng::seed_from_u64(1), personalization)
+    }
+
+    /// The first output block is `Hash(V)` for `V` from Hash_df over the
+    /// entropy input, nonce and personalization string.
+    #[test]
+    fn instantiate() {
+        let mut seed = vec![0u8; 96];
+        StdRng::seed_from_u64(1).fill_bytes(&mut seed);
+        seed.extend_from_slice(b"device 1");
+        let mut v = [0u8; SEED_LEN];
+        hash_df(&seed, &mut v);
+
+        let mut output = [0u8; 80];
+        drbg(b"device 1").fill_bytes(&mut output);
+        assert_eq!(output[..64], streebog_512(&v));
+        add(&mut v, &[1]);
+        assert_eq!(output[64..], streebog_512(&v)[..16]);
+
+        let mut other = [0u8; 80];
+        drbg(b"device 2").fill_bytes(&mut other);
+        assert_ne!(output, other);
+    }
+
+    #[test]
+    fn reseed() {
+        let mut first = drbg(b"");
+        let mut second = drbg(b"");
+        assert_eq!(first.next_u64(), second.next_u64());
+
+        second.reseed(b"");
+        assert_ne!(first.next_u64(), second.next_u64());
+
+        // Prediction resistance reseeds on every request, additional
+        // input changes the request it is given to.
+        let mut resistant = drbg(b"").prediction_resistance(true);
+        let mut plain = drbg(b"");
+        assert_ne!(resistant.next_u64(), plain.next_u64());
+
+        let (mut a, mut b) = ([0u8; 32], [0u8; 32]);
+        drbg(b"").generate(&mut a, b"request");
+        drbg(b"").generate(&mut b, b"");
+        assert_ne!(a, b);
+
+        let mut carry = [0x00, 0xff, 0xff];
+        add(&mut carry, &[0x01]);
+        assert_eq!(carry, [0x01, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn key_generation() {
+        let curve = Curve::from_oid(oid::CRYPTOPRO_A).unwrap();
+        let first = PrivateKey::generate_with_rng(&mut drbg(b"keys"), curve.clone());
+        let second = PrivateKey::generate_with_rng(&mut drbg(b"keys"), curve.clone());
+        assert_eq!(first.public_key(), second.public_key());
+
+        let mut long = vec![0u8; (1 << 16) + 32];
+        StreebogDrbg::new(b"").fill_bytes(&mut long);
+        assert_ne!(long[..32], long[1 << 16..(1 << 16) + 32]);
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    InvalidHex,
+    InvalidLength { expected: usize, actual: usize },
+    Unaligned { block: usize, actual: usize },
+    InvalidSegment { block: usize, actual: usize },
+    InvalidTagLength { min: usize, max: usize, len: usize },
+    InvalidPadding,
+    MessageTooLong,
+    ScalarOutOfRange,
+    PointNotOnCurve,
+    PointNotInSubgroup,
+    Asn1(&'static str),
+    Pem(&'static str),
+    Cms(&'static str),
+    UnknownCurve(String),
+    UnsupportedAlgorithm(String),
+    WrongPassword,
+    InvalidSignature,
+    InvalidTag,
+    InvalidNonce,
+    IssuerMismatch,
+    KeyMismatch,
+    CurveMismatch,
+    Io(String),
+    File { path: String, reason: Box<Error> },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidHex => write!(f, "invalid hex string"),
+            Error::InvalidLength { expected, actual } => {
+                write!(f, "expected {} bytes, got {}", expected, actual)
+            }
+            Error::Unaligned { block, actual } => {
+                write!(
+                    f,
+                    "expected whole {}-byte blocks, got {} bytes",
+                    block, actual
+                )
+            }
+            Error::InvalidSegment { block, actual } => {
+                write!(f, "segment of {} bytes is not in 1..={}", actual, block)
+            }
+            Error::InvalidTagLength { min, max, len } => {
+                write!(f, "tag of {} bytes is not in {}..={}", len, min, max)
+            }
+            Error::InvalidPadding => write!(f, "invalid padding"),
+            Error::MessageTooLong => write!(f, "message exceeds the length limit of the mode"),
+            Error::ScalarOutOfRange => write!(f, "private key is not in [1, q)"),
+            Error::PointNotOnCurve => write!(f, "public key is not on the curve"),
+            Error::PointNotInSubgroup => write!(f, "public key is not in the subgroup of order q"),
+            Error::Asn1(reason) => write!(f, "malformed DER: {}", reason),
+            Error::Pem(reason) => write!(f, "malformed PEM: {}", reason),
+            Error::Cms(reason) => write!(f, "CMS: {}", reason),
+            Error::UnknownCurve(oid) => write!(f, "unknown parameter set {}", oid),
+            Error::UnsupportedAlgorithm(oid) => write!(f, "unsupported algorithm {}", oid),
+            Error::WrongPassword => write!(f, "wrong password"),
+            Error::InvalidSignature => write!(f, "signature verification failed"),
+            Error::InvalidTag => write!(f, "authentication tag mismatch"),
+            Error::InvalidNonce => write!(f, "nonce must have its most significant bit clear"),
+            Error::IssuerMismatch => write!(f, "issuer name does not match the issuer's subject"),
+            Error::KeyMismatch => write!(f, "private key does not match the public key"),
+            Error::CurveMismatch => write!(f, "keys are on different curves"),
+            Error::Io(reason) => write!(f, "{}", reason),
+            Error::File { path, reason } => write!(f, "{}: {}", path, reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
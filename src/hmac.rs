@@ -0,0 +1,150 @@
+use crate::{streebog_256, streebog_512, Block, BLOCK_SIZE};
+use zeroize::{Zeroize, Zeroizing};
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+/// HMAC over Streebog-256 as in R 50.1.113-2016 (RFC 7836), memory byte order.
+pub fn hmac_streebog_256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let (inner, outer) = pads(key, |key| streebog_256(key).to_vec());
+
+    let inner = streebog_256(&Zeroizing::new([&inner[..], data].concat()));
+    streebog_256(&Zeroizing::new([&outer[..], &inner[..]].concat()))
+}
+
+/// HMAC over Streebog-512 as in R 50.1.113-2016 (RFC 7836), memory byte order.
+pub fn hmac_streebog_512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let (inner, outer) = pads(key, |key| streebog_512(key).to_vec());
+
+    let inner = streebog_512(&Zeroizing::new([&inner[..], data].concat()));
+    streebog_512(&Zeroizing::new([&outer[..], &inner[..]].concat()))
+}
+
+/// PBKDF2 of RFC 8018 with HMAC-Streebog-512 as the PRF, as in
+/// R 50.1.111-2016.
+pub fn pbkdf2_streebog_512(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    len: usize,
+) -> Zeroizing<Vec<u8>> {
+    let mut output = Zeroizing::new(Vec::with_capacity(len.div_ceil(64) * 64));
+
+    for index in 1..=len.div_ceil(64) as u32 {
+        let mut u = hmac_streebog_512(password, &[salt, &index.to_be_bytes()].concat());
+        let mut block = u;
+        for _ in 1..iterations {
+            u = hmac_streebog_512(password, &u);
+            for (a, b) in block.iter_mut().zip(u) {
+                *a ^= b;
+            }
+        }
+        output.extend_from_slice(&block);
+        u.zeroize();
+        block.zeroize();
+    }
+    output.truncate(len);
+
+    output
+}
+
+/// KDF_TREE_GOSTR3411_2012_256 of R 50.1.113-2016: HMAC-Streebog-256 of
+/// `[i] || label || 0x00 || seed || [L]` for a counter `i` of `r` bytes and
+/// the output length `L` in bits.
+pub fn kdf_tree_streebog_256(
+    key: &[u8],
+    label: &[u8],
+    seed: &[u8],
+    r: usize,
+    len: usize,
+) -> Zeroizing<Vec<u8>> {
+    let bits = (len as u64 * 8).to_be_bytes();
+    let bits = &bits[bits.iter().position(|byte| *byte != 0).unwrap_or(7)..];
+
+    let mut output = Zeroizing::new(Vec::with_capacity(len.div_ceil(32) * 32));
+    for index in 1..=len.div_ceil(32) as u64 {
+        let counter = &index.to_be_bytes()[8 - r..];
+        let data = [counter, label, &[0], seed, bits].concat();
+        output.extend_from_slice(&hmac_streebog_256(key, &data));
+    }
+    output.truncate(len);
+
+    output
+}
+
+/// The key blocks XORed with `ipad` and `opad`, wiped on drop.
+fn pads(key: &[u8], digest: impl Fn(&[u8]) -> Vec<u8>) -> (Zeroizing<Block>, Zeroizing<Block>) {
+    let mut block = Zeroizing::new([0u8; BLOCK_SIZE]);
+    if key.len() > BLOCK_SIZE {
+        let key = Zeroizing::new(digest(key));
+        block[..key.len()].copy_from_slice(&key);
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Zeroizing::new([0u8; BLOCK_SIZE]);
+    let mut outer = Zeroizing::new([0u8; BLOCK_SIZE]);
+    for i in 0..BLOCK_SIZE {
+        inner[i] = block[i] ^ IPAD;
+        outer[i] = block[i] ^ OPAD;
+    }
+
+    (inner, outer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hmac_streebog_256, hmac_streebog_512, kdf_tree_streebog_256, pbkdf2_streebog_512};
+    use crate::ByteParse;
+
+    #[test]
+    fn hmac() {
+        let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f".parse_bytes();
+        let data = "0126bdb87800af214341456563780100".parse_bytes();
+
+        assert_eq!(
+            hmac_streebog_256(&key, &data).to_vec(),
+            "a1aa5f7de402d7b3d323f2991c8d4534013137010a83754fd0af6d7cd4922ed9".parse_bytes()
+        );
+        assert_eq!(
+            hmac_streebog_512(&key, &data).to_vec(),
+            "a59bab22ecae19c65fbde6e5f4e9f5d8549d31f037f9df9b905500e171923a77\
+             3d5f1530f2ed7e964cb2eedc29e9ad2f3afe93b2814f79f5000ffc0366c251e6"
+                .parse_bytes()
+        );
+    }
+
+    #[test]
+    fn pbkdf2() {
+        assert_eq!(
+            *pbkdf2_streebog_512(b"password", b"salt", 1, 64),
+            "64770af7f748c3b1c9ac831dbcfd85c26111b30a8a657ddc3056b80ca73e040d\
+             2854fd36811f6d825cc4ab66ec0a68a490a9e5cf5156b3a2b7eecddbf9a16b47"
+                .parse_bytes()
+        );
+        assert_eq!(
+            *pbkdf2_streebog_512(b"password", b"salt", 2, 64),
+            "5a585bafdfbb6e8830d6d68aa3b43ac00d2e4aebce01c9b31c2caed56f0236d4\
+             d34b2b8fbd2c4e89d54d46f50e47d45bbac301571743119e8d3c42ba66d348de"
+                .parse_bytes()
+        );
+    }
+
+    /// R 50.1.113-2016, A.2.
+    #[test]
+    fn kdf_tree() {
+        let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f".parse_bytes();
+        assert_eq!(
+            *kdf_tree_streebog_256(
+                &key,
+                &"26bdb878".parse_bytes(),
+                &"af21434145656378".parse_bytes(),
+                1,
+                64
+            ),
+            "22b6837845c6bef65ea71672b265831086d3c76aebe6dae91cad51d83f79d16b\
+             074c9330599d7f8d712fca54392f4ddde93751206b3584c8f43f9e6dc51531f9"
+                .parse_bytes()
+        );
+    }
+}
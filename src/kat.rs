@@ -0,0 +1,217 @@
+//! Known-answer tests read from the scanned Appendix A of GOST 34.10-2018
+//! in `text.txt`, plus the Streebog examples of GOST 34.11-2018.
+//!
+//! The scan mixes Cyrillic look-alikes into labels and hex digits, splits
+//! long numbers with a trailing `W` or `\\`, and writes the base as a
+//! trailing `16` or `10`. Only the hexadecimal lines are used.
+
+use crate::curve::Curve;
+use crate::point::Point;
+use crate::signature::Signature;
+use crate::{hash_256, hash_512, streebog_256, streebog_512, ByteParse};
+use num_bigint_dig::BigInt;
+
+const APPENDIX: &str = include_str!("text.txt");
+
+struct Example {
+    values: Vec<(String, BigInt)>,
+}
+
+impl Example {
+    fn parse(section: &str) -> Self {
+        let mut values = vec![];
+        let mut lines = section.lines();
+
+        while let Some(line) = lines.next() {
+            let Some((label, value)) = line.split_once('=') else {
+                continue;
+            };
+            let label = normalize_label(label.trim());
+            if label.is_empty() || label.chars().count() > 2 || label.contains(' ') {
+                continue;
+            }
+
+            let mut value = value.trim().to_string();
+            while value.ends_with('W') || value.ends_with('\\') {
+                value = value.trim_end_matches(['W', '\\']).to_string();
+                match lines.next() {
+                    Some(next) => value.push_str(next.trim()),
+                    None => break,
+                }
+            }
+
+            if let Some(value) = parse_hex(&value) {
+                values.push((label, value));
+            }
+        }
+
+        Self { values }
+    }
+
+    fn get(&self, label: &str, nth: usize) -> BigInt {
+        self.values
+            .iter()
+            .filter(|(name, _)| name == label)
+            .nth(nth)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_else(|| panic!("no value #{} for {}", nth, label))
+    }
+}
+
+fn normalize_label(label: &str) -> String {
+    label
+        .chars()
+        .map(|c| match c {
+            'р' | 'Р' => 'p',
+            'а' | 'А' => 'a',
+            'Ь' => 'b',
+            'т' => 'm',
+            'у' | 'У' => 'y',
+            'х' | 'Х' => 'x',
+            'к' => 'k',
+            'г' => 'r',
+            'е' => 'e',
+            'с' | 'С' => 'c',
+            c => c.to_ascii_lowercase(),
+        })
+        .collect()
+}
+
+fn parse_hex(value: &str) -> Option<BigInt> {
+    let value = value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            'А' => 'A',
+            'В' => 'B',
+            'С' => 'C',
+            'Е' => 'E',
+            'З' => '3',
+            c => c,
+        })
+        .collect::<String>();
+    let value = value.trim_end_matches(['.', ',', '_']);
+    let digits = value
+        .strip_suffix("16")
+        .or_else(|| value.strip_suffix("ie"))?;
+
+    BigInt::parse_bytes(digits.as_bytes(), 16)
+}
+
+fn example(number: usize) -> Example {
+    let first = APPENDIX.find("Пример 1").unwrap();
+    let second = APPENDIX.find("Пример 2").unwrap();
+    let end = APPENDIX.find("Библиография").unwrap();
+
+    match number {
+        1 => Example::parse(&APPENDIX[first..second]),
+        2 => Example::parse(&APPENDIX[second..end]),
+        _ => unreachable!(),
+    }
+}
+
+fn check_example(example: &Example, curve: Curve, key: Point) {
+    let d = example.get("d", 0);
+    let e = example.get("e", 0);
+    let k = example.get("k", 0);
+
+    let public = curve.point.clone().multiply(d.clone(), &curve.p, &curve.a);
+    assert_eq!((&public.x, &public.y), (&key.x, &key.y));
+
+    let c = curve.point.clone().multiply(k.clone(), &curve.p, &curve.a);
+    assert_eq!(c.x, example.get("xc", 0));
+    assert_eq!(c.y, example.get("yc", 0));
+
+    let mut digest = e.to_bytes_be().1;
+    digest.reverse();
+    assert_eq!(Signature::digest_to_e(&digest, &curve.q), e);
+    assert_eq!(example.get("e", 1), e);
+
+    let sign = Signature::sign_e(&e, &k, &d, &curve).unwrap();
+    assert_eq!(sign.r, example.get("r", 0));
+    assert_eq!(sign.s, example.get("s", 0));
+    assert_eq!(example.get("r", 1), sign.r);
+
+    assert!(sign.verify_e(&e, &key, &curve));
+    assert!(!sign.verify_e(&(&e + 1), &key, &curve));
+}
+
+#[test]
+fn example_1() {
+    let example = example(1);
+
+    // The scan lost the line with x of P; section 5.3 sets it to 2.
+    let curve = Curve::new(
+        example.get("a", 0),
+        example.get("b", 0),
+        example.get("p", 0),
+        example.get("m", 0),
+        example.get("q", 0),
+        Point::new(BigInt::from(2), example.get("y", 0)),
+    );
+    let key = Point::new(example.get("xq", 0), example.get("yq", 0));
+
+    check_example(&example, curve, key);
+}
+
+#[test]
+fn example_2() {
+    let example = example(2);
+
+    // The scan labels y of Q as "Ур", the same as y of P.
+    let curve = Curve::new(
+        example.get("a", 0),
+        example.get("b", 0),
+        example.get("p", 0),
+        example.get("m", 0),
+        example.get("q", 0),
+        Point::new(example.get("xp", 0), example.get("yp", 0)),
+    );
+    let key = Point::new(example.get("xq", 0), example.get("yp", 1));
+
+    check_example(&example, curve, key);
+}
+
+#[test]
+fn streebog_examples() {
+    // M1 and M2 in the standard's notation, most significant byte first.
+    let m1 = "32313039383736353433323130393837363534333231303938373635343332\
+              31303938373635343332313039383736353433323130393837363534333231\
+              30"
+    .parse_bytes();
+    let m2 = "fbe2e5f0eee3c820fbeafaebef20fffbf0e1e0f0f520e0ed20e8ece0ebe5f0f2\
+              f120fff0eeec20f120faf2fee5e2202ce8f6f3ede220e8e6eee1e8f0f2d1202c\
+              e8f0f2e5e220e5d1"
+        .parse_bytes();
+
+    let vectors = [
+        (
+            &m1,
+            "486f64c1917879417fef082b3381a4e211c324f074654c38823a7b76f830ad00\
+             fa1fbae42b1285c0352f227524bc9ab16254288dd6863dccd5b9f54a1ad0541b",
+            "00557be5e584fd52a449b16b0251d05d27f94ab76cbaa6da890b59d8ef1e159d",
+        ),
+        (
+            &m2,
+            "28fbc9bada033b1460642bdcddb90c3fb3e56c497ccd0f62b8a2ad4935e85f03\
+             7613966de4ee00531ae60f3b5a47f8dae06915d5f2f194996fcabf2622e6881e",
+            "508f7e553c06501d749a66fc28c6cac0b005746d97537fa85d9e40904efed29d",
+        ),
+    ];
+
+    for (message, expected_512, expected_256) in vectors {
+        let expected_512 = expected_512.parse_bytes();
+        let expected_256 = expected_256.parse_bytes();
+        assert_eq!(hash_512(message).to_vec(), expected_512);
+        assert_eq!(hash_256(message).to_vec(), expected_256);
+
+        let mut memory = message.clone();
+        memory.reverse();
+        let mut digest = streebog_512(&memory).to_vec();
+        digest.reverse();
+        assert_eq!(digest, expected_512);
+        let mut digest = streebog_256(&memory).to_vec();
+        digest.reverse();
+        assert_eq!(digest, expected_256);
+    }
+}
@@ -0,0 +1,129 @@
+//! KExp15 and KImp15 of R 1323565.1.017-2018 (RFC 9337): a key is wrapped
+//! together with its MAC over `IV || K` under one key encryption key and
+//! encrypted in CTR mode under another.
+
+use crate::error::Error;
+use crate::mac::Mac;
+use crate::modes::{BlockCipher, Ctr};
+use zeroize::Zeroizing;
+
+/// `CTR(kek_enc, iv, K || OMAC(kek_mac, iv || K))`; `iv` is half a block.
+pub fn kexp15<C: BlockCipher>(
+    key: &[u8],
+    kek_mac: C,
+    kek_enc: C,
+    iv: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let mut ctr = Ctr::new(kek_enc, iv, C::BLOCK_SIZE)?;
+
+    let mut mac = Mac::new(kek_mac);
+    mac.update(iv);
+    mac.update(key);
+
+    let mut wrapped = key.to_vec();
+    wrapped.extend_from_slice(&mac.finalize(C::BLOCK_SIZE)?);
+    ctr.apply(&mut wrapped);
+
+    Ok(wrapped)
+}
+
+/// Undoes `kexp15`, failing with `InvalidTag` if the wrapped key or `iv`
+/// has been tampered with.
+pub fn kimp15<C: BlockCipher>(
+    wrapped: &[u8],
+    kek_mac: C,
+    kek_enc: C,
+    iv: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let mut ctr = Ctr::new(kek_enc, iv, C::BLOCK_SIZE)?;
+    if wrapped.len() <= C::BLOCK_SIZE {
+        return Err(Error::InvalidTag);
+    }
+
+    let mut key = Zeroizing::new(wrapped.to_vec());
+    ctr.apply(&mut key);
+    let length = key.len() - C::BLOCK_SIZE;
+    let tag = key.split_off(length);
+
+    let mut mac = Mac::new(kek_mac);
+    mac.update(iv);
+    mac.update(&key);
+    mac.verify(&tag)?;
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{kexp15, kimp15};
+    use crate::error::Error;
+    use crate::kuznyechik::Kuznyechik;
+    use crate::magma::Magma;
+    use crate::ByteParse;
+
+    fn keys() -> ([u8; 32], [u8; 32], [u8; 32]) {
+        let key = "8899aabbccddeeff0011223344556677fedcba98765432100123456789abcdef".parse_bytes();
+        let mac = "08090a0b0c0d0e0f0001020304050607101112131415161718191a1b1c1d1e1f".parse_bytes();
+        let enc = "202122232425262728292a2b2c2d2e2f38393a3b3c3d3e3f3031323334353637".parse_bytes();
+
+        (
+            key.try_into().unwrap(),
+            mac.try_into().unwrap(),
+            enc.try_into().unwrap(),
+        )
+    }
+
+    /// R 1323565.1.017-2018, A.1.1.
+    #[test]
+    fn magma() {
+        let (key, mac, enc) = keys();
+        let iv = "67bed654".parse_bytes();
+        let expected = "cfd5a12d5b81b6e1e99c916d07900c6a\
+                        c12703fb3abded55567bf3742c899c75\
+                        5dafe7b42e3a8bd9"
+            .parse_bytes();
+
+        let wrapped = kexp15(&key, Magma::new(&mac), Magma::new(&enc), &iv).unwrap();
+        assert_eq!(wrapped, expected);
+        let unwrapped = kimp15(&wrapped, Magma::new(&mac), Magma::new(&enc), &iv).unwrap();
+        assert_eq!(*unwrapped, key);
+    }
+
+    /// R 1323565.1.017-2018, A.1.2, and tampering.
+    #[test]
+    fn kuznyechik() {
+        let (key, mac, enc) = keys();
+        let iv = "0909472dd9f26be8".parse_bytes();
+        let expected = "e36184e84e8d736ff36cc2e5ae065dc6\
+                        56b23c20f549b02fdff88e1f3f30d8c2\
+                        9a53f3ca554dbad80de152b9a4625b32"
+            .parse_bytes();
+
+        let wrapped = kexp15(&key, Kuznyechik::new(&mac), Kuznyechik::new(&enc), &iv).unwrap();
+        assert_eq!(wrapped, expected);
+        let unwrapped =
+            kimp15(&wrapped, Kuznyechik::new(&mac), Kuznyechik::new(&enc), &iv).unwrap();
+        assert_eq!(*unwrapped, key);
+
+        let mut tampered = wrapped.clone();
+        tampered[5] ^= 1;
+        assert_eq!(
+            kimp15(&tampered, Kuznyechik::new(&mac), Kuznyechik::new(&enc), &iv).err(),
+            Some(Error::InvalidTag)
+        );
+        assert_eq!(
+            kimp15(&wrapped, Kuznyechik::new(&enc), Kuznyechik::new(&enc), &iv).err(),
+            Some(Error::InvalidTag)
+        );
+        assert_eq!(
+            kimp15(
+                &wrapped[..16],
+                Kuznyechik::new(&mac),
+                Kuznyechik::new(&enc),
+                &iv
+            )
+            .err(),
+            Some(Error::InvalidTag)
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use crate::curve::Curve;
+use crate::error::Error;
+use crate::point::Point;
+use crate::signature::Signature;
+use crate::{to_hex, ByteParse};
+use num_bigint_dig::{BigInt, Sign};
+use num_traits::Zero;
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+use zeroize::{Zeroize, Zeroizing};
+
+/// The scalar is wiped when the key is dropped, and `Debug` omits it. Keys
+/// are not `Clone`: borrow them, or round-trip `to_bytes` for a deliberate
+/// second copy.
+pub struct PrivateKey {
+    d: BigInt,
+    curve: Curve,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    point: Point,
+    curve: Curve,
+}
+
+impl PrivateKey {
+    pub fn new(d: BigInt, curve: Curve) -> Result<Self, Error> {
+        if d <= BigInt::zero() || d >= curve.q {
+            return Err(Error::ScalarOutOfRange);
+        }
+
+        Ok(Self { d, curve })
+    }
+
+    pub fn generate(curve: Curve) -> Self {
+        Self::generate_with_rng(&mut rand::thread_rng(), curve)
+    }
+
+    pub fn generate_with_rng<R: CryptoRng + RngCore>(rng: &mut R, curve: Curve) -> Self {
+        let d = Signature::rand_k(rng, &curve.q);
+
+        Self { d, curve }
+    }
+
+    pub fn scalar(&self) -> &BigInt {
+        &self.d
+    }
+
+    pub fn curve(&self) -> &Curve {
+        &self.curve
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        let curve = &self.curve;
+        let point = curve
+            .point
+            .clone()
+            .multiply(self.d.clone(), &curve.p, &curve.a);
+
+        PublicKey {
+            point,
+            curve: curve.clone(),
+        }
+    }
+
+    /// `d` as a little-endian integer of `curve.size()` bytes.
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(to_le_bytes(&self.d, self.curve.size()))
+    }
+
+    pub fn from_bytes(bytes: &[u8], curve: Curve) -> Result<Self, Error> {
+        check_length(bytes, curve.size())?;
+
+        Self::new(BigInt::from_bytes_le(Sign::Plus, bytes), curve)
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str, curve: Curve) -> Result<Self, Error> {
+        let bytes = hex.try_parse_bytes().ok_or(Error::InvalidHex)?;
+
+        Self::from_bytes(&bytes, curve)
+    }
+}
+
+/// Overwrites the limbs of the scalar in place; the key is unusable after.
+impl Zeroize for PrivateKey {
+    fn zeroize(&mut self) {
+        self.d.zeroize();
+    }
+}
+
+impl Drop for PrivateKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrivateKey")
+            .field("curve", &self.curve.oid())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PublicKey {
+    /// Accepts only affine points of the subgroup generated by `curve.point`.
+    pub fn new(point: Point, curve: Curve) -> Result<Self, Error> {
+        if !curve.contains(&point) {
+            return Err(Error::PointNotOnCurve);
+        }
+        if !point
+            .clone()
+            .multiply(curve.q.clone(), &curve.p, &curve.a)
+            .is_identity()
+        {
+            return Err(Error::PointNotInSubgroup);
+        }
+
+        Ok(Self { point, curve })
+    }
+
+    pub fn point(&self) -> &Point {
+        &self.point
+    }
+
+    pub fn curve(&self) -> &Curve {
+        &self.curve
+    }
+
+    /// `x || y`, each a little-endian integer of `curve.size()` bytes, as in
+    /// RFC 4491.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let size = self.curve.size();
+
+        [
+            to_le_bytes(&self.point.x, size),
+            to_le_bytes(&self.point.y, size),
+        ]
+        .concat()
+    }
+
+    pub fn from_bytes(bytes: &[u8], curve: Curve) -> Result<Self, Error> {
+        let size = curve.size();
+        check_length(bytes, 2 * size)?;
+
+        let x = BigInt::from_bytes_le(Sign::Plus, &bytes[..size]);
+        let y = BigInt::from_bytes_le(Sign::Plus, &bytes[size..]);
+
+        Self::new(Point::new(x, y), curve)
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.to_bytes())
+    }
+
+    pub fn from_hex(hex: &str, curve: Curve) -> Result<Self, Error> {
+        let bytes = hex.try_parse_bytes().ok_or(Error::InvalidHex)?;
+
+        Self::from_bytes(&bytes, curve)
+    }
+}
+
+pub(crate) fn to_le_bytes(value: &BigInt, size: usize) -> Vec<u8> {
+    let value = Zeroizing::new(value.to_bytes_le().1);
+    let mut bytes = vec![0u8; size];
+    bytes[..value.len()].copy_from_slice(&value);
+
+    bytes
+}
+
+pub(crate) fn check_length(bytes: &[u8], expected: usize) -> Result<(), Error> {
+    if bytes.len() != expected {
+        return Err(Error::InvalidLength {
+            expected,
+            actual: bytes.len(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PrivateKey, PublicKey};
+    use crate::curve::Curve;
+    use crate::error::Error;
+    use crate::oid;
+    use crate::point::Point;
+    use num_bigint_dig::BigInt;
+    use num_traits::Zero;
+    use zeroize::Zeroize;
+
+    fn hex(value: &str) -> BigInt {
+        BigInt::parse_bytes(value.as_bytes(), 16).unwrap()
+    }
+
+    fn curve() -> Curve {
+        Curve::from_oid(oid::GOST3410_2001_TEST).unwrap()
+    }
+
+    #[test]
+    fn encodings() {
+        let d = hex("7A929ADE789BB9BE10ED359DD39A72C11B60961F49397EEE1D19CE9891EC3B28");
+        let key = PrivateKey::new(d.clone(), curve()).unwrap();
+        assert_eq!(
+            key.to_hex(),
+            "283bec9198ce191dee7e39491f96601bc1729ad39d35ed10beb99b78de9a927a"
+        );
+
+        let public = key.public_key();
+        assert_eq!(
+            public.point().x,
+            hex("7F2B49E270DB6D90D8595BEC458B50C58585BA1D4E9B788F6689DBD8E56FD80B")
+        );
+        assert_eq!(&public.to_bytes()[..2], &[0x0b, 0xd8]);
+        assert_eq!(&public.to_bytes()[32..34], &[0xda, 0x77]);
+
+        let debug = format!("{:?}", key);
+        assert_eq!(
+            debug,
+            "PrivateKey { curve: Some(\"1.2.643.2.2.35.0\"), .. }"
+        );
+        assert!(!debug.contains(&d.to_string()));
+
+        let parsed = PrivateKey::from_hex(&key.to_hex(), curve()).unwrap();
+        assert_eq!(parsed.scalar(), &d);
+        assert_eq!(PublicKey::from_hex(&public.to_hex(), curve()), Ok(public));
+    }
+
+    /// What `Drop` runs: the limbs stay allocated but hold zeros.
+    #[test]
+    fn wipe() {
+        let d = hex("7A929ADE789BB9BE10ED359DD39A72C11B60961F49397EEE1D19CE9891EC3B28");
+        let mut key = PrivateKey::new(d, curve()).unwrap();
+        key.zeroize();
+
+        let (_, limbs) = key.d.to_bytes_le();
+        assert!(key.d.is_zero());
+        assert!(limbs.iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn validation() {
+        let curve = curve();
+
+        assert_eq!(
+            PrivateKey::from_bytes(&[0u8; 32], curve.clone()).err(),
+            Some(Error::ScalarOutOfRange)
+        );
+        assert_eq!(
+            PrivateKey::from_bytes(&curve.q.to_bytes_le().1, curve.clone()).err(),
+            Some(Error::ScalarOutOfRange)
+        );
+        assert_eq!(
+            PrivateKey::from_bytes(&[1u8; 31], curve.clone()).err(),
+            Some(Error::InvalidLength {
+                expected: 32,
+                actual: 31
+            })
+        );
+        assert_eq!(
+            PrivateKey::from_hex("zz", curve.clone()).err(),
+            Some(Error::InvalidHex)
+        );
+
+        let mut bytes = PrivateKey::generate(curve.clone()).public_key().to_bytes();
+        bytes[0] ^= 1;
+        assert_eq!(
+            PublicKey::from_bytes(&bytes, curve.clone()),
+            Err(Error::PointNotOnCurve)
+        );
+        assert_eq!(
+            PublicKey::new(Point::identity(), curve),
+            Err(Error::PointNotOnCurve)
+        );
+    }
+
+    #[test]
+    fn order_two() {
+        // The only roots of x^3 + ax + b on the cofactor-4 curves.
+        for (curve, x) in [
+            (
+                oid::TC26_256_A,
+                "0100FE73F595FF158E974B44D478D9588744FE5C192AC47EA63075DCE7A14AAA",
+            ),
+            (
+                oid::TC26_512_C,
+                "9A628F975594ECEFD89BA28A2539FFB79C8AB238AEED0851FA5C1ABB02B80B44\
+                 C6734501B83A011DD625CD0B5145091A6D9ACD4B1F5C5B1E21B2B249DDFD1271",
+            ),
+        ] {
+            let curve = Curve::from_oid(curve).unwrap();
+            let point = Point::new(hex(x), BigInt::zero());
+            assert!(curve.contains(&point));
+            assert_eq!(PublicKey::new(point, curve), Err(Error::PointNotInSubgroup));
+        }
+    }
+}
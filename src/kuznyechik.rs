@@ -0,0 +1,273 @@
+//! The Kuznyechik block cipher of GOST R 34.12-2015: 128-bit blocks, a
+//! 256-bit key and ten rounds of `X`, `S` and `L`.
+
+use crate::modes::BlockCipher;
+use crate::table::PI;
+use std::sync::OnceLock;
+use zeroize::Zeroize;
+
+pub const KUZNYECHIK_BLOCK: usize = 16;
+
+/// Coefficients of the linear map `l` of GOST R 34.12-2015, for the bytes
+/// in memory order.
+const L: [u8; KUZNYECHIK_BLOCK] = [
+    148, 32, 133, 16, 194, 192, 1, 251, 1, 192, 194, 16, 133, 32, 148, 1,
+];
+
+pub struct Kuznyechik {
+    keys: [[u8; KUZNYECHIK_BLOCK]; 10],
+}
+
+/// `L(S(x))` and `L^-1(x)` of a block with one nonzero byte, for every
+/// position and value, so that a round is sixteen lookups and XORs.
+struct Tables {
+    substitute_linear: Vec<[u8; KUZNYECHIK_BLOCK]>,
+    inverse_linear: Vec<[u8; KUZNYECHIK_BLOCK]>,
+    inverse_pi: [u8; 256],
+}
+
+impl Kuznyechik {
+    pub fn new(key: &[u8; 32]) -> Self {
+        let mut keys = [[0u8; KUZNYECHIK_BLOCK]; 10];
+        keys[0].copy_from_slice(&key[..16]);
+        keys[1].copy_from_slice(&key[16..]);
+
+        for pair in 1..5 {
+            let mut a1 = keys[2 * pair - 2];
+            let mut a0 = keys[2 * pair - 1];
+            for i in 0..8 {
+                let mut constant = [0u8; KUZNYECHIK_BLOCK];
+                constant[15] = (8 * (pair - 1) + i + 1) as u8;
+                linear(&mut constant);
+
+                let mut block = a1;
+                xor(&mut block, &constant);
+                lookup(&tables().substitute_linear, &mut block);
+                xor(&mut block, &a0);
+
+                a0 = a1;
+                a1 = block;
+            }
+            keys[2 * pair] = a1;
+            keys[2 * pair + 1] = a0;
+            a1.zeroize();
+            a0.zeroize();
+        }
+
+        Self { keys }
+    }
+
+    pub fn encrypt_block(&self, block: &mut [u8; KUZNYECHIK_BLOCK]) {
+        let tables = tables();
+        for key in &self.keys[..9] {
+            xor(block, key);
+            lookup(&tables.substitute_linear, block);
+        }
+        xor(block, &self.keys[9]);
+    }
+
+    pub fn decrypt_block(&self, block: &mut [u8; KUZNYECHIK_BLOCK]) {
+        let tables = tables();
+        for key in self.keys[1..].iter().rev() {
+            xor(block, key);
+            lookup(&tables.inverse_linear, block);
+            for byte in block.iter_mut() {
+                *byte = tables.inverse_pi[*byte as usize];
+            }
+        }
+        xor(block, &self.keys[0]);
+    }
+}
+
+impl Drop for Kuznyechik {
+    fn drop(&mut self) {
+        self.keys.zeroize();
+    }
+}
+
+impl BlockCipher for Kuznyechik {
+    const BLOCK_SIZE: usize = KUZNYECHIK_BLOCK;
+
+    fn with_key(&self, key: &[u8; 32]) -> Self {
+        Kuznyechik::new(key)
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        Kuznyechik::encrypt_block(self, block.try_into().unwrap());
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        Kuznyechik::decrypt_block(self, block.try_into().unwrap());
+    }
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+
+    TABLES.get_or_init(|| {
+        let mut substitute_linear = Vec::with_capacity(KUZNYECHIK_BLOCK * 256);
+        let mut inverse_linear = Vec::with_capacity(KUZNYECHIK_BLOCK * 256);
+        for position in 0..KUZNYECHIK_BLOCK {
+            for value in 0..=255 {
+                let mut block = [0u8; KUZNYECHIK_BLOCK];
+                block[position] = PI[value as usize];
+                linear(&mut block);
+                substitute_linear.push(block);
+
+                let mut block = [0u8; KUZNYECHIK_BLOCK];
+                block[position] = value;
+                linear_inverse(&mut block);
+                inverse_linear.push(block);
+            }
+        }
+
+        let mut inverse_pi = [0u8; 256];
+        for (index, value) in PI.iter().enumerate() {
+            inverse_pi[*value as usize] = index as u8;
+        }
+
+        Tables {
+            substitute_linear,
+            inverse_linear,
+            inverse_pi,
+        }
+    })
+}
+
+/// Replaces `block` by the XOR of the table rows its bytes select.
+fn lookup(table: &[[u8; KUZNYECHIK_BLOCK]], block: &mut [u8; KUZNYECHIK_BLOCK]) {
+    let mut output = [0u8; KUZNYECHIK_BLOCK];
+    for (position, byte) in block.iter().enumerate() {
+        xor(&mut output, &table[position * 256 + *byte as usize]);
+    }
+    *block = output;
+}
+
+fn xor(block: &mut [u8; KUZNYECHIK_BLOCK], other: &[u8; KUZNYECHIK_BLOCK]) {
+    for (a, b) in block.iter_mut().zip(other) {
+        *a ^= b;
+    }
+}
+
+/// Sixteen rounds of `R`, each shifting the block by one byte and putting
+/// `l(block)` in front.
+fn linear(block: &mut [u8; KUZNYECHIK_BLOCK]) {
+    for _ in 0..KUZNYECHIK_BLOCK {
+        let mut sum = 0;
+        for (byte, coefficient) in block.iter().zip(L) {
+            sum ^= multiply(*byte, coefficient);
+        }
+        block.copy_within(..15, 1);
+        block[0] = sum;
+    }
+}
+
+/// Sixteen rounds of `R^-1`, undoing `linear`.
+fn linear_inverse(block: &mut [u8; KUZNYECHIK_BLOCK]) {
+    for _ in 0..KUZNYECHIK_BLOCK {
+        let mut sum = block[0];
+        for (byte, coefficient) in block[1..].iter().zip(L) {
+            sum ^= multiply(*byte, coefficient);
+        }
+        block.copy_within(1.., 0);
+        block[15] = sum;
+    }
+}
+
+/// Multiplication in GF(2^8) modulo `x^8 + x^7 + x^6 + x + 1`.
+fn multiply(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0xc3;
+        }
+        b >>= 1;
+    }
+
+    product
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{linear, linear_inverse, lookup, Kuznyechik};
+    use crate::table::PI;
+    use crate::ByteParse;
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+
+    fn cipher() -> Kuznyechik {
+        let key = "8899aabbccddeeff0011223344556677fedcba98765432100123456789abcdef".parse_bytes();
+        Kuznyechik::new(&key.try_into().unwrap())
+    }
+
+    /// The round keys of GOST R 34.12-2015, A.1.4.
+    #[test]
+    fn key_schedule() {
+        let keys = [
+            "8899aabbccddeeff0011223344556677",
+            "fedcba98765432100123456789abcdef",
+            "db31485315694343228d6aef8cc78c44",
+            "3d4553d8e9cfec6815ebadc40a9ffd04",
+            "57646468c44a5e28d3e59246f429f1ac",
+            "bd079435165c6432b532e82834da581b",
+            "51e640757e8745de705727265a0098b1",
+            "5a7925017b9fdd3ed72a91a22286f984",
+            "bb44e25378c73123a5f32f73cdb6e517",
+            "72e9dd7416bcf45b755dbaa88e4a4043",
+        ];
+
+        for (key, expected) in cipher().keys.iter().zip(keys) {
+            assert_eq!(key.to_vec(), expected.parse_bytes());
+        }
+    }
+
+    #[test]
+    fn encrypt() {
+        let mut block: [u8; 16] = "1122334455667700ffeeddccbbaa9988"
+            .parse_bytes()
+            .try_into()
+            .unwrap();
+        cipher().encrypt_block(&mut block);
+
+        assert_eq!(
+            block.to_vec(),
+            "7f679d90bebc24305a468d42b9d4edcd".parse_bytes()
+        );
+
+        cipher().decrypt_block(&mut block);
+        assert_eq!(
+            block.to_vec(),
+            "1122334455667700ffeeddccbbaa9988".parse_bytes()
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..64 {
+            let mut block = [0u8; 16];
+            rng.fill_bytes(&mut block);
+
+            let mut substituted = block;
+            for byte in substituted.iter_mut() {
+                *byte = PI[*byte as usize];
+            }
+            let mut expected = substituted;
+            linear(&mut expected);
+
+            let mut fast = block;
+            lookup(&super::tables().substitute_linear, &mut fast);
+            assert_eq!(fast, expected);
+
+            linear_inverse(&mut expected);
+            assert_eq!(expected, substituted);
+            lookup(&super::tables().inverse_linear, &mut fast);
+            assert_eq!(fast, substituted);
+        }
+    }
+}
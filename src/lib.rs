@@ -0,0 +1,544 @@
+use bitvec::prelude::*;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::table::{A, C, PI, TAU};
+
+pub mod acpkm;
+pub mod chain;
+pub mod checksum;
+pub mod cms;
+pub mod crl;
+pub mod csr;
+pub mod curve;
+pub mod der;
+pub mod drbg;
+pub mod error;
+pub mod hmac;
+#[cfg(test)]
+mod kat;
+pub mod kexp;
+pub mod key;
+pub mod kuznyechik;
+pub mod mac;
+pub mod magma;
+pub mod mgm;
+pub mod modes;
+pub mod nonce;
+pub mod oid;
+pub mod pem;
+pub mod pkcs8;
+pub mod point;
+pub mod signature;
+mod table;
+#[cfg(test)]
+mod testing;
+pub mod vko;
+pub mod x509;
+
+pub(crate) const BLOCK_SIZE: usize = 64;
+
+pub type Block = [u8; BLOCK_SIZE];
+
+pub fn hash_512(message: &[u8]) -> Block {
+    hash([0u8; 64], message)
+}
+
+pub fn hash_256(message: &[u8]) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    let hash = hash([1u8; 64], message);
+    output[..32].copy_from_slice(&hash[..32]);
+
+    output
+}
+
+/// Streebog-512 over `data` taken in memory order, as `gost12sum` and
+/// OpenSSL do. `hash_512` works on the standard's big-endian notation,
+/// so the input and the digest are both reversed around it.
+pub fn streebog_512(data: &[u8]) -> Block {
+    let mut message = Zeroizing::new(data.to_vec());
+    message.reverse();
+    let mut digest = hash_512(&message);
+    digest.reverse();
+
+    digest
+}
+
+pub fn streebog_256(data: &[u8]) -> [u8; 32] {
+    let mut message = Zeroizing::new(data.to_vec());
+    message.reverse();
+    let mut digest = hash_256(&message);
+    digest.reverse();
+
+    digest
+}
+
+/// Incremental Streebog over data in memory order; `finalize` returns the
+/// same bytes as `streebog_256` or `streebog_512` over the whole input.
+/// The state is wiped when the hasher is dropped; it is not `Clone`, so
+/// no copy of it outlives that.
+pub struct Streebog {
+    hash: Block,
+    n: Block,
+    sigma: Block,
+    buffer: Block,
+    filled: usize,
+    size: usize,
+}
+
+impl Streebog {
+    pub fn new_256() -> Self {
+        Self::new([1u8; 64], 32)
+    }
+
+    pub fn new_512() -> Self {
+        Self::new([0u8; 64], 64)
+    }
+
+    fn new(iv: Block, size: usize) -> Self {
+        Self {
+            hash: iv,
+            n: [0u8; 64],
+            sigma: [0u8; 64],
+            buffer: [0u8; 64],
+            filled: 0,
+            size,
+        }
+    }
+
+    pub fn digest_size(&self) -> usize {
+        self.size
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let take = (BLOCK_SIZE - self.filled).min(data.len());
+            self.buffer[self.filled..self.filled + take].copy_from_slice(&data[..take]);
+            self.filled += take;
+            data = &data[take..];
+
+            if self.filled == BLOCK_SIZE {
+                self.compress_buffer();
+            }
+        }
+    }
+
+    fn compress_buffer(&mut self) {
+        let mut n_512 = [0u8; 64];
+        n_512[62] = 0x02;
+
+        let mut section = self.buffer;
+        section.reverse();
+        self.hash = compression(self.n, self.hash, section);
+        self.n = add(self.n, n_512);
+        self.sigma = add(self.sigma, section);
+
+        section.zeroize();
+        self.buffer.zeroize();
+        self.filled = 0;
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        let mut rest = Zeroizing::new(self.buffer[..self.filled].to_vec());
+        rest.reverse();
+        let mut section = Zeroizing::new(padding(&rest));
+
+        let len = rest.len() * 8;
+        let mut v = [0u8; 64];
+        let v0 = [0u8; 64];
+        v[63] = (len & 0xFF) as u8;
+        v[62] = (len >> 8) as u8;
+
+        let mut hash = Zeroizing::new(compression(self.n, self.hash, *section));
+        let n = Zeroizing::new(add(self.n, v));
+        let sigma = Zeroizing::new(add(self.sigma, *section));
+        section.zeroize();
+
+        *hash = compression(v0, *hash, *n);
+        *hash = compression(v0, *hash, *sigma);
+
+        let mut digest = hash[..self.size].to_vec();
+        digest.reverse();
+
+        digest
+    }
+}
+
+impl Zeroize for Streebog {
+    fn zeroize(&mut self) {
+        self.hash.zeroize();
+        self.n.zeroize();
+        self.sigma.zeroize();
+        self.buffer.zeroize();
+        self.filled = 0;
+    }
+}
+
+impl Drop for Streebog {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+fn hash(iv: Block, message: &[u8]) -> Block {
+    let mut hash = iv;
+    let mut n = [0u8; 64];
+    let mut sigma = [0u8; 64];
+
+    let mut len = message.len();
+    let mut p = 0;
+
+    let mut n_512 = [0u8; 64];
+    n_512[62] = 0x02;
+
+    while len >= 64 {
+        let mut section = [0u8; 64];
+        for i in 0..64 {
+            section[i] = message[message.len() - (p + 1) * 64 + i];
+        }
+        hash = compression(n, hash, section);
+        n = add(n, n_512);
+        sigma = add(sigma, section);
+        section.zeroize();
+
+        len -= 64;
+        p += 1;
+    }
+
+    len *= 8;
+    let rest = &message[..(message.len() - p * 64)];
+    let mut section = padding(rest);
+
+    let mut v = [0u8; 64];
+    let v0 = [0u8; 64];
+    v[63] = (len & 0xFF) as u8;
+    v[62] = (len >> 8) as u8;
+
+    hash = compression(n, hash, section);
+
+    n = add(n, v);
+    sigma = add(sigma, section);
+
+    hash = compression(v0, hash, n);
+    hash = compression(v0, hash, sigma);
+
+    section.zeroize();
+    sigma.zeroize();
+
+    hash
+}
+
+fn padding(m: &[u8]) -> Block {
+    let mut output = [0u8; BLOCK_SIZE];
+    for i in 0..m.len() {
+        output[BLOCK_SIZE - m.len() + i] = m[i]
+    }
+    if m.len() < BLOCK_SIZE {
+        output[BLOCK_SIZE - m.len() - 1] = 0x01;
+    }
+
+    output
+}
+
+pub fn add(l: Block, r: Block) -> Block {
+    let mut result = [0u8; 64];
+    let mut t = 0i32;
+    for i in (0..64).rev() {
+        t = l[i] as i32 + r[i] as i32 + (t >> 8);
+        result[i] = (t & 0xFF) as u8;
+    }
+    result
+}
+
+fn xor(k: Block, a: Block) -> Block {
+    let mut output = [0u8; BLOCK_SIZE];
+    for i in 0..BLOCK_SIZE {
+        output[i] = k[i] ^ a[i];
+    }
+
+    output
+}
+
+fn bijective(a: Block) -> Block {
+    let mut output = [0u8; BLOCK_SIZE];
+    for i in 0..BLOCK_SIZE {
+        output[i] = PI[a[i] as usize];
+    }
+
+    output
+}
+
+fn permutation(a: Block) -> Block {
+    let mut output = [0u8; BLOCK_SIZE];
+    for i in 0..BLOCK_SIZE {
+        output[i] = a[TAU[i]];
+    }
+
+    output
+}
+
+fn linear(a: Block) -> Block {
+    let mut output = [0u8; 64];
+
+    for i in 0..8 {
+        let mut t = 0u64;
+        let mut temp = [0u8; 8];
+
+        for j in 0..8 {
+            temp[j] = a[i * 8 + j];
+        }
+        let bits = temp.view_bits::<Msb0>();
+        for j in 0..64 {
+            if bits[j] {
+                t ^= A[j];
+            }
+        }
+
+        let mut t = t.to_ne_bytes();
+        t.reverse();
+        for j in 0..8 {
+            output[i * 8 + j] = t[j];
+        }
+    }
+
+    output
+}
+
+fn linear_permutation_bijective(a: Block) -> Block {
+    linear(permutation(bijective(a)))
+}
+
+fn key_schedule(k: Block, i: usize) -> Block {
+    linear_permutation_bijective(xor(k, C[i]))
+}
+
+fn e_transformation(k: Block, m: Block) -> Block {
+    let mut s = xor(k, m);
+    let mut k = k;
+    for i in 0..12 {
+        s = linear_permutation_bijective(s);
+        k = key_schedule(k, i);
+        s = xor(k, s);
+    }
+    k.zeroize();
+
+    s
+}
+
+fn compression(n: Block, h: Block, m: Block) -> Block {
+    let k = xor(h, n);
+    let k = linear_permutation_bijective(k);
+    let t = e_transformation(k, m);
+    let t = xor(t, h);
+    xor(t, m)
+}
+
+pub trait ByteParse {
+    fn parse_bytes(self) -> Vec<u8>;
+    fn try_parse_bytes(self) -> Option<Vec<u8>>;
+}
+
+impl ByteParse for &str {
+    fn parse_bytes(self) -> Vec<u8> {
+        let mut vec = vec![];
+        for i in (0..self.len()).step_by(2) {
+            vec.push(u8::from_str_radix(&self[i..(i + 2)], 16).unwrap())
+        }
+
+        vec
+    }
+
+    fn try_parse_bytes(self) -> Option<Vec<u8>> {
+        if !self.len().is_multiple_of(2) || !self.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        Some(self.parse_bytes())
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::curve::Curve;
+    use crate::key::{to_le_bytes, PrivateKey};
+    use crate::nonce::Nonce;
+    use crate::point::Point;
+    use crate::signature::Signature;
+    use crate::{hash_512, streebog_256, streebog_512, ByteParse, Streebog};
+    use num_bigint_dig::{BigInt, Sign};
+    use std::str::FromStr;
+    use zeroize::Zeroize;
+
+    const MSG: [u8; 63] = [
+        0x32u8, 0x31, 0x30, 0x39, 0x38, 0x37, 0x36, 0x35, 0x34, 0x33, 0x32, 0x31, 0x30, 0x39, 0x38,
+        0x37, 0x36, 0x35, 0x34, 0x33, 0x32, 0x31, 0x30, 0x39, 0x38, 0x37, 0x36, 0x35, 0x34, 0x33,
+        0x32, 0x31, 0x30, 0x39, 0x38, 0x37, 0x36, 0x35, 0x34, 0x33, 0x32, 0x31, 0x30, 0x39, 0x38,
+        0x37, 0x36, 0x35, 0x34, 0x33, 0x32, 0x31, 0x30, 0x39, 0x38, 0x37, 0x36, 0x35, 0x34, 0x33,
+        0x32, 0x31, 0x30,
+    ];
+
+    #[test]
+    fn byte_parser_test() {
+        let message = "fbe2e5f0eee3c820fbeafaebef20fffbf0e1e0f0f520e0ed20e8ece0ebe5f0f2f120fff0eeec20f120faf2fee5e2202ce8f6f3ede220e8e6eee1e8f0f2d1202ce8f0f2e5e220e5d1";
+        let bytes = message.parse_bytes();
+        assert_eq!((bytes[0], bytes[1]), (0xfb, 0xe2));
+    }
+
+    #[test]
+    fn hasher() {
+        let hash_actual = hash_512(&MSG);
+
+        let hash_expected = [
+            0x48u8, 0x6F, 0x64, 0xC1, 0x91, 0x78, 0x79, 0x41, 0x7F, 0xEF, 0x08, 0x2B, 0x33, 0x81,
+            0xA4, 0xE2, 0x11, 0xC3, 0x24, 0xF0, 0x74, 0x65, 0x4C, 0x38, 0x82, 0x3A, 0x7B, 0x76,
+            0xF8, 0x30, 0xAD, 0x00, 0xFA, 0x1F, 0xBA, 0xE4, 0x2B, 0x12, 0x85, 0xC0, 0x35, 0x2F,
+            0x22, 0x75, 0x24, 0xBC, 0x9A, 0xB1, 0x62, 0x54, 0x28, 0x8D, 0xD6, 0x86, 0x3D, 0xCC,
+            0xD5, 0xB9, 0xF5, 0x4A, 0x1A, 0xD0, 0x54, 0x1B,
+        ];
+
+        assert_eq!(hash_actual, hash_expected);
+
+        let mut message = MSG;
+        message.reverse();
+        let mut hash_expected = hash_expected;
+        hash_expected.reverse();
+
+        assert_eq!(streebog_512(&message), hash_expected);
+    }
+
+    #[test]
+    fn streaming_hasher() {
+        let data = (0..300u32)
+            .map(|i| (i * 7 % 256) as u8)
+            .collect::<Vec<u8>>();
+
+        for len in [0, 1, 63, 64, 65, 128, 300] {
+            let data = &data[..len];
+            for chunk in [1, 7, 64, 100] {
+                let mut hasher = Streebog::new_512();
+                let mut hasher_256 = Streebog::new_256();
+                for part in data.chunks(chunk) {
+                    hasher.update(part);
+                    hasher_256.update(part);
+                }
+
+                assert_eq!(hasher.finalize(), streebog_512(data).to_vec());
+                assert_eq!(hasher_256.finalize(), streebog_256(data).to_vec());
+            }
+        }
+    }
+
+    /// What `Drop` runs: every secret-dependent field ends up zero.
+    #[test]
+    fn hasher_wipe() {
+        let mut hasher = Streebog::new_256();
+        hasher.update(&[0xa5; 100]);
+        hasher.zeroize();
+
+        for state in [hasher.hash, hasher.n, hasher.sigma, hasher.buffer] {
+            assert_eq!(state, [0u8; 64]);
+        }
+        assert_eq!(hasher.filled, 0);
+    }
+
+    fn test_curve() -> Curve {
+        let p = BigInt::from_str(
+            "57896044618658097711785492504343953926634992332820282019728792003956564821041",
+        )
+        .unwrap();
+        let a = BigInt::from_str("7").unwrap();
+        let b = BigInt::from_str(
+            "43308876546767276905765904595650931995942111794451039583252968842033849580414",
+        )
+        .unwrap();
+        let m = BigInt::from_str(
+            "57896044618658097711785492504343953927082934583725450622380973592137631069619",
+        )
+        .unwrap();
+        let q = BigInt::from_str(
+            "57896044618658097711785492504343953927082934583725450622380973592137631069619",
+        )
+        .unwrap();
+
+        let point = Point::new(
+            BigInt::from_str("2").unwrap(),
+            BigInt::from_str(
+                "4018974056539037503335449422937059775635739389905545080690979365213431566280",
+            )
+            .unwrap(),
+        );
+
+        Curve::new(a, b, p, m, q, point)
+    }
+
+    #[test]
+    fn signer() {
+        let d = BigInt::from_str(
+            "55441196065363246126355624130324183196576709222340016572108097750006097525544",
+        )
+        .unwrap();
+
+        let key = PrivateKey::new(d, test_curve()).unwrap();
+        let sign = Signature::sign(&MSG, &key);
+        let is_verified = sign.verify(&MSG, &key.public_key());
+
+        assert!(is_verified);
+    }
+
+    #[test]
+    fn deterministic_signer() {
+        let d = BigInt::from_str(
+            "55441196065363246126355624130324183196576709222340016572108097750006097525544",
+        )
+        .unwrap();
+        let key = PrivateKey::new(d, test_curve()).unwrap();
+        let public = key.public_key();
+
+        // Regenerated when messages came to be hashed by curve size in
+        // memory order; the original vectors are in `legacy_digest`.
+        let sign = Signature::sign_with_nonce(&MSG, &key, Nonce::Deterministic);
+        assert_eq!(
+            sign.r.to_str_radix(16),
+            "888fddbaed7418df7085877505cb201ed75cd2266d658ddf6aacb9a8465e7b4"
+        );
+        assert_eq!(
+            sign.s.to_str_radix(16),
+            "5c86ee96bcfb18e1fb747e7be78e86676d6801c7439df6601ab52b08caa60d4b"
+        );
+        assert!(sign.verify(&MSG, &public));
+
+        let again = Signature::sign_with_nonce(&MSG, &key, Nonce::Deterministic);
+        assert_eq!((again.r, again.s), (sign.r.clone(), sign.s.clone()));
+
+        let hedged = Signature::sign_with_nonce(&MSG, &key, Nonce::Hedged);
+        assert_ne!(hedged.r, sign.r);
+        assert!(hedged.verify(&MSG, &public));
+    }
+
+    /// The deterministic signature of `MSG` from before the digest change,
+    /// over `hash_512` of the big-endian notation: it no longer passes
+    /// `verify`, but still passes `verify_prehashed` over that digest
+    /// reduced mod `q`.
+    #[test]
+    fn legacy_digest() {
+        let d = BigInt::from_str(
+            "55441196065363246126355624130324183196576709222340016572108097750006097525544",
+        )
+        .unwrap();
+        let curve = test_curve();
+        let public = PrivateKey::new(d, curve.clone()).unwrap().public_key();
+        let hex = |text: &str| BigInt::parse_bytes(text.as_bytes(), 16).unwrap();
+        let alpha = BigInt::from_bytes_le(Sign::Plus, &hash_512(&MSG));
+        let digest = to_le_bytes(&(alpha % &curve.q), curve.size());
+
+        let legacy = Signature::new(
+            hex("5d83e19a9b3d76f469276d87829d7d7400742a313bb51653ebbcf056bf7e0379"),
+            hex("2982118c5e71bbd8fb93f464d16e5db1dfc7b655eead3b61bc0d4e73db7fc053"),
+        );
+        assert!(!legacy.verify(&MSG, &public));
+        assert_eq!(legacy.verify_prehashed(&digest, &public), Ok(true));
+    }
+}
@@ -0,0 +1,217 @@
+//! The message authentication code (imitovstavka) of GOST R 34.13-2015,
+//! the CMAC/OMAC1 construction over Kuznyechik or Magma.
+
+use crate::error::Error;
+use crate::modes::BlockCipher;
+use zeroize::Zeroize;
+
+pub struct Mac<C> {
+    cipher: C,
+    k1: Vec<u8>,
+    k2: Vec<u8>,
+    state: Vec<u8>,
+    /// The last block seen, which is held back until it is known whether
+    /// it is the final one.
+    buffer: Vec<u8>,
+}
+
+impl<C: BlockCipher> Mac<C> {
+    pub fn new(cipher: C) -> Self {
+        let mut r = vec![0u8; C::BLOCK_SIZE];
+        cipher.encrypt_block(&mut r);
+        let k1 = double(&r);
+        let k2 = double(&k1);
+        r.zeroize();
+
+        Self {
+            cipher,
+            k1,
+            k2,
+            state: vec![0; C::BLOCK_SIZE],
+            buffer: Vec::with_capacity(C::BLOCK_SIZE),
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.buffer.len() == C::BLOCK_SIZE {
+                xor(&mut self.state, &self.buffer);
+                self.cipher.encrypt_block(&mut self.state);
+                self.buffer.clear();
+            }
+
+            let take = (C::BLOCK_SIZE - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+        }
+    }
+
+    /// The first `len` bytes of the MAC, where `0 < len <= n`.
+    pub fn finalize(mut self, len: usize) -> Result<Vec<u8>, Error> {
+        check_tag_len::<C>(1, len)?;
+
+        if self.buffer.len() == C::BLOCK_SIZE {
+            xor(&mut self.state, &self.k1);
+        } else {
+            self.buffer.push(0x80);
+            self.buffer.resize(C::BLOCK_SIZE, 0);
+            xor(&mut self.state, &self.k2);
+        }
+        xor(&mut self.state, &self.buffer);
+        self.cipher.encrypt_block(&mut self.state);
+
+        Ok(self.state[..len].to_vec())
+    }
+
+    /// Checks a MAC truncated to the length of `tag`, in constant time.
+    pub fn verify(self, tag: &[u8]) -> Result<(), Error> {
+        if tag.is_empty() || tag.len() > C::BLOCK_SIZE {
+            return Err(Error::InvalidTag);
+        }
+
+        if constant_time_eq(&self.finalize(tag.len())?, tag) {
+            Ok(())
+        } else {
+            Err(Error::InvalidTag)
+        }
+    }
+}
+
+impl<C> Drop for Mac<C> {
+    fn drop(&mut self) {
+        self.k1.zeroize();
+        self.k2.zeroize();
+        self.state.zeroize();
+        self.buffer.zeroize();
+    }
+}
+
+/// Checks that a tag of `len` bytes is at least `min` and at most a block.
+pub(crate) fn check_tag_len<C: BlockCipher>(min: usize, len: usize) -> Result<(), Error> {
+    if len < min || len > C::BLOCK_SIZE {
+        return Err(Error::InvalidTagLength {
+            min,
+            max: C::BLOCK_SIZE,
+            len,
+        });
+    }
+
+    Ok(())
+}
+
+/// Compares two byte strings without branching on their contents.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let difference = a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(difference) == 0
+}
+
+/// Multiplication by `x` in GF(2^n): a left shift, reduced by `B_64` or
+/// `B_128` when a bit falls off.
+pub(crate) fn double(block: &[u8]) -> Vec<u8> {
+    let mut output = vec![0u8; block.len()];
+    let mut carry = 0;
+    for (out, byte) in output.iter_mut().zip(block).rev() {
+        *out = byte << 1 | carry;
+        carry = byte >> 7;
+    }
+
+    let reduction = if block.len() == 16 { 0x87 } else { 0x1b };
+    let last = output.len() - 1;
+    output[last] ^= reduction & 0u8.wrapping_sub(carry);
+
+    output
+}
+
+fn xor(data: &mut [u8], other: &[u8]) {
+    for (a, b) in data.iter_mut().zip(other) {
+        *a ^= b;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mac;
+    use crate::error::Error;
+    use crate::kuznyechik::Kuznyechik;
+    use crate::magma::Magma;
+    use crate::modes::Padding;
+    use crate::ByteParse;
+
+    /// GOST R 34.13-2015, A.1.6.
+    #[test]
+    fn kuznyechik() {
+        let key = "8899aabbccddeeff0011223344556677fedcba98765432100123456789abcdef".parse_bytes();
+        let cipher = || Kuznyechik::new(&key.clone().try_into().unwrap());
+        let data = "1122334455667700ffeeddccbbaa9988\
+                    00112233445566778899aabbcceeff0a\
+                    112233445566778899aabbcceeff0a00\
+                    2233445566778899aabbcceeff0a0011"
+            .parse_bytes();
+
+        let mac = Mac::new(cipher());
+        assert_eq!(mac.k1, "297d82bc4d39e3ca0de0573298151dc7".parse_bytes());
+        assert_eq!(mac.k2, "52fb05789a73c7941bc0ae65302a3b8e".parse_bytes());
+
+        let mut mac = Mac::new(cipher());
+        mac.update(&data);
+        assert_eq!(mac.finalize(8).unwrap(), "336f4d296059fbe3".parse_bytes());
+
+        let mut mac = Mac::new(cipher());
+        for part in data.chunks(5) {
+            mac.update(part);
+        }
+        assert_eq!(mac.verify(&"336f4d296059fbe3".parse_bytes()), Ok(()));
+
+        let mut mac = Mac::new(cipher());
+        mac.update(&data[..63]);
+        assert_eq!(
+            mac.verify(&"336f4d296059fbe3".parse_bytes()),
+            Err(Error::InvalidTag)
+        );
+    }
+
+    /// GOST R 34.13-2015, A.2.6.
+    #[test]
+    fn magma() {
+        let key = "ffeeddccbbaa99887766554433221100f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff".parse_bytes();
+        let cipher = || Magma::new(&key.clone().try_into().unwrap());
+        let data = "92def06b3c130a59db54c704f8189d204a98fb2e67a8024c8912409b17b57e41".parse_bytes();
+
+        let mac = Mac::new(cipher());
+        assert_eq!(mac.k1, "5f459b3342521424".parse_bytes());
+        assert_eq!(mac.k2, "be8b366684a42848".parse_bytes());
+
+        let mut mac = Mac::new(cipher());
+        mac.update(&data);
+        assert_eq!(mac.finalize(4).unwrap(), "154e7210".parse_bytes());
+
+        // A partial last block is padded and masked with `K2`.
+        let mut mac = Mac::new(cipher());
+        mac.update(&data[..13]);
+        let mut expected = data[..8].to_vec();
+        cipher().encrypt_block(expected.as_mut_slice().try_into().unwrap());
+        let mut last = data[8..13].to_vec();
+        Padding::Bit.pad(&mut last, 8);
+        for ((a, b), k) in expected.iter_mut().zip(last).zip(&mac.k2) {
+            *a ^= b ^ k;
+        }
+        cipher().encrypt_block(expected.as_mut_slice().try_into().unwrap());
+        assert_eq!(mac.finalize(8).unwrap(), expected);
+
+        assert_eq!(Mac::new(cipher()).verify(&[]), Err(Error::InvalidTag));
+        for len in [0, 9] {
+            assert_eq!(
+                Mac::new(cipher()).finalize(len),
+                Err(Error::InvalidTagLength {
+                    min: 1,
+                    max: 8,
+                    len
+                })
+            );
+        }
+    }
+}
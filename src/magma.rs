@@ -0,0 +1,361 @@
+//! The Magma block cipher of GOST R 34.12-2015: 64-bit blocks, a 256-bit
+//! key and 32 Feistel rounds. With another S-box and little-endian byte
+//! order it is the GOST 28147-89 cipher.
+
+use crate::modes::BlockCipher;
+use crate::oid;
+use zeroize::Zeroize;
+
+pub const MAGMA_BLOCK: usize = 8;
+
+/// The substitution `π_0 .. π_7` of the round function; `π_i` maps nibble
+/// `i` of a word, counting from the least significant one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SBox(pub [[u8; 16]; 8]);
+
+/// Named S-boxes, as `π_0 .. π_7`.
+const NAMED_SBOXES: [(&str, SBox); 5] = [
+    (
+        oid::GOST28147_PARAM_Z,
+        SBox([
+            [12, 4, 6, 2, 10, 5, 11, 9, 14, 8, 13, 7, 0, 3, 15, 1],
+            [6, 8, 2, 3, 9, 10, 5, 12, 1, 14, 4, 7, 11, 13, 0, 15],
+            [11, 3, 5, 8, 2, 15, 10, 13, 14, 1, 7, 4, 12, 9, 6, 0],
+            [12, 8, 2, 1, 13, 4, 15, 6, 7, 0, 10, 5, 3, 14, 9, 11],
+            [7, 15, 5, 10, 8, 1, 6, 13, 0, 9, 3, 14, 11, 4, 2, 12],
+            [5, 13, 15, 6, 9, 2, 12, 10, 11, 7, 8, 1, 4, 3, 14, 0],
+            [8, 14, 2, 5, 6, 9, 1, 12, 15, 4, 11, 0, 13, 10, 3, 7],
+            [1, 7, 14, 13, 0, 5, 8, 3, 4, 15, 10, 6, 9, 12, 11, 2],
+        ]),
+    ),
+    (
+        oid::GOST28147_CRYPTOPRO_A,
+        SBox([
+            [9, 6, 3, 2, 8, 11, 1, 7, 10, 4, 14, 15, 12, 0, 13, 5],
+            [3, 7, 14, 9, 8, 10, 15, 0, 5, 2, 6, 12, 11, 4, 13, 1],
+            [14, 4, 6, 2, 11, 3, 13, 8, 12, 15, 5, 10, 0, 7, 1, 9],
+            [14, 7, 10, 12, 13, 1, 3, 9, 0, 2, 11, 4, 15, 8, 5, 6],
+            [11, 5, 1, 9, 8, 13, 15, 0, 14, 4, 2, 3, 12, 7, 10, 6],
+            [3, 10, 13, 12, 1, 2, 0, 11, 7, 5, 9, 4, 8, 15, 14, 6],
+            [1, 13, 2, 9, 7, 10, 6, 0, 8, 12, 4, 5, 15, 3, 11, 14],
+            [11, 10, 15, 5, 0, 12, 14, 8, 6, 2, 3, 9, 1, 7, 13, 4],
+        ]),
+    ),
+    (
+        oid::GOST28147_CRYPTOPRO_B,
+        SBox([
+            [8, 4, 11, 1, 3, 5, 0, 9, 2, 14, 10, 12, 13, 6, 7, 15],
+            [0, 1, 2, 10, 4, 13, 5, 12, 9, 7, 3, 15, 11, 8, 6, 14],
+            [14, 12, 0, 10, 9, 2, 13, 11, 7, 5, 8, 15, 3, 6, 1, 4],
+            [7, 5, 0, 13, 11, 6, 1, 2, 3, 10, 12, 15, 4, 14, 9, 8],
+            [2, 7, 12, 15, 9, 5, 10, 11, 1, 4, 0, 13, 6, 8, 14, 3],
+            [8, 3, 2, 6, 4, 13, 14, 11, 12, 1, 7, 15, 10, 0, 9, 5],
+            [5, 2, 10, 11, 9, 1, 12, 3, 7, 4, 13, 0, 6, 15, 8, 14],
+            [0, 4, 11, 14, 8, 3, 7, 1, 10, 2, 9, 6, 15, 13, 5, 12],
+        ]),
+    ),
+    (
+        oid::GOST28147_CRYPTOPRO_C,
+        SBox([
+            [1, 11, 12, 2, 9, 13, 0, 15, 4, 5, 8, 14, 10, 7, 6, 3],
+            [0, 1, 7, 13, 11, 4, 5, 2, 8, 14, 15, 12, 9, 10, 6, 3],
+            [8, 2, 5, 0, 4, 9, 15, 10, 3, 7, 12, 13, 6, 14, 1, 11],
+            [3, 6, 0, 1, 5, 13, 10, 8, 11, 2, 9, 7, 14, 15, 12, 4],
+            [8, 13, 11, 0, 4, 5, 1, 2, 9, 3, 12, 14, 6, 15, 10, 7],
+            [12, 9, 11, 1, 8, 14, 2, 4, 7, 3, 6, 5, 10, 0, 15, 13],
+            [10, 9, 6, 8, 13, 14, 2, 0, 15, 3, 5, 11, 4, 1, 12, 7],
+            [7, 4, 0, 5, 10, 2, 15, 14, 12, 6, 1, 11, 13, 9, 3, 8],
+        ]),
+    ),
+    (
+        oid::GOST28147_CRYPTOPRO_D,
+        SBox([
+            [15, 12, 2, 10, 6, 4, 5, 0, 7, 9, 14, 13, 1, 11, 8, 3],
+            [11, 6, 3, 4, 12, 15, 14, 2, 7, 13, 8, 0, 5, 10, 9, 1],
+            [1, 12, 11, 0, 15, 14, 6, 5, 10, 13, 4, 8, 9, 3, 7, 2],
+            [1, 5, 14, 12, 10, 7, 0, 13, 6, 2, 11, 4, 9, 3, 15, 8],
+            [0, 12, 8, 9, 13, 2, 10, 11, 7, 3, 6, 5, 4, 14, 15, 1],
+            [8, 0, 15, 3, 2, 5, 14, 11, 1, 10, 4, 7, 12, 9, 13, 6],
+            [3, 0, 6, 15, 1, 14, 9, 2, 13, 8, 12, 4, 11, 10, 5, 7],
+            [1, 10, 6, 8, 15, 11, 0, 4, 12, 3, 5, 9, 7, 13, 2, 14],
+        ]),
+    ),
+];
+
+impl SBox {
+    /// `id-tc26-gost-28147-param-Z`, the S-box of GOST R 34.12-2015.
+    pub const Z: SBox = NAMED_SBOXES[0].1;
+
+    pub fn from_oid(oid: &str) -> Option<Self> {
+        NAMED_SBOXES
+            .iter()
+            .find(|(name, _)| *name == oid)
+            .map(|(_, sbox)| *sbox)
+    }
+
+    /// Pairs of nibble substitutions, one table per byte of a word.
+    fn byte_tables(&self) -> [[u8; 256]; 4] {
+        let mut tables = [[0u8; 256]; 4];
+        for (index, table) in tables.iter_mut().enumerate() {
+            for (byte, entry) in table.iter_mut().enumerate() {
+                let low = self.0[2 * index][byte & 0x0f];
+                let high = self.0[2 * index + 1][byte >> 4];
+                *entry = high << 4 | low;
+            }
+        }
+
+        tables
+    }
+}
+
+pub struct Magma {
+    keys: [u32; 8],
+    tables: [[u8; 256]; 4],
+    /// GOST 28147-89 byte order: little-endian key words and blocks.
+    reversed: bool,
+}
+
+impl Magma {
+    /// GOST R 34.12-2015 Magma, with the S-box `Z`.
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self::with_sbox(key, &SBox::Z)
+    }
+
+    /// GOST R 34.12-2015 byte order with another S-box.
+    pub fn with_sbox(key: &[u8; 32], sbox: &SBox) -> Self {
+        Self {
+            keys: key_words(key, false),
+            tables: sbox.byte_tables(),
+            reversed: false,
+        }
+    }
+
+    /// GOST 28147-89 in simple substitution mode, as implemented by
+    /// CryptoPro and OpenSSL's GOST engine.
+    pub fn gost28147(key: &[u8; 32], sbox: &SBox) -> Self {
+        Self {
+            keys: key_words(key, true),
+            tables: sbox.byte_tables(),
+            reversed: true,
+        }
+    }
+
+    pub fn encrypt_block(&self, block: &mut [u8; MAGMA_BLOCK]) {
+        let schedule = (0..24).map(|i| i % 8).chain((0..8).rev());
+        self.rounds(block, schedule);
+    }
+
+    pub fn decrypt_block(&self, block: &mut [u8; MAGMA_BLOCK]) {
+        let schedule = (0..8).chain((0..24).rev().map(|i| i % 8));
+        self.rounds(block, schedule);
+    }
+
+    /// Rounds `G[K_1] .. G[K_31]` and `G*[K_32]` for round keys `K_i`
+    /// given as indices into `keys`.
+    fn rounds(&self, block: &mut [u8; MAGMA_BLOCK], schedule: impl Iterator<Item = usize>) {
+        if self.reversed {
+            block.reverse();
+        }
+        let mut a1 = u32::from_be_bytes(block[..4].try_into().unwrap());
+        let mut a0 = u32::from_be_bytes(block[4..].try_into().unwrap());
+
+        for key in schedule {
+            let next = a1 ^ self.g(self.keys[key], a0);
+            a1 = a0;
+            a0 = next;
+        }
+
+        // `G*` does not swap the halves.
+        block[..4].copy_from_slice(&a0.to_be_bytes());
+        block[4..].copy_from_slice(&a1.to_be_bytes());
+        if self.reversed {
+            block.reverse();
+        }
+    }
+
+    /// `g[k](a) = t(a + k) <<< 11`.
+    fn g(&self, key: u32, a: u32) -> u32 {
+        let bytes = a.wrapping_add(key).to_le_bytes();
+        let mut substituted = [0u8; 4];
+        for (index, byte) in bytes.iter().enumerate() {
+            substituted[index] = self.tables[index][*byte as usize];
+        }
+
+        u32::from_le_bytes(substituted).rotate_left(11)
+    }
+}
+
+impl Drop for Magma {
+    fn drop(&mut self) {
+        self.keys.zeroize();
+    }
+}
+
+impl BlockCipher for Magma {
+    const BLOCK_SIZE: usize = MAGMA_BLOCK;
+
+    fn with_key(&self, key: &[u8; 32]) -> Self {
+        Self {
+            keys: key_words(key, self.reversed),
+            tables: self.tables,
+            reversed: self.reversed,
+        }
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        Magma::encrypt_block(self, block.try_into().unwrap());
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        Magma::decrypt_block(self, block.try_into().unwrap());
+    }
+}
+
+fn key_words(key: &[u8; 32], reversed: bool) -> [u32; 8] {
+    let mut keys = [0u32; 8];
+    for (word, bytes) in keys.iter_mut().zip(key.chunks(4)) {
+        let bytes = bytes.try_into().unwrap();
+        *word = if reversed {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        };
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Magma, SBox, NAMED_SBOXES};
+    use crate::oid;
+    use crate::ByteParse;
+
+    fn key() -> [u8; 32] {
+        "ffeeddccbbaa99887766554433221100f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff"
+            .parse_bytes()
+            .try_into()
+            .unwrap()
+    }
+
+    /// GOST R 34.12-2015, A.2.
+    #[test]
+    fn round_function() {
+        let cipher = Magma::new(&key());
+        assert_eq!(
+            cipher.keys,
+            [
+                0xffeeddcc, 0xbbaa9988, 0x77665544, 0x33221100, 0xf0f1f2f3, 0xf4f5f6f7, 0xf8f9fafb,
+                0xfcfdfeff
+            ]
+        );
+
+        // `t` is `g` with a zero key, before the rotation.
+        for (input, output) in [
+            (0xfdb97531, 0x2a196f34),
+            (0x2a196f34, 0xebd9f03a),
+            (0xebd9f03a, 0xb039bb3d),
+            (0xb039bb3d, 0x68695433),
+        ] {
+            assert_eq!(cipher.g(0, input).rotate_right(11), output);
+        }
+
+        for (key, input, output) in [
+            (0x87654321, 0xfedcba98, 0xfdcbc20c),
+            (0xfdcbc20c, 0x87654321, 0x7e791a4b),
+            (0x7e791a4b, 0xfdcbc20c, 0xc76549ec),
+            (0xc76549ec, 0x7e791a4b, 0x9791c849),
+        ] {
+            assert_eq!(cipher.g(key, input), output);
+        }
+    }
+
+    #[test]
+    fn encrypt() {
+        let cipher = Magma::new(&key());
+        let mut block: [u8; 8] = "fedcba9876543210".parse_bytes().try_into().unwrap();
+
+        cipher.encrypt_block(&mut block);
+        assert_eq!(block.to_vec(), "4ee901e5c2d8ca3d".parse_bytes());
+        cipher.decrypt_block(&mut block);
+        assert_eq!(block.to_vec(), "fedcba9876543210".parse_bytes());
+    }
+
+    #[test]
+    fn sboxes() {
+        for (oid, sbox) in NAMED_SBOXES {
+            assert_eq!(SBox::from_oid(oid), Some(sbox));
+            for row in sbox.0 {
+                let mut sorted = row;
+                sorted.sort();
+                assert_eq!(sorted, std::array::from_fn(|i| i as u8), "{}", oid);
+            }
+        }
+        assert_eq!(SBox::from_oid("1.2.3"), None);
+    }
+
+    /// GOST 28147-89 under every named S-box, in its own byte order:
+    /// little-endian key words and block halves. The expected blocks come
+    /// from libgcrypt 1.10 (`GCRY_CIPHER_GOST28147` in ECB mode with
+    /// `gcry_cipher_set_sbox`), an implementation independent of this one.
+    #[test]
+    fn gost28147_sboxes() {
+        let key: [u8; 32] = "0112233445566778899aabbccddeef00112233445566778899aabbccddeeff10"
+            .parse_bytes()
+            .try_into()
+            .unwrap();
+        let plaintext = "f0e1d2c3b4a5968778695a4b3c2d1e0f".parse_bytes();
+
+        for (oid, expected) in [
+            (
+                oid::GOST28147_CRYPTOPRO_A,
+                "2a35c24738cad664096a5bd4e4a193b3",
+            ),
+            (
+                oid::GOST28147_CRYPTOPRO_B,
+                "c34e502c92e91e5d711ea983aa4ca436",
+            ),
+            (
+                oid::GOST28147_CRYPTOPRO_C,
+                "309de5520683cdede7786ef9bfb19002",
+            ),
+            (
+                oid::GOST28147_CRYPTOPRO_D,
+                "f10d7a5e21e0549fefe8ee37127df07f",
+            ),
+            (oid::GOST28147_PARAM_Z, "fbdcb3760c35a27baf3c9ebff691776f"),
+        ] {
+            let cipher = Magma::gost28147(&key, &SBox::from_oid(oid).unwrap());
+            let mut data = plaintext.clone();
+            for block in data.chunks_mut(8) {
+                cipher.encrypt_block(block.try_into().unwrap());
+            }
+            assert_eq!(data, expected.parse_bytes(), "{}", oid);
+
+            for block in data.chunks_mut(8) {
+                cipher.decrypt_block(block.try_into().unwrap());
+            }
+            assert_eq!(data, plaintext, "{}", oid);
+        }
+    }
+
+    /// GOST 28147-89 is Magma with every block and key word byte-reversed.
+    #[test]
+    fn gost28147() {
+        let mut key = key();
+        let magma = Magma::new(&key);
+        for word in key.chunks_mut(4) {
+            word.reverse();
+        }
+        let legacy = Magma::gost28147(&key, &SBox::Z);
+
+        let mut block: [u8; 8] = "fedcba9876543210".parse_bytes().try_into().unwrap();
+        block.reverse();
+        legacy.encrypt_block(&mut block);
+        block.reverse();
+        assert_eq!(block.to_vec(), "4ee901e5c2d8ca3d".parse_bytes());
+
+        magma.decrypt_block(&mut block);
+        assert_eq!(block.to_vec(), "fedcba9876543210".parse_bytes());
+    }
+}
@@ -1,21 +1,43 @@
-use crate::curve::Curve;
-use crate::signature::Signature;
-use bitvec::prelude::*;
+use digital_sign::checksum::{self, Digest};
+use digital_sign::curve::Curve;
+use digital_sign::error::Error;
+use digital_sign::key::{PrivateKey, PublicKey};
+use digital_sign::oid;
+use digital_sign::point::Point;
+use digital_sign::signature::Signature;
 use num_bigint_dig::BigInt;
+use std::io::{self, Read};
+use std::process::ExitCode;
 use std::str::FromStr;
 
-use crate::table::{A, C, PI, TAU};
-
-mod curve;
-mod point;
-mod signature;
-mod table;
-
-const BLOCK_SIZE: usize = 64;
-
-type Block = [u8; BLOCK_SIZE];
+const USAGE: &str = "usage: digital-sign sum [-l] [FILE]...
+       digital-sign sum [-l] --check FILE
+       digital-sign genkey [-l]
+       digital-sign pubkey [KEY]
+       digital-sign sign KEY [FILE]
+       digital-sign verify PUBKEY SIGNATURE [FILE]";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    match args.first().map(String::as_str) {
+        Some("sum") => sum(&args[1..]),
+        Some("genkey") => genkey(&args[1..]),
+        Some("pubkey") => pubkey(&args[1..]),
+        Some("sign") => sign(&args[1..]),
+        Some("verify") => verify(&args[1..]),
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+        None => {
+            demo();
+            ExitCode::SUCCESS
+        }
+    }
+}
 
-fn main() {
+fn demo() {
     let message = "Юра - балерина".bytes().collect::<Vec<u8>>();
     let key = BigInt::from_str(
         "55441196065363246126355624130324183196576709222340016572108097750006097525544",
@@ -39,280 +61,238 @@ fn main() {
             "57896044618658097711785492504343953927082934583725450622380973592137631069619",
         )
         .unwrap(),
+        Point::new(
+            BigInt::from_str("2").unwrap(),
+            BigInt::from_str(
+                "4018974056539037503335449422937059775635739389905545080690979365213431566280",
+            )
+            .unwrap(),
+        ),
     );
-    let sign = Signature::sign(&message, key, curve.clone());
-    println!("{}", sign.verify(&message, curve));
-}
-
-/*fn main() {
-    /*let bytes = include_bytes!("text.txt");
-    let time = std::time::Instant::now();
-    let hash = hash_512(bytes);
-    let elapsed = time.elapsed();
-    println!("Hash: {:x?}", hash);
-    println!("Time: {} ms", elapsed.as_millis());*/
-}*/
-
-fn hash_512(message: &[u8]) -> Block {
-    hash([0u8; 64], message)
+    let key = PrivateKey::new(key, curve).unwrap();
+    let sign = Signature::sign(&message, &key);
+    println!("{}", sign.verify(&message, &key.public_key()));
 }
 
-fn hash_256(message: &[u8]) -> [u8; 32] {
-    let mut output = [0u8; 32];
-    let hash = hash([1u8; 64], message);
-    output[..32].copy_from_slice(&hash[..32]);
+fn sum(args: &[String]) -> ExitCode {
+    let mut digest = Digest::Streebog256;
+    let mut check = None;
+    let mut files = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-l" => digest = Digest::Streebog512,
+            "-c" | "--check" => match args.next() {
+                Some(file) => check = Some(file.clone()),
+                None => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::FAILURE;
+                }
+            },
+            _ => files.push(arg.clone()),
+        }
+    }
 
-    output
+    match check {
+        Some(file) => check_sums(&file),
+        None => print_sums(digest, &files),
+    }
 }
 
-fn hash(iv: Block, message: &[u8]) -> Block {
-    let mut hash = iv;
-    let mut n = [0u8; 64];
-    let mut sigma = [0u8; 64];
+fn print_sums(digest: Digest, files: &[String]) -> ExitCode {
+    let mut status = ExitCode::SUCCESS;
 
-    let mut len = message.len();
-    let mut p = 0;
-
-    let mut n_512 = [0u8; 64];
-    n_512[62] = 0x02;
-
-    while len >= 64 {
-        let mut section = [0u8; 64];
-        for i in 0..64 {
-            section[i] = message[message.len() - (p + 1) * 64 + i];
+    if files.is_empty() {
+        match read_input("-") {
+            Ok(data) => println!("{}", checksum::format_line(digest, &data, "-")),
+            Err(err) => {
+                eprintln!("-: {}", err);
+                status = ExitCode::FAILURE;
+            }
         }
-        hash = compression(n, hash, section);
-        n = add(n, n_512);
-        sigma = add(sigma, section);
-
-        len -= 64;
-        p += 1;
     }
 
-    len *= 8;
-    let rest = &message[..(message.len() - p * 64)];
-    let section = padding(rest);
-
-    let mut v = [0u8; 64];
-    let v0 = [0u8; 64];
-    v[63] = (len & 0xFF) as u8;
-    v[62] = (len >> 8) as u8;
-
-    hash = compression(n, hash, section);
+    for file in files {
+        match read_input(file) {
+            Ok(data) => println!("{}", checksum::format_line(digest, &data, file)),
+            Err(err) => {
+                eprintln!("{}: {}", file, err);
+                status = ExitCode::FAILURE;
+            }
+        }
+    }
 
-    n = add(n, v);
-    sigma = add(sigma, section);
+    status
+}
 
-    hash = compression(v0, hash, n);
-    hash = compression(v0, hash, sigma);
+fn check_sums(file: &str) -> ExitCode {
+    let listing = match read_input(file) {
+        Ok(data) => String::from_utf8_lossy(&data).into_owned(),
+        Err(err) => {
+            eprintln!("{}: {}", file, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut malformed = 0;
+    let mut unreadable = 0;
+    let mut failed = 0;
+
+    for line in listing.lines().filter(|line| !line.trim().is_empty()) {
+        let entry = match checksum::parse_line(line) {
+            Some(entry) => entry,
+            None => {
+                malformed += 1;
+                continue;
+            }
+        };
 
-    hash
-}
+        match read_input(&entry.filename) {
+            Ok(data) if entry.matches(&data) => println!("{}: OK", entry.filename),
+            Ok(_) => {
+                println!("{}: FAILED", entry.filename);
+                failed += 1;
+            }
+            Err(err) => {
+                eprintln!("{}: {}", entry.filename, err);
+                println!("{}: FAILED open or read", entry.filename);
+                unreadable += 1;
+            }
+        }
+    }
 
-fn padding(m: &[u8]) -> Block {
-    let mut output = [0u8; BLOCK_SIZE];
-    for i in 0..m.len() {
-        output[BLOCK_SIZE - m.len() + i] = m[i]
+    if malformed > 0 {
+        eprintln!("WARNING: {} line(s) are improperly formatted", malformed);
     }
-    if m.len() < BLOCK_SIZE {
-        output[BLOCK_SIZE - m.len() - 1] = 0x01;
+    if unreadable > 0 {
+        eprintln!("WARNING: {} listed file(s) could not be read", unreadable);
+    }
+    if failed > 0 {
+        eprintln!("WARNING: {} computed checksum(s) did NOT match", failed);
     }
 
-    output
-}
-
-pub fn add(l: Block, r: Block) -> Block {
-    let mut result = [0u8; 64];
-    let mut t = 0i32;
-    for i in (0..64).rev() {
-        t = l[i] as i32 + r[i] as i32 + (t >> 8);
-        result[i] = (t & 0xFF) as u8;
+    if malformed + unreadable + failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
     }
-    result
 }
 
-fn xor(k: Block, a: Block) -> Block {
-    let mut output = [0u8; BLOCK_SIZE];
-    for i in 0..BLOCK_SIZE {
-        output[i] = k[i] ^ a[i];
-    }
+fn genkey(args: &[String]) -> ExitCode {
+    let curve = match args {
+        [] => oid::CRYPTOPRO_A,
+        [flag] if flag == "-l" => oid::TC26_512_A,
+        _ => return usage(),
+    };
 
-    output
+    let key = PrivateKey::generate(Curve::from_oid(curve).unwrap());
+    print_result(key.to_pkcs8_pem())
 }
 
-fn bijective(a: Block) -> Block {
-    let mut output = [0u8; BLOCK_SIZE];
-    for i in 0..BLOCK_SIZE {
-        output[i] = PI[a[i] as usize];
-    }
+fn pubkey(args: &[String]) -> ExitCode {
+    let file = match args {
+        [] => "-",
+        [file] => file,
+        _ => return usage(),
+    };
 
-    output
+    match read_text(file).and_then(|text| load(file, PrivateKey::from_pkcs8_pem(&text))) {
+        Ok(key) => print_result(key.public_key().to_public_key_pem()),
+        Err(err) => fail(err),
+    }
 }
 
-fn permutation(a: Block) -> Block {
-    let mut output = [0u8; BLOCK_SIZE];
-    for i in 0..BLOCK_SIZE {
-        output[i] = a[TAU[i]];
+fn sign(args: &[String]) -> ExitCode {
+    let (key_file, file) = match args {
+        [key] => (key, "-"),
+        [key, file] => (key, file.as_str()),
+        _ => return usage(),
+    };
+
+    let key = match read_text(key_file)
+        .and_then(|text| load(key_file, PrivateKey::from_pkcs8_pem(&text)))
+    {
+        Ok(key) => key,
+        Err(err) => return fail(err),
+    };
+
+    match read_input(file) {
+        Ok(data) => {
+            print!("{}", Signature::sign(&data, &key).to_pem(key.curve()));
+            ExitCode::SUCCESS
+        }
+        Err(err) => fail(format!("{}: {}", file, err)),
     }
-
-    output
 }
 
-fn linear(a: Block) -> Block {
-    let mut output = [0u8; 64];
-
-    for i in 0..8 {
-        let mut t = 0u64;
-        let mut temp = [0u8; 8];
-
-        for j in 0..8 {
-            temp[j] = a[i * 8 + j];
+fn verify(args: &[String]) -> ExitCode {
+    let (key_file, signature_file, file) = match args {
+        [key, signature] => (key, signature, "-"),
+        [key, signature, file] => (key, signature, file.as_str()),
+        _ => return usage(),
+    };
+
+    let loaded = read_text(key_file)
+        .and_then(|text| load(key_file, PublicKey::from_public_key_pem(&text)))
+        .and_then(|key| {
+            let text = read_text(signature_file)?;
+            Ok((key, load(signature_file, Signature::from_pem(&text))?))
+        })
+        .and_then(|loaded| match read_input(file) {
+            Ok(data) => Ok((loaded, data)),
+            Err(err) => Err(format!("{}: {}", file, err)),
+        });
+
+    match loaded {
+        Ok(((key, signature), data)) if signature.verify(&data, &key) => {
+            println!("Verified OK");
+            ExitCode::SUCCESS
         }
-        let bits = temp.view_bits::<Msb0>();
-        for j in 0..64 {
-            if bits[j] {
-                t ^= A[j];
-            }
-        }
-
-        let mut t = t.to_ne_bytes();
-        t.reverse();
-        for j in 0..8 {
-            output[i * 8 + j] = t[j];
+        Ok(_) => {
+            println!("Verification failure");
+            ExitCode::FAILURE
         }
+        Err(err) => fail(err),
     }
-
-    output
 }
 
-fn linear_permutation_bijective(a: Block) -> Block {
-    linear(permutation(bijective(a)))
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::FAILURE
 }
 
-fn key_schedule(k: Block, i: usize) -> Block {
-    linear_permutation_bijective(xor(k, C[i]))
+fn fail(message: String) -> ExitCode {
+    eprintln!("{}", message);
+    ExitCode::FAILURE
 }
 
-fn e_transformation(k: Block, m: Block) -> Block {
-    let mut s = xor(k, m);
-    let mut k = k;
-    for i in 0..12 {
-        s = linear_permutation_bijective(s);
-        k = key_schedule(k, i);
-        s = xor(k, s);
+fn print_result(pem: Result<String, Error>) -> ExitCode {
+    match pem {
+        Ok(pem) => {
+            print!("{}", pem);
+            ExitCode::SUCCESS
+        }
+        Err(err) => fail(err.to_string()),
     }
-
-    s
 }
 
-fn compression(n: Block, h: Block, m: Block) -> Block {
-    let k = xor(h, n);
-    let k = linear_permutation_bijective(k);
-    let t = e_transformation(k, m);
-    let t = xor(t, h);
-    xor(t, m)
+fn load<T>(file: &str, result: Result<T, Error>) -> Result<T, String> {
+    result.map_err(|err| format!("{}: {}", file, err))
 }
 
-trait ByteParse {
-    fn parse_bytes(self) -> Vec<u8>;
-}
-
-impl ByteParse for &str {
-    fn parse_bytes(self) -> Vec<u8> {
-        let mut vec = vec![];
-        for i in (0..self.len()).step_by(2) {
-            vec.push(u8::from_str_radix(&self[i..(i + 2)], 16).unwrap())
-        }
-
-        vec
-    }
+fn read_text(file: &str) -> Result<String, String> {
+    read_input(file)
+        .map(|data| String::from_utf8_lossy(&data).into_owned())
+        .map_err(|err| format!("{}: {}", file, err))
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::{hash_512, sign, verify, ByteParse};
-    use num_bigint_dig::BigInt;
-    use std::str::FromStr;
-
-    const MSG: [u8; 63] = [
-        0x32u8, 0x31, 0x30, 0x39, 0x38, 0x37, 0x36, 0x35, 0x34, 0x33, 0x32, 0x31, 0x30, 0x39, 0x38,
-        0x37, 0x36, 0x35, 0x34, 0x33, 0x32, 0x31, 0x30, 0x39, 0x38, 0x37, 0x36, 0x35, 0x34, 0x33,
-        0x32, 0x31, 0x30, 0x39, 0x38, 0x37, 0x36, 0x35, 0x34, 0x33, 0x32, 0x31, 0x30, 0x39, 0x38,
-        0x37, 0x36, 0x35, 0x34, 0x33, 0x32, 0x31, 0x30, 0x39, 0x38, 0x37, 0x36, 0x35, 0x34, 0x33,
-        0x32, 0x31, 0x30,
-    ];
-
-    #[test]
-    fn byte_parser_test() {
-        let message = "fbe2e5f0eee3c820fbeafaebef20fffbf0e1e0f0f520e0ed20e8ece0ebe5f0f2f120fff0eeec20f120faf2fee5e2202ce8f6f3ede220e8e6eee1e8f0f2d1202ce8f0f2e5e220e5d1";
-        let bytes = message.parse_bytes();
-        assert_eq!((bytes[0], bytes[1]), (0xfb, 0xe2));
-    }
-
-    #[test]
-    fn hasher() {
-        let hash_actual = hash_512(&MSG);
-
-        let hash_expected = [
-            0x48u8, 0x6F, 0x64, 0xC1, 0x91, 0x78, 0x79, 0x41, 0x7F, 0xEF, 0x08, 0x2B, 0x33, 0x81,
-            0xA4, 0xE2, 0x11, 0xC3, 0x24, 0xF0, 0x74, 0x65, 0x4C, 0x38, 0x82, 0x3A, 0x7B, 0x76,
-            0xF8, 0x30, 0xAD, 0x00, 0xFA, 0x1F, 0xBA, 0xE4, 0x2B, 0x12, 0x85, 0xC0, 0x35, 0x2F,
-            0x22, 0x75, 0x24, 0xBC, 0x9A, 0xB1, 0x62, 0x54, 0x28, 0x8D, 0xD6, 0x86, 0x3D, 0xCC,
-            0xD5, 0xB9, 0xF5, 0x4A, 0x1A, 0xD0, 0x54, 0x1B,
-        ];
-
-        assert_eq!(hash_actual, hash_expected);
-    }
-
-    #[test]
-    fn signer() {
-        let hash = hash_512(&MSG);
-
-        let p = BigInt::from_str(
-            "57896044618658097711785492504343953926634992332820282019728792003956564821041",
-        )
-        .unwrap();
-        let a = BigInt::from_str("7").unwrap();
-        let b = BigInt::from_str(
-            "43308876546767276905765904595650931995942111794451039583252968842033849580414",
-        )
-        .unwrap();
-        let m = BigInt::from_str(
-            "57896044618658097711785492504343953927082934583725450622380973592137631069619",
-        )
-        .unwrap();
-        let q = BigInt::from_str(
-            "57896044618658097711785492504343953927082934583725450622380973592137631069619",
-        )
-        .unwrap();
-
-        let point = (
-            BigInt::from_str("2").unwrap(),
-            BigInt::from_str(
-                "4018974056539037503335449422937059775635739389905545080690979365213431566280",
-            )
-            .unwrap(),
-        );
-
-        let d = BigInt::from_str(
-            "55441196065363246126355624130324183196576709222340016572108097750006097525544",
-        )
-        .unwrap();
-
-        let check_point = (
-            BigInt::from_str(
-                "57520216126176808443631405023338071176630104906313632182896741342206604859403",
-            )
-            .unwrap(),
-            BigInt::from_str(
-                "17614944419213781543809391949654080031942662045363639260709847859438286763994",
-            )
-            .unwrap(),
-        );
-
-        let (sign, r, s) = sign(&p, &a, &q, &point, &d, hash);
-        let is_verified = verify(&sign, &r, &s, &q, &p, &point, &check_point, &a, hash);
-
-        assert!(is_verified);
+fn read_input(file: &str) -> io::Result<Vec<u8>> {
+    if file == "-" {
+        let mut data = vec![];
+        io::stdin().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        std::fs::read(file)
     }
 }
@@ -0,0 +1,325 @@
+//! The Multilinear Galois Mode of R 1323565.1.026-2019 (RFC 9058):
+//! authenticated encryption with associated data over Kuznyechik or Magma.
+//! Data is encrypted under the counter `E(0 || nonce)`, incremented in its
+//! right half, and authenticated with the keys `E(E(1 || nonce))`, the
+//! counter incremented in its left half, multiplied in GF(2^n).
+
+use crate::error::Error;
+use crate::mac::{check_tag_len, constant_time_eq};
+use crate::modes::{increment, BlockCipher};
+use zeroize::Zeroize;
+
+pub struct Mgm<C> {
+    cipher: C,
+    tag_len: usize,
+}
+
+impl<C: BlockCipher> Mgm<C> {
+    /// A full-block tag.
+    pub fn new(cipher: C) -> Self {
+        Self {
+            cipher,
+            tag_len: C::BLOCK_SIZE,
+        }
+    }
+
+    /// Tags truncated to `tag_len` bytes, where `4 <= tag_len <= n`.
+    pub fn with_tag_len(cipher: C, tag_len: usize) -> Result<Self, Error> {
+        check_tag_len::<C>(4, tag_len)?;
+
+        Ok(Self { cipher, tag_len })
+    }
+
+    /// Encrypts `data` in place and returns the tag. `nonce` is a block
+    /// whose most significant bit is zero; `aad` and `data` together must
+    /// be shorter than `2^(n/2)` bits.
+    pub fn encrypt_detached(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        data: &mut [u8],
+    ) -> Result<Vec<u8>, Error> {
+        self.check_nonce(nonce)?;
+        check_lengths::<C>(aad.len(), data.len())?;
+        self.apply(nonce, data);
+
+        Ok(self.tag(nonce, aad, data))
+    }
+
+    /// Checks `tag` and only then decrypts `data` in place.
+    pub fn decrypt_detached(
+        &self,
+        nonce: &[u8],
+        aad: &[u8],
+        data: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), Error> {
+        self.check_nonce(nonce)?;
+        check_lengths::<C>(aad.len(), data.len())?;
+        if !constant_time_eq(&self.tag(nonce, aad, data), tag) {
+            return Err(Error::InvalidTag);
+        }
+        self.apply(nonce, data);
+
+        Ok(())
+    }
+
+    /// The ciphertext followed by the tag.
+    pub fn encrypt(&self, nonce: &[u8], aad: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut output = data.to_vec();
+        let tag = self.encrypt_detached(nonce, aad, &mut output)?;
+        output.extend_from_slice(&tag);
+
+        Ok(output)
+    }
+
+    /// The plaintext of a ciphertext followed by its tag.
+    pub fn decrypt(&self, nonce: &[u8], aad: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+        if data.len() < self.tag_len {
+            return Err(Error::InvalidTag);
+        }
+        let (ciphertext, tag) = data.split_at(data.len() - self.tag_len);
+        let mut output = ciphertext.to_vec();
+        self.decrypt_detached(nonce, aad, &mut output, tag)?;
+
+        Ok(output)
+    }
+
+    fn check_nonce(&self, nonce: &[u8]) -> Result<(), Error> {
+        if nonce.len() != C::BLOCK_SIZE {
+            return Err(Error::InvalidLength {
+                expected: C::BLOCK_SIZE,
+                actual: nonce.len(),
+            });
+        }
+        if nonce[0] & 0x80 != 0 {
+            return Err(Error::InvalidNonce);
+        }
+
+        Ok(())
+    }
+
+    /// Encryption and decryption: XOR with `E(Y_i)`.
+    fn apply(&self, nonce: &[u8], data: &mut [u8]) {
+        let mut counter = nonce.to_vec();
+        self.cipher.encrypt_block(&mut counter);
+
+        let mut gamma = vec![0u8; C::BLOCK_SIZE];
+        for chunk in data.chunks_mut(C::BLOCK_SIZE) {
+            gamma.copy_from_slice(&counter);
+            self.cipher.encrypt_block(&mut gamma);
+            for (byte, gamma) in chunk.iter_mut().zip(&gamma) {
+                *byte ^= gamma;
+            }
+            increment(&mut counter[C::BLOCK_SIZE / 2..]);
+        }
+        gamma.zeroize();
+    }
+
+    /// `MSB(E(sum H_i ⊗ A_i ⊕ sum H_j ⊗ C_j ⊕ H ⊗ (len(A) || len(C))))`,
+    /// with `A` and `C` padded by zeros to whole blocks.
+    fn tag(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+        let half = C::BLOCK_SIZE / 2;
+        let mut counter = nonce.to_vec();
+        counter[0] |= 0x80;
+        self.cipher.encrypt_block(&mut counter);
+
+        let mut lengths = ((aad.len() as u64 * 8).to_be_bytes()[8 - half..]).to_vec();
+        lengths.extend_from_slice(&(ciphertext.len() as u64 * 8).to_be_bytes()[8 - half..]);
+
+        let mut sum = 0u128;
+        let mut h = vec![0u8; C::BLOCK_SIZE];
+        let blocks = aad
+            .chunks(C::BLOCK_SIZE)
+            .chain(ciphertext.chunks(C::BLOCK_SIZE));
+        for block in blocks.chain([&lengths[..]]) {
+            h.copy_from_slice(&counter);
+            self.cipher.encrypt_block(&mut h);
+            let mut padded = block.to_vec();
+            padded.resize(C::BLOCK_SIZE, 0);
+            sum ^= multiply(to_int(&h), to_int(&padded), C::BLOCK_SIZE);
+            increment(&mut counter[..half]);
+        }
+        h.zeroize();
+
+        let mut tag = sum.to_be_bytes()[16 - C::BLOCK_SIZE..].to_vec();
+        self.cipher.encrypt_block(&mut tag);
+        tag.truncate(self.tag_len);
+
+        tag
+    }
+}
+
+/// The lengths block holds `len(A)` and `len(C)` in `n/2` bits each, so
+/// longer input would be authenticated under a wrapped length.
+fn check_lengths<C: BlockCipher>(aad: usize, data: usize) -> Result<(), Error> {
+    let bits = (aad as u128 + data as u128) * 8;
+    if bits >= 1 << (C::BLOCK_SIZE * 4) {
+        return Err(Error::MessageTooLong);
+    }
+
+    Ok(())
+}
+
+fn to_int(block: &[u8]) -> u128 {
+    block
+        .iter()
+        .fold(0, |value, byte| value << 8 | u128::from(*byte))
+}
+
+/// Multiplication in GF(2^64) modulo `x^64 + x^4 + x^3 + x + 1` or in
+/// GF(2^128) modulo `x^128 + x^7 + x^2 + x + 1`, without branches on the
+/// operands.
+fn multiply(mut a: u128, b: u128, size: usize) -> u128 {
+    let bits = 8 * size;
+    let (mask, reduction) = if bits == 128 {
+        (u128::MAX, 0x87)
+    } else {
+        (u128::from(u64::MAX), 0x1b)
+    };
+
+    let mut product = 0;
+    for i in 0..bits {
+        product ^= a & 0u128.wrapping_sub(b >> i & 1);
+        let carry = a >> (bits - 1) & 1;
+        a = (a << 1) & mask ^ (reduction & 0u128.wrapping_sub(carry));
+    }
+
+    product
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_lengths, Mgm};
+    use crate::error::Error;
+    use crate::kuznyechik::Kuznyechik;
+    use crate::magma::Magma;
+    use crate::ByteParse;
+
+    /// RFC 9058, A.1.
+    #[test]
+    fn kuznyechik() {
+        let key = "8899aabbccddeeff0011223344556677fedcba98765432100123456789abcdef".parse_bytes();
+        let mgm = Mgm::new(Kuznyechik::new(&key.try_into().unwrap()));
+        let nonce = "1122334455667700ffeeddccbbaa9988".parse_bytes();
+        let aad = "02020202020202020101010101010101\
+                   04040404040404040303030303030303\
+                   ea0505050505050505"
+            .parse_bytes();
+        let plaintext = "1122334455667700ffeeddccbbaa9988\
+                         00112233445566778899aabbcceeff0a\
+                         112233445566778899aabbcceeff0a00\
+                         2233445566778899aabbcceeff0a0011\
+                         aabbcc"
+            .parse_bytes();
+        let ciphertext = "a9757b8147956e9055b8a33de89f42fc\
+                          8075d2212bf9fd5bd3f7069aadc16b39\
+                          497ab15915a6ba85936b5d0ea9f6851c\
+                          c60c14d4d3f883d0ab94420695c76deb\
+                          2c7552"
+            .parse_bytes();
+        let tag = "cf5d656f40c34f5c46e8bb0e29fcdb4c".parse_bytes();
+
+        let mut data = plaintext.clone();
+        assert_eq!(
+            mgm.encrypt_detached(&nonce, &aad, &mut data),
+            Ok(tag.clone())
+        );
+        assert_eq!(data, ciphertext);
+        assert_eq!(mgm.decrypt_detached(&nonce, &aad, &mut data, &tag), Ok(()));
+        assert_eq!(data, plaintext);
+
+        let sealed = mgm.encrypt(&nonce, &aad, &plaintext).unwrap();
+        assert_eq!(sealed, [&ciphertext[..], &tag[..]].concat());
+        assert_eq!(mgm.decrypt(&nonce, &aad, &sealed), Ok(plaintext.clone()));
+        assert_eq!(
+            mgm.decrypt(&nonce, &aad[1..], &sealed),
+            Err(Error::InvalidTag)
+        );
+
+        let mut nonce = nonce;
+        nonce[0] |= 0x80;
+        assert_eq!(
+            mgm.encrypt(&nonce, &aad, &plaintext),
+            Err(Error::InvalidNonce)
+        );
+    }
+
+    /// RFC 9058, A.2, with the tag also truncated to 32 bits.
+    #[test]
+    fn magma() {
+        let key = "ffeeddccbbaa99887766554433221100f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff".parse_bytes();
+        let cipher = || Magma::new(&key.clone().try_into().unwrap());
+        let nonce = "12def06b3c130a59".parse_bytes();
+        let aad = "01010101010101010202020202020202\
+                   03030303030303030404040404040404\
+                   0505050505050505ea"
+            .parse_bytes();
+        let plaintext = "ffeeddccbbaa99881122334455667700\
+                         8899aabbcceeff0a0011223344556677\
+                         99aabbcceeff0a001122334455667788\
+                         aabbcceeff0a00112233445566778899\
+                         aabbcc"
+            .parse_bytes();
+        let ciphertext = "c795066c5f9ea03b85113342459185ae\
+                          1f2e00d6bf2b785d940470b8bb9c8e7d\
+                          9a5dd3731f7ddc70ec27cb0ace6fa576\
+                          70f65c646abb75d547aa37c3bcb5c34e\
+                          03bb9c"
+            .parse_bytes();
+        let tag = "a7928069aa10fd10".parse_bytes();
+
+        let mut data = plaintext.clone();
+        let mgm = Mgm::new(cipher());
+        assert_eq!(
+            mgm.encrypt_detached(&nonce, &aad, &mut data),
+            Ok(tag.clone())
+        );
+        assert_eq!(data, ciphertext);
+        assert_eq!(mgm.decrypt_detached(&nonce, &aad, &mut data, &tag), Ok(()));
+        assert_eq!(data, plaintext);
+
+        let truncated = Mgm::with_tag_len(cipher(), 4).unwrap();
+        let sealed = truncated.encrypt(&nonce, &aad, &plaintext).unwrap();
+        assert_eq!(sealed, [&ciphertext[..], &tag[..4]].concat());
+        assert_eq!(truncated.decrypt(&nonce, &aad, &sealed), Ok(plaintext));
+        assert_eq!(
+            truncated.decrypt(&nonce, &aad, &sealed[..3]),
+            Err(Error::InvalidTag)
+        );
+
+        for len in [3, 9] {
+            assert_eq!(
+                Mgm::with_tag_len(cipher(), len).err(),
+                Some(Error::InvalidTagLength {
+                    min: 4,
+                    max: 8,
+                    len
+                })
+            );
+        }
+    }
+
+    /// `2^(n/2)` bits is `2^29` bytes for Magma and `2^61` for Kuznyechik.
+    #[test]
+    fn lengths() {
+        assert_eq!(check_lengths::<Magma>(1 << 28, (1 << 28) - 1), Ok(()));
+        assert_eq!(
+            check_lengths::<Magma>(1 << 28, 1 << 28),
+            Err(Error::MessageTooLong)
+        );
+        assert_eq!(
+            check_lengths::<Magma>(0, 1 << 29),
+            Err(Error::MessageTooLong)
+        );
+        assert_eq!(check_lengths::<Kuznyechik>(0, (1 << 61) - 1), Ok(()));
+        assert_eq!(
+            check_lengths::<Kuznyechik>(1 << 60, 1 << 60),
+            Err(Error::MessageTooLong)
+        );
+        assert_eq!(
+            check_lengths::<Kuznyechik>(usize::MAX, usize::MAX),
+            Err(Error::MessageTooLong)
+        );
+    }
+}
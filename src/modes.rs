@@ -0,0 +1,644 @@
+//! Modes of operation of GOST R 34.13-2015 for Kuznyechik and Magma.
+//! Unlike their NIST namesakes, OFB, CBC and CFB keep a register of `m`
+//! bytes, which may hold several blocks, and the stream modes use only the
+//! first `s` bytes of every encrypted block. Both parameters are in bytes.
+
+use crate::error::Error;
+use zeroize::Zeroize;
+
+pub trait BlockCipher {
+    const BLOCK_SIZE: usize;
+
+    /// The same cipher under another key; Magma keeps its S-box and byte
+    /// order.
+    fn with_key(&self, key: &[u8; 32]) -> Self;
+
+    /// Panics unless `block` is `BLOCK_SIZE` bytes long.
+    fn encrypt_block(&self, block: &mut [u8]);
+
+    /// Panics unless `block` is `BLOCK_SIZE` bytes long.
+    fn decrypt_block(&self, block: &mut [u8]);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    /// Procedure 1: zeros up to a whole block. It cannot be removed
+    /// unless the length of the data is known.
+    Zeros,
+    /// Procedure 2: a one bit, then zeros up to a whole block. A whole
+    /// block is added to data that already fills whole blocks.
+    Bit,
+    /// Procedure 3: nothing for data that fills whole blocks, otherwise
+    /// procedure 2. This is the padding of the MAC.
+    BitIfPartial,
+}
+
+impl Padding {
+    pub fn pad(self, data: &mut Vec<u8>, block: usize) {
+        let partial = !data.len().is_multiple_of(block);
+        match self {
+            Padding::Zeros if !partial => return,
+            Padding::BitIfPartial if !partial => return,
+            Padding::Zeros => {}
+            Padding::Bit | Padding::BitIfPartial => data.push(0x80),
+        }
+        data.resize(data.len().div_ceil(block) * block, 0);
+    }
+
+    /// Removes procedure 2 padding.
+    pub fn unpad_bit(data: &mut Vec<u8>, block: usize) -> Result<(), Error> {
+        if data.is_empty() || !data.len().is_multiple_of(block) {
+            return Err(Error::Unaligned {
+                block,
+                actual: data.len(),
+            });
+        }
+
+        let last = data.len() - block;
+        match data[last..].iter().rposition(|byte| *byte != 0) {
+            Some(marker) if data[last + marker] == 0x80 => {
+                data.truncate(last + marker);
+                Ok(())
+            }
+            _ => Err(Error::InvalidPadding),
+        }
+    }
+}
+
+/// Electronic codebook: every block on its own.
+pub struct Ecb<C> {
+    cipher: C,
+}
+
+/// Counter mode: the counter starts at `iv || 0` and `s` bytes of its
+/// encryption mask `s` bytes of data.
+pub struct Ctr<C> {
+    cipher: C,
+    counter: Vec<u8>,
+    keystream: Keystream,
+}
+
+/// Output feedback: the register of `m` bytes shifts in the encryption of
+/// its first block, and `s` bytes of that encryption mask the data.
+pub struct Ofb<C> {
+    cipher: C,
+    register: Vec<u8>,
+    keystream: Keystream,
+}
+
+/// Cipher block chaining with a register of `m` bytes: a block is masked
+/// by the ciphertext `m / n` blocks back.
+pub struct Cbc<C> {
+    cipher: C,
+    register: Vec<u8>,
+}
+
+/// Cipher feedback: `s` bytes of the encryption of the register's first
+/// block mask the data, and the register shifts in `s` bytes of
+/// ciphertext.
+pub struct Cfb<C> {
+    cipher: C,
+    register: Vec<u8>,
+    gamma: Vec<u8>,
+    feedback: Vec<u8>,
+}
+
+/// The unused part of the last segment of gamma.
+pub(crate) struct Keystream {
+    gamma: Vec<u8>,
+    used: usize,
+}
+
+impl<C: BlockCipher> Ecb<C> {
+    pub fn new(cipher: C) -> Self {
+        Self { cipher }
+    }
+
+    pub fn encrypt(&self, data: &mut [u8]) -> Result<(), Error> {
+        for block in blocks::<C>(data)? {
+            self.cipher.encrypt_block(block);
+        }
+
+        Ok(())
+    }
+
+    pub fn decrypt(&self, data: &mut [u8]) -> Result<(), Error> {
+        for block in blocks::<C>(data)? {
+            self.cipher.decrypt_block(block);
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    /// `iv` is half a block and `0 < segment <= n`.
+    pub fn new(cipher: C, iv: &[u8], segment: usize) -> Result<Self, Error> {
+        if iv.len() != C::BLOCK_SIZE / 2 {
+            return Err(Error::InvalidLength {
+                expected: C::BLOCK_SIZE / 2,
+                actual: iv.len(),
+            });
+        }
+        let mut counter = iv.to_vec();
+        counter.resize(C::BLOCK_SIZE, 0);
+
+        Ok(Self {
+            cipher,
+            counter,
+            keystream: Keystream::new::<C>(segment)?,
+        })
+    }
+
+    /// Encrypts or decrypts the next part of the data in place.
+    pub fn apply(&mut self, data: &mut [u8]) {
+        let Self {
+            cipher,
+            counter,
+            keystream,
+        } = self;
+        keystream.apply(data, |gamma| {
+            let mut block = counter.clone();
+            cipher.encrypt_block(&mut block);
+            gamma.copy_from_slice(&block[..gamma.len()]);
+            block.zeroize();
+            increment(counter);
+        });
+    }
+}
+
+impl<C: BlockCipher> Ofb<C> {
+    /// `iv` is the initial register of `m` bytes, a whole number of
+    /// blocks, and `0 < segment <= n`.
+    pub fn new(cipher: C, iv: &[u8], segment: usize) -> Result<Self, Error> {
+        Ok(Self {
+            cipher,
+            register: register::<C>(iv)?,
+            keystream: Keystream::new::<C>(segment)?,
+        })
+    }
+
+    /// Encrypts or decrypts the next part of the data in place.
+    pub fn apply(&mut self, data: &mut [u8]) {
+        let Self {
+            cipher,
+            register,
+            keystream,
+        } = self;
+        keystream.apply(data, |gamma| {
+            let mut block = register[..C::BLOCK_SIZE].to_vec();
+            cipher.encrypt_block(&mut block);
+            gamma.copy_from_slice(&block[..gamma.len()]);
+            shift(register, &block);
+            block.zeroize();
+        });
+    }
+}
+
+impl<C: BlockCipher> Cbc<C> {
+    /// `iv` is the initial register of `m` bytes, a whole number of
+    /// blocks.
+    pub fn new(cipher: C, iv: &[u8]) -> Result<Self, Error> {
+        Ok(Self {
+            cipher,
+            register: register::<C>(iv)?,
+        })
+    }
+
+    /// Encrypts the next whole blocks in place.
+    pub fn encrypt(&mut self, data: &mut [u8]) -> Result<(), Error> {
+        for block in blocks::<C>(data)? {
+            xor(block, &self.register);
+            self.cipher.encrypt_block(block);
+            shift(&mut self.register, block);
+        }
+
+        Ok(())
+    }
+
+    /// Decrypts the next whole blocks in place.
+    pub fn decrypt(&mut self, data: &mut [u8]) -> Result<(), Error> {
+        for block in blocks::<C>(data)? {
+            let ciphertext = block.to_vec();
+            self.cipher.decrypt_block(block);
+            xor(block, &self.register);
+            shift(&mut self.register, &ciphertext);
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: BlockCipher> Cfb<C> {
+    /// `iv` is the initial register of `m >= n` bytes and
+    /// `0 < segment <= n`.
+    pub fn new(cipher: C, iv: &[u8], segment: usize) -> Result<Self, Error> {
+        if iv.len() < C::BLOCK_SIZE {
+            return Err(Error::InvalidLength {
+                expected: C::BLOCK_SIZE,
+                actual: iv.len(),
+            });
+        }
+        check_segment::<C>(segment)?;
+
+        Ok(Self {
+            cipher,
+            register: iv.to_vec(),
+            gamma: vec![0; segment],
+            feedback: Vec::with_capacity(segment),
+        })
+    }
+
+    /// Encrypts the next part of the data in place.
+    pub fn encrypt(&mut self, data: &mut [u8]) {
+        for byte in data {
+            *byte ^= self.next_gamma();
+            self.push_feedback(*byte);
+        }
+    }
+
+    /// Decrypts the next part of the data in place.
+    pub fn decrypt(&mut self, data: &mut [u8]) {
+        for byte in data {
+            let ciphertext = *byte;
+            *byte ^= self.next_gamma();
+            self.push_feedback(ciphertext);
+        }
+    }
+
+    fn next_gamma(&mut self) -> u8 {
+        if self.feedback.is_empty() {
+            let mut block = self.register[..C::BLOCK_SIZE].to_vec();
+            self.cipher.encrypt_block(&mut block);
+            let segment = self.gamma.len();
+            self.gamma.copy_from_slice(&block[..segment]);
+            block.zeroize();
+        }
+
+        self.gamma[self.feedback.len()]
+    }
+
+    fn push_feedback(&mut self, ciphertext: u8) {
+        self.feedback.push(ciphertext);
+        if self.feedback.len() == self.gamma.len() {
+            shift(&mut self.register, &self.feedback);
+            self.feedback.clear();
+        }
+    }
+}
+
+impl Keystream {
+    pub(crate) fn new<C: BlockCipher>(segment: usize) -> Result<Self, Error> {
+        check_segment::<C>(segment)?;
+
+        Ok(Self {
+            gamma: vec![0; segment],
+            used: segment,
+        })
+    }
+
+    /// XORs `data` with the gamma, filling a new segment by `next` once
+    /// the last one is used up.
+    pub(crate) fn apply(&mut self, data: &mut [u8], mut next: impl FnMut(&mut [u8])) {
+        for byte in data {
+            if self.used == self.gamma.len() {
+                next(&mut self.gamma);
+                self.used = 0;
+            }
+            *byte ^= self.gamma[self.used];
+            self.used += 1;
+        }
+    }
+
+    pub(crate) fn zeroize(&mut self) {
+        self.gamma.zeroize();
+    }
+}
+
+impl<C> Drop for Ctr<C> {
+    fn drop(&mut self) {
+        self.keystream.zeroize();
+    }
+}
+
+impl<C> Drop for Ofb<C> {
+    fn drop(&mut self) {
+        self.register.zeroize();
+        self.keystream.zeroize();
+    }
+}
+
+impl<C> Drop for Cfb<C> {
+    fn drop(&mut self) {
+        self.gamma.zeroize();
+    }
+}
+
+fn blocks<C: BlockCipher>(data: &mut [u8]) -> Result<std::slice::ChunksExactMut<'_, u8>, Error> {
+    if !data.len().is_multiple_of(C::BLOCK_SIZE) {
+        return Err(Error::Unaligned {
+            block: C::BLOCK_SIZE,
+            actual: data.len(),
+        });
+    }
+
+    Ok(data.chunks_exact_mut(C::BLOCK_SIZE))
+}
+
+fn register<C: BlockCipher>(iv: &[u8]) -> Result<Vec<u8>, Error> {
+    if iv.is_empty() || !iv.len().is_multiple_of(C::BLOCK_SIZE) {
+        return Err(Error::Unaligned {
+            block: C::BLOCK_SIZE,
+            actual: iv.len(),
+        });
+    }
+
+    Ok(iv.to_vec())
+}
+
+fn check_segment<C: BlockCipher>(segment: usize) -> Result<(), Error> {
+    if segment == 0 || segment > C::BLOCK_SIZE {
+        return Err(Error::InvalidSegment {
+            block: C::BLOCK_SIZE,
+            actual: segment,
+        });
+    }
+
+    Ok(())
+}
+
+/// Adds one to a big-endian number, wrapping around.
+pub(crate) fn increment(number: &mut [u8]) {
+    for byte in number.iter_mut().rev() {
+        *byte = byte.wrapping_add(1);
+        if *byte != 0 {
+            break;
+        }
+    }
+}
+
+/// Drops the first `input.len()` bytes of the register and appends
+/// `input`.
+fn shift(register: &mut Vec<u8>, input: &[u8]) {
+    register.drain(..input.len());
+    register.extend_from_slice(input);
+}
+
+fn xor(data: &mut [u8], other: &[u8]) {
+    for (a, b) in data.iter_mut().zip(other) {
+        *a ^= b;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockCipher, Cbc, Cfb, Ctr, Ecb, Ofb, Padding};
+    use crate::error::Error;
+    use crate::kuznyechik::Kuznyechik;
+    use crate::magma::Magma;
+    use crate::ByteParse;
+
+    fn kuznyechik() -> Kuznyechik {
+        let key = "8899aabbccddeeff0011223344556677fedcba98765432100123456789abcdef".parse_bytes();
+        Kuznyechik::new(&key.try_into().unwrap())
+    }
+
+    fn magma() -> Magma {
+        let key = "ffeeddccbbaa99887766554433221100f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff".parse_bytes();
+        Magma::new(&key.try_into().unwrap())
+    }
+
+    /// Checks one mode both ways against a vector.
+    fn check(
+        plaintext: &str,
+        ciphertext: &str,
+        mut encrypt: impl FnMut(&mut [u8]),
+        mut decrypt: impl FnMut(&mut [u8]),
+    ) {
+        let mut data = plaintext.parse_bytes();
+        encrypt(&mut data);
+        assert_eq!(data, ciphertext.parse_bytes());
+        decrypt(&mut data);
+        assert_eq!(data, plaintext.parse_bytes());
+    }
+
+    /// GOST R 34.13-2015, A.1.
+    #[test]
+    fn kuznyechik_modes() {
+        let plaintext = "1122334455667700ffeeddccbbaa9988\
+                         00112233445566778899aabbcceeff0a\
+                         112233445566778899aabbcceeff0a00\
+                         2233445566778899aabbcceeff0a0011";
+        let iv = "1234567890abcef0a1b2c3d4e5f0011223344556677889901213141516171819".parse_bytes();
+
+        let ecb = Ecb::new(kuznyechik());
+        check(
+            plaintext,
+            "7f679d90bebc24305a468d42b9d4edcd\
+             b429912c6e0032f9285452d76718d08b\
+             f0ca33549d247ceef3f5a5313bd4b157\
+             d0b09ccde830b9eb3a02c4c5aa8ada98",
+            |data| ecb.encrypt(data).unwrap(),
+            |data| ecb.decrypt(data).unwrap(),
+        );
+
+        let ctr = || Ctr::new(kuznyechik(), &iv[..8], 16).unwrap();
+        check(
+            plaintext,
+            "f195d8bec10ed1dbd57b5fa240bda1b8\
+             85eee733f6a13e5df33ce4b33c45dee4\
+             a5eae88be6356ed3d5e877f13564a3a5\
+             cb91fab1f20cbab6d1c6d15820bdba73",
+            |data| ctr().apply(data),
+            |data| ctr().apply(data),
+        );
+
+        let ofb = || Ofb::new(kuznyechik(), &iv, 16).unwrap();
+        check(
+            plaintext,
+            "81800a59b1842b24ff1f795e897abd95\
+             ed5b47a7048cfab48fb521369d9326bf\
+             66a257ac3ca0b8b1c80fe7fc10288a13\
+             203ebbc066138660a0292243f6903150",
+            |data| ofb().apply(data),
+            |data| ofb().apply(data),
+        );
+
+        let cbc = || Cbc::new(kuznyechik(), &iv).unwrap();
+        check(
+            plaintext,
+            "689972d4a085fa4d90e52e3d6d7dcc27\
+             2826e661b478eca6af1e8e448d5ea5ac\
+             fe7babf1e91999e85640e8b0f49d90d0\
+             167688065a895c631a2d9a1560b63970",
+            |data| cbc().encrypt(data).unwrap(),
+            |data| cbc().decrypt(data).unwrap(),
+        );
+
+        let cfb = || Cfb::new(kuznyechik(), &iv, 16).unwrap();
+        check(
+            plaintext,
+            "81800a59b1842b24ff1f795e897abd95\
+             ed5b47a7048cfab48fb521369d9326bf\
+             79f2a8eb5cc68d38842d264e97a238b5\
+             4ffebecd4e922de6c75bd9dd44fbf4d1",
+            |data| cfb().encrypt(data),
+            |data| cfb().decrypt(data),
+        );
+    }
+
+    /// GOST R 34.13-2015, A.2.
+    #[test]
+    fn magma_modes() {
+        let plaintext = "92def06b3c130a59db54c704f8189d204a98fb2e67a8024c8912409b17b57e41";
+        let iv = "1234567890abcdef234567890abcdef134567890abcdef12".parse_bytes();
+
+        let ecb = Ecb::new(magma());
+        check(
+            plaintext,
+            "2b073f0494f372a0de70e715d3556e4811d8d9e9eacfbc1e7c68260996c67efb",
+            |data| ecb.encrypt(data).unwrap(),
+            |data| ecb.decrypt(data).unwrap(),
+        );
+
+        let ctr = || Ctr::new(magma(), &iv[..4], 8).unwrap();
+        check(
+            plaintext,
+            "4e98110c97b7b93c3e250d93d6e85d69136d868807b2dbef568eb680ab52a12d",
+            |data| ctr().apply(data),
+            |data| ctr().apply(data),
+        );
+
+        let ofb = || Ofb::new(magma(), &iv[..16], 8).unwrap();
+        check(
+            plaintext,
+            "db37e0e266903c830d46644c1f9a089ca0f83062430e327ec824efb8bd4fdb05",
+            |data| ofb().apply(data),
+            |data| ofb().apply(data),
+        );
+
+        let cbc = || Cbc::new(magma(), &iv).unwrap();
+        check(
+            plaintext,
+            "96d1b05eea683919aff76129abb937b95058b4a1c4bc001920b78b1a7cd7e667",
+            |data| cbc().encrypt(data).unwrap(),
+            |data| cbc().decrypt(data).unwrap(),
+        );
+
+        let cfb = || Cfb::new(magma(), &iv[..16], 8).unwrap();
+        check(
+            plaintext,
+            "db37e0e266903c830d46644c1f9a089c24bdd2035315d38bbcc0321421075505",
+            |data| cfb().encrypt(data),
+            |data| cfb().decrypt(data),
+        );
+    }
+
+    /// Feeding the data in uneven parts gives the same result, for any
+    /// segment size.
+    #[test]
+    fn streaming() {
+        let plaintext: Vec<u8> = (0..77).collect();
+        let iv = "1234567890abcef0a1b2c3d4e5f00112".parse_bytes();
+
+        for segment in [1, 5, 8] {
+            let mut whole = plaintext.clone();
+            Cfb::new(magma(), &iv[..12], segment)
+                .unwrap()
+                .encrypt(&mut whole);
+
+            let mut parts = plaintext.clone();
+            let mut cfb = Cfb::new(magma(), &iv[..12], segment).unwrap();
+            let mut ofb = Ofb::new(magma(), &iv[..16], segment).unwrap();
+            let mut ctr = Ctr::new(magma(), &iv[..4], segment).unwrap();
+            for part in parts.chunks_mut(7) {
+                cfb.encrypt(part);
+            }
+            assert_eq!(parts, whole);
+
+            let mut cfb = Cfb::new(magma(), &iv[..12], segment).unwrap();
+            for part in parts.chunks_mut(3) {
+                cfb.decrypt(part);
+            }
+            assert_eq!(parts, plaintext);
+
+            for part in parts.chunks_mut(6) {
+                ofb.apply(part);
+                ctr.apply(part);
+            }
+            let mut ofb = Ofb::new(magma(), &iv[..16], segment).unwrap();
+            let mut ctr = Ctr::new(magma(), &iv[..4], segment).unwrap();
+            ofb.apply(&mut parts);
+            ctr.apply(&mut parts);
+            assert_eq!(parts, plaintext);
+        }
+
+        let mut cbc = Cbc::new(kuznyechik(), &iv).unwrap();
+        let mut data = plaintext[..64].to_vec();
+        for part in data.chunks_mut(32) {
+            cbc.encrypt(part).unwrap();
+        }
+        Cbc::new(kuznyechik(), &iv)
+            .unwrap()
+            .decrypt(&mut data)
+            .unwrap();
+        assert_eq!(data, plaintext[..64]);
+
+        assert_eq!(
+            cbc.encrypt(&mut data[..15]),
+            Err(Error::Unaligned {
+                block: 16,
+                actual: 15
+            })
+        );
+        assert!(Cbc::new(kuznyechik(), &iv[..8]).is_err());
+        assert!(Ctr::new(kuznyechik(), &iv, 16).is_err());
+
+        for segment in [0, 17] {
+            let error = Some(Error::InvalidSegment {
+                block: 16,
+                actual: segment,
+            });
+            assert_eq!(Ctr::new(kuznyechik(), &iv[..8], segment).err(), error);
+            assert_eq!(Ofb::new(kuznyechik(), &iv, segment).err(), error);
+            assert_eq!(Cfb::new(kuznyechik(), &iv, segment).err(), error);
+        }
+    }
+
+    #[test]
+    fn padding() {
+        let block = Magma::BLOCK_SIZE;
+        for (data, padding, padded) in [
+            ("0102", Padding::Zeros, "0102000000000000"),
+            ("0102030405060708", Padding::Zeros, "0102030405060708"),
+            ("0102", Padding::Bit, "0102800000000000"),
+            (
+                "0102030405060708",
+                Padding::Bit,
+                "01020304050607088000000000000000",
+            ),
+            ("", Padding::Bit, "8000000000000000"),
+            ("0102", Padding::BitIfPartial, "0102800000000000"),
+            (
+                "0102030405060708",
+                Padding::BitIfPartial,
+                "0102030405060708",
+            ),
+        ] {
+            let mut bytes = data.parse_bytes();
+            padding.pad(&mut bytes, block);
+            assert_eq!(bytes, padded.parse_bytes());
+
+            if padding == Padding::Bit {
+                Padding::unpad_bit(&mut bytes, block).unwrap();
+                assert_eq!(bytes, data.parse_bytes());
+            }
+        }
+
+        let mut bytes = "0102030405060708".parse_bytes();
+        assert_eq!(
+            Padding::unpad_bit(&mut bytes, block),
+            Err(Error::InvalidPadding)
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::hmac::hmac_streebog_512;
+use num_bigint_dig::{BigInt, Sign};
+use num_traits::{One, Zero};
+use zeroize::{Zeroize, Zeroizing};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nonce {
+    /// `k` drawn uniformly from the system RNG.
+    Random,
+    /// `k` derived from the private key and the message digest only, in the
+    /// manner of RFC 6979 with HMAC-Streebog-512 in place of HMAC-SHA.
+    Deterministic,
+    /// The deterministic derivation with 64 fresh random bytes appended to
+    /// the seed data after `x || h`, as in RFC 6979 section 3.6, so a broken
+    /// RNG degrades to the deterministic mode.
+    Hedged,
+}
+
+/// HMAC-DRBG of RFC 6979 section 3.2 keyed by `(d, h)` and yielding
+/// candidates `k` in `[1, q)`.
+pub(crate) struct DeterministicK {
+    k: [u8; 64],
+    v: [u8; 64],
+    q: BigInt,
+    qlen: usize,
+}
+
+impl DeterministicK {
+    pub(crate) fn new(key: &BigInt, digest: &[u8], extra: &[u8], q: &BigInt) -> Self {
+        let qlen = q.bits();
+        let rlen = qlen.div_ceil(8);
+
+        let x = Zeroizing::new(int_to_octets(key, rlen));
+        let h = int_to_octets(&(bits_to_int(digest, qlen) % q), rlen);
+
+        let mut generator = Self {
+            k: [0x00; 64],
+            v: [0x01; 64],
+            q: q.clone(),
+            qlen,
+        };
+
+        for separator in [0x00, 0x01] {
+            let seed = Zeroizing::new([&generator.v[..], &[separator], &x, &h, extra].concat());
+            generator.k = hmac_streebog_512(&generator.k, &seed);
+            generator.v = hmac_streebog_512(&generator.k, &generator.v);
+        }
+
+        generator
+    }
+
+    pub(crate) fn next_k(&mut self) -> BigInt {
+        loop {
+            let mut t = Zeroizing::new(vec![]);
+            while t.len() * 8 < self.qlen {
+                self.v = hmac_streebog_512(&self.k, &self.v);
+                t.extend_from_slice(&self.v);
+            }
+
+            let mut k = bits_to_int(&t, self.qlen);
+
+            self.k = hmac_streebog_512(&self.k, &[&self.v[..], &[0x00]].concat());
+            self.v = hmac_streebog_512(&self.k, &self.v);
+
+            if k >= BigInt::one() && k < self.q {
+                return k;
+            }
+            k.zeroize();
+        }
+    }
+}
+
+impl Drop for DeterministicK {
+    fn drop(&mut self) {
+        self.k.zeroize();
+        self.v.zeroize();
+    }
+}
+
+fn bits_to_int(bytes: &[u8], qlen: usize) -> BigInt {
+    let value = BigInt::from_bytes_be(Sign::Plus, bytes);
+    let blen = bytes.len() * 8;
+    if blen > qlen {
+        value >> (blen - qlen)
+    } else {
+        value
+    }
+}
+
+fn int_to_octets(value: &BigInt, rlen: usize) -> Vec<u8> {
+    let mut output = vec![0u8; rlen];
+    if !value.is_zero() {
+        let (_, bytes) = value.to_bytes_be();
+        output[rlen - bytes.len()..].copy_from_slice(&bytes);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeterministicK;
+    use crate::hash_512;
+    use num_bigint_dig::BigInt;
+    use std::str::FromStr;
+
+    #[test]
+    fn deterministic_k() {
+        let q = BigInt::from_str(
+            "57896044618658097711785492504343953927082934583725450622380973592137631069619",
+        )
+        .unwrap();
+        let d = BigInt::from_str(
+            "55441196065363246126355624130324183196576709222340016572108097750006097525544",
+        )
+        .unwrap();
+        let digest = hash_512(b"sample");
+
+        let mut generator = DeterministicK::new(&d, &digest, &[], &q);
+        let first = generator.next_k();
+        let second = generator.next_k();
+        assert_ne!(first, second);
+
+        assert_eq!(
+            first.to_str_radix(16),
+            "564cfebb30d6a755f79f6d4f3501dd09cf9313328e64c3034191af8708d84ef6"
+        );
+        assert_eq!(DeterministicK::new(&d, &digest, &[], &q).next_k(), first);
+        assert_ne!(DeterministicK::new(&d, &digest, &[1], &q).next_k(), first);
+    }
+}
@@ -0,0 +1,65 @@
+pub const GOST3410_12_256: &str = "1.2.643.7.1.1.1.1";
+pub const GOST3410_12_512: &str = "1.2.643.7.1.1.1.2";
+pub const GOST3411_12_256: &str = "1.2.643.7.1.1.2.2";
+pub const GOST3411_12_512: &str = "1.2.643.7.1.1.2.3";
+pub const SIGNWITHDIGEST_GOST3410_12_256: &str = "1.2.643.7.1.1.3.2";
+pub const SIGNWITHDIGEST_GOST3410_12_512: &str = "1.2.643.7.1.1.3.3";
+
+pub const GOST3410_2001_TEST: &str = "1.2.643.2.2.35.0";
+pub const CRYPTOPRO_A: &str = "1.2.643.2.2.35.1";
+pub const CRYPTOPRO_B: &str = "1.2.643.2.2.35.2";
+pub const CRYPTOPRO_C: &str = "1.2.643.2.2.35.3";
+pub const CRYPTOPRO_XCH_A: &str = "1.2.643.2.2.36.0";
+pub const CRYPTOPRO_XCH_B: &str = "1.2.643.2.2.36.1";
+pub const TC26_256_A: &str = "1.2.643.7.1.2.1.1.1";
+pub const TC26_256_B: &str = "1.2.643.7.1.2.1.1.2";
+pub const TC26_256_C: &str = "1.2.643.7.1.2.1.1.3";
+pub const TC26_256_D: &str = "1.2.643.7.1.2.1.1.4";
+pub const TC26_512_TEST: &str = "1.2.643.7.1.2.1.2.0";
+pub const TC26_512_A: &str = "1.2.643.7.1.2.1.2.1";
+pub const TC26_512_B: &str = "1.2.643.7.1.2.1.2.2";
+pub const TC26_512_C: &str = "1.2.643.7.1.2.1.2.3";
+
+pub const HMAC_GOST3411_12_256: &str = "1.2.643.7.1.1.4.1";
+pub const HMAC_GOST3411_12_512: &str = "1.2.643.7.1.1.4.2";
+pub const MAGMA_CTR_ACPKM: &str = "1.2.643.7.1.1.5.1.1";
+pub const MAGMA_CTR_ACPKM_OMAC: &str = "1.2.643.7.1.1.5.1.2";
+pub const KUZNYECHIK_CTR_ACPKM: &str = "1.2.643.7.1.1.5.2.1";
+pub const KUZNYECHIK_CTR_ACPKM_OMAC: &str = "1.2.643.7.1.1.5.2.2";
+pub const AGREEMENT_GOST3410_12_256: &str = "1.2.643.7.1.1.6.1";
+pub const AGREEMENT_GOST3410_12_512: &str = "1.2.643.7.1.1.6.2";
+pub const MAGMA_KEXP15: &str = "1.2.643.7.1.1.7.1.1";
+pub const KUZNYECHIK_KEXP15: &str = "1.2.643.7.1.1.7.2.1";
+pub const CMS_MAC_ATTR: &str = "1.2.643.7.1.0.6.1.1";
+
+pub const GOST28147_PARAM_Z: &str = "1.2.643.7.1.2.5.1.1";
+pub const GOST28147_CRYPTOPRO_A: &str = "1.2.643.2.2.31.1";
+pub const GOST28147_CRYPTOPRO_B: &str = "1.2.643.2.2.31.2";
+pub const GOST28147_CRYPTOPRO_C: &str = "1.2.643.2.2.31.3";
+pub const GOST28147_CRYPTOPRO_D: &str = "1.2.643.2.2.31.4";
+
+pub const PBES2: &str = "1.2.840.113549.1.5.13";
+pub const PBKDF2: &str = "1.2.840.113549.1.5.12";
+pub const EXTENSION_REQUEST: &str = "1.2.840.113549.1.9.14";
+
+pub const DATA: &str = "1.2.840.113549.1.7.1";
+pub const SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+pub const ENVELOPED_DATA: &str = "1.2.840.113549.1.7.3";
+pub const CONTENT_TYPE: &str = "1.2.840.113549.1.9.3";
+pub const MESSAGE_DIGEST: &str = "1.2.840.113549.1.9.4";
+pub const SIGNING_TIME: &str = "1.2.840.113549.1.9.5";
+
+pub const COMMON_NAME: &str = "2.5.4.3";
+pub const COUNTRY: &str = "2.5.4.6";
+pub const LOCALITY: &str = "2.5.4.7";
+pub const STATE: &str = "2.5.4.8";
+pub const ORGANIZATION: &str = "2.5.4.10";
+pub const ORGANIZATIONAL_UNIT: &str = "2.5.4.11";
+
+pub const SUBJECT_KEY_IDENTIFIER: &str = "2.5.29.14";
+pub const KEY_USAGE: &str = "2.5.29.15";
+pub const SUBJECT_ALT_NAME: &str = "2.5.29.17";
+pub const BASIC_CONSTRAINTS: &str = "2.5.29.19";
+pub const CRL_NUMBER: &str = "2.5.29.20";
+pub const CRL_REASON: &str = "2.5.29.21";
+pub const AUTHORITY_KEY_IDENTIFIER: &str = "2.5.29.35";
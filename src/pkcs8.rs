@@ -0,0 +1,497 @@
+//! `SubjectPublicKeyInfo` and PKCS#8 `PrivateKeyInfo` for GOST R 34.10-2012
+//! keys, in the form of RFC 4491 and RFC 9215 used by gost-engine and
+//! CryptoPro.
+
+use crate::acpkm::{CtrAcpkm, KUZNYECHIK_SECTION};
+use crate::curve::Curve;
+use crate::der::{self, Reader, Tlv};
+use crate::error::Error;
+use crate::hmac::pbkdf2_streebog_512;
+use crate::key::{PrivateKey, PublicKey};
+use crate::kuznyechik::{Kuznyechik, KUZNYECHIK_BLOCK};
+use crate::oid;
+use num_bigint_dig::{BigInt, Sign};
+use num_traits::ToPrimitive;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+/// PBKDF2 iterations used by `PrivateKey::encrypt`.
+pub const PBKDF2_ITERATIONS: u32 = 2000;
+
+/// `AlgorithmIdentifier` of a key on `curve`: the 256 or 512-bit algorithm
+/// OID with `GostR3410-2012-PublicKeyParameters`. The digest parameter set
+/// is only given for the 256-bit sets inherited from GOST R 34.10-2001.
+pub fn key_algorithm(curve: &Curve) -> Result<Vec<u8>, Error> {
+    let param_set = curve
+        .oid()
+        .ok_or_else(|| Error::UnknownCurve("unnamed curve".to_string()))?;
+
+    let (algorithm, digest) = if curve.size() == 32 {
+        (oid::GOST3410_12_256, Some(oid::GOST3411_12_256))
+    } else {
+        (oid::GOST3410_12_512, None)
+    };
+
+    let mut parameters = vec![der::oid(param_set)?];
+    if let Some(digest) = digest.filter(|_| param_set.starts_with("1.2.643.2.2.")) {
+        parameters.push(der::oid(digest)?);
+    }
+
+    Ok(der::sequence(&[
+        der::oid(algorithm)?,
+        der::sequence(&parameters),
+    ]))
+}
+
+/// The curve named by a key `AlgorithmIdentifier`.
+pub fn parse_key_algorithm(algorithm: Tlv) -> Result<Curve, Error> {
+    let mut reader = algorithm.expect(der::SEQUENCE)?.reader();
+    let name = reader.expect(der::OID)?.oid()?;
+    let size = match name.as_str() {
+        oid::GOST3410_12_256 => 32,
+        oid::GOST3410_12_512 => 64,
+        _ => return Err(Error::UnsupportedAlgorithm(name)),
+    };
+
+    let mut parameters = reader.expect(der::SEQUENCE)?.reader();
+    reader.finish()?;
+    let param_set = parameters.expect(der::OID)?.oid()?;
+    if let Some(digest) = parameters.optional(der::OID)? {
+        digest.oid()?;
+    }
+    parameters.finish()?;
+
+    let curve = Curve::from_oid(&param_set).ok_or(Error::UnknownCurve(param_set))?;
+    if curve.size() != size {
+        return Err(Error::UnsupportedAlgorithm(name));
+    }
+
+    Ok(curve)
+}
+
+impl PublicKey {
+    pub fn to_spki_der(&self) -> Result<Vec<u8>, Error> {
+        Ok(der::sequence(&[
+            key_algorithm(self.curve())?,
+            der::bit_string(&der::octet_string(&self.to_bytes())),
+        ]))
+    }
+
+    pub fn from_spki_der(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_spki(der::parse(bytes)?)
+    }
+
+    pub(crate) fn from_spki(spki: Tlv) -> Result<Self, Error> {
+        let mut reader = spki.expect(der::SEQUENCE)?.reader();
+        let curve = parse_key_algorithm(reader.read()?)?;
+        let key = reader.expect(der::BIT_STRING)?.bit_string()?;
+        reader.finish()?;
+
+        let point = der::parse(key)?.octet_string()?;
+
+        Self::from_bytes(point, curve)
+    }
+}
+
+impl PrivateKey {
+    /// PKCS#8 v1 `PrivateKeyInfo` with `d` as a little-endian OCTET STRING.
+    pub fn to_pkcs8_der(&self) -> Result<Vec<u8>, Error> {
+        let inner = Zeroizing::new(der::octet_string(&self.to_bytes()));
+        let key = Zeroizing::new(der::octet_string(&inner));
+        let mut encoded = [der::small_integer(0), key_algorithm(self.curve())?].concat();
+        encoded.extend_from_slice(&key);
+
+        Ok(der::encode(der::SEQUENCE, &Zeroizing::new(encoded)))
+    }
+
+    /// Also accepts the private key as a big-endian INTEGER or as bare
+    /// little-endian bytes, which older gost-engine versions write. A v2
+    /// public key, if present, must belong to the private key.
+    pub fn from_pkcs8_der(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = der::parse(bytes)?.expect(der::SEQUENCE)?.reader();
+        match reader.expect(der::INTEGER)?.integer()? {
+            version if version == BigInt::from(0) || version == BigInt::from(1) => {}
+            _ => return Err(Error::Asn1("unsupported PrivateKeyInfo version")),
+        }
+
+        let curve = parse_key_algorithm(reader.read()?)?;
+        let key = reader.expect(der::OCTET_STRING)?.content;
+        reader.optional(der::context(0))?;
+        let public_key = reader.optional(der::context_primitive(1))?;
+        reader.finish()?;
+
+        let key = match der::parse(key) {
+            Ok(tlv) if tlv.tag == der::OCTET_STRING => Self::from_bytes(tlv.content, curve),
+            Ok(tlv) if tlv.tag == der::INTEGER => Self::new(tlv.unsigned()?, curve),
+            _ if key.len() == curve.size() => {
+                Self::new(BigInt::from_bytes_le(Sign::Plus, key), curve)
+            }
+            _ => Err(Error::Asn1("unrecognized private key encoding")),
+        }?;
+
+        if let Some(public_key) = public_key {
+            let bits = Tlv {
+                tag: der::BIT_STRING,
+                ..public_key
+            }
+            .bit_string()?;
+            if der::parse(bits)?.octet_string()? != key.public_key().to_bytes() {
+                return Err(Error::KeyMismatch);
+            }
+        }
+
+        Ok(key)
+    }
+
+    /// PKCS#8 `EncryptedPrivateKeyInfo` under PBES2 as profiled by RFC 9337:
+    /// PBKDF2-HMAC-Streebog-512 and Kuznyechik in CTR-ACPKM mode.
+    pub fn encrypt(&self, password: &[u8]) -> Result<Vec<u8>, Error> {
+        self.encrypt_with_rng(&mut rand::thread_rng(), password, PBKDF2_ITERATIONS)
+    }
+
+    pub fn encrypt_with_rng<R: CryptoRng + RngCore>(
+        &self,
+        rng: &mut R,
+        password: &[u8],
+        iterations: u32,
+    ) -> Result<Vec<u8>, Error> {
+        let mut salt = [0u8; 32];
+        let mut ukm = [0u8; KUZNYECHIK_BLOCK];
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut ukm);
+
+        let mut data = Zeroizing::new(self.to_pkcs8_der()?);
+        let parameters = Pbes2 {
+            salt: salt.to_vec(),
+            iterations,
+            ukm: ukm.to_vec(),
+        };
+        parameters.apply(password, &mut data);
+
+        Ok(der::sequence(&[
+            parameters.to_der()?,
+            der::octet_string(&data),
+        ]))
+    }
+
+    pub fn decrypt(bytes: &[u8], password: &[u8]) -> Result<Self, Error> {
+        let mut reader = der::parse(bytes)?.expect(der::SEQUENCE)?.reader();
+        let parameters = Pbes2::from_der(reader.read()?)?;
+        let mut data = Zeroizing::new(reader.expect(der::OCTET_STRING)?.content.to_vec());
+        reader.finish()?;
+
+        parameters.apply(password, &mut data);
+
+        // PBES2 has no MAC: a wrong password shows as a key info that does
+        // not parse, or whose public key does not match its private key.
+        Self::from_pkcs8_der(&data).map_err(|_| Error::WrongPassword)
+    }
+}
+
+/// The parameters of PBES2 with the only scheme `encrypt` writes.
+struct Pbes2 {
+    salt: Vec<u8>,
+    iterations: u32,
+    ukm: Vec<u8>,
+}
+
+impl Pbes2 {
+    /// Encrypts or decrypts `data` in place with CTR-ACPKM: the key comes
+    /// from PBKDF2 and the first half of `ukm` is the initialization vector.
+    fn apply(&self, password: &[u8], data: &mut [u8]) {
+        let key = pbkdf2_streebog_512(password, &self.salt, self.iterations, 32);
+        let cipher = Kuznyechik::new(key[..].try_into().unwrap());
+
+        CtrAcpkm::new(cipher, &self.ukm[..8], KUZNYECHIK_SECTION)
+            .unwrap()
+            .apply(data);
+    }
+
+    fn to_der(&self) -> Result<Vec<u8>, Error> {
+        let kdf = der::sequence(&[
+            der::oid(oid::PBKDF2)?,
+            der::sequence(&[
+                der::octet_string(&self.salt),
+                der::small_integer(self.iterations as u64),
+                der::small_integer(32),
+                der::sequence(&[der::oid(oid::HMAC_GOST3411_12_512)?, der::null()]),
+            ]),
+        ]);
+        let scheme = der::sequence(&[
+            der::oid(oid::KUZNYECHIK_CTR_ACPKM)?,
+            der::sequence(&[der::octet_string(&self.ukm)]),
+        ]);
+
+        Ok(der::sequence(&[
+            der::oid(oid::PBES2)?,
+            der::sequence(&[kdf, scheme]),
+        ]))
+    }
+
+    fn from_der(algorithm: Tlv) -> Result<Self, Error> {
+        let mut reader = algorithm.expect(der::SEQUENCE)?.reader();
+        expect_oid(&mut reader, oid::PBES2)?;
+        let mut parameters = reader.expect(der::SEQUENCE)?.reader();
+        reader.finish()?;
+
+        let mut kdf = parameters.expect(der::SEQUENCE)?.reader();
+        expect_oid(&mut kdf, oid::PBKDF2)?;
+        let mut kdf_parameters = kdf.expect(der::SEQUENCE)?.reader();
+        kdf.finish()?;
+
+        let salt = kdf_parameters.expect(der::OCTET_STRING)?.content.to_vec();
+        let iterations = kdf_parameters
+            .expect(der::INTEGER)?
+            .unsigned()?
+            .to_u32()
+            .filter(|iterations| *iterations > 0)
+            .ok_or(Error::Asn1("invalid iteration count"))?;
+        if let Some(len) = kdf_parameters.optional(der::INTEGER)? {
+            if len.unsigned()? != BigInt::from(32) {
+                return Err(Error::Asn1("unexpected key length"));
+            }
+        }
+        // The default PRF is HMAC-SHA1, which is not supported.
+        let mut prf = kdf_parameters.expect(der::SEQUENCE)?.reader();
+        expect_oid(&mut prf, oid::HMAC_GOST3411_12_512)?;
+        prf.optional(der::NULL)?;
+        prf.finish()?;
+        kdf_parameters.finish()?;
+
+        let mut scheme = parameters.expect(der::SEQUENCE)?.reader();
+        expect_oid(&mut scheme, oid::KUZNYECHIK_CTR_ACPKM)?;
+        let mut scheme_parameters = scheme.expect(der::SEQUENCE)?.reader();
+        scheme.finish()?;
+        let ukm = scheme_parameters
+            .expect(der::OCTET_STRING)?
+            .content
+            .to_vec();
+        scheme_parameters.finish()?;
+        parameters.finish()?;
+
+        if ukm.len() != KUZNYECHIK_BLOCK {
+            return Err(Error::InvalidLength {
+                expected: KUZNYECHIK_BLOCK,
+                actual: ukm.len(),
+            });
+        }
+
+        Ok(Self {
+            salt,
+            iterations,
+            ukm,
+        })
+    }
+}
+
+fn expect_oid(reader: &mut Reader, expected: &str) -> Result<(), Error> {
+    let name = reader.expect(der::OID)?.oid()?;
+    if name != expected {
+        return Err(Error::UnsupportedAlgorithm(name));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pbes2;
+    use crate::curve::Curve;
+    use crate::error::Error;
+    use crate::hmac::pbkdf2_streebog_512;
+    use crate::key::{PrivateKey, PublicKey};
+    use crate::kuznyechik::{Kuznyechik, KUZNYECHIK_BLOCK};
+    use crate::modes::Ctr;
+    use crate::{der, oid, ByteParse};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const D: &str = "283bec9198ce191dee7e39491f96601bc1729ad39d35ed10beb99b78de9a927a";
+
+    // AlgorithmIdentifier { id-tc26-gost3410-12-256,
+    //   { id-GostR3410-2001-TestParamSet, id-tc26-gost3411-12-256 } }
+    const ALGORITHM: &str = "301f06082a85030701010101301306072a85030202230006082a85030701010202";
+
+    fn curve() -> Curve {
+        Curve::from_oid(oid::GOST3410_2001_TEST).unwrap()
+    }
+
+    #[test]
+    fn private_key_info() {
+        let fixture = format!("3048020100{}04220420{}", ALGORITHM, D).parse_bytes();
+        let key = PrivateKey::from_pkcs8_der(&fixture).unwrap();
+        assert_eq!(key.to_hex(), D);
+        assert_eq!(key.curve(), &curve());
+        assert_eq!(key.to_pkcs8_der().unwrap(), fixture);
+
+        // The scalar as a big-endian INTEGER instead of an OCTET STRING.
+        let integer = format!(
+            "3048020100{}04220220{}",
+            ALGORITHM, "7a929ade789bb9be10ed359dd39a72c11b60961f49397eee1d19ce9891ec3b28"
+        );
+        let parsed = PrivateKey::from_pkcs8_der(&integer.parse_bytes()).unwrap();
+        assert_eq!(parsed.scalar(), key.scalar());
+
+        let bare = format!("3046020100{}0420{}", ALGORITHM, D).parse_bytes();
+        let parsed = PrivateKey::from_pkcs8_der(&bare).unwrap();
+        assert_eq!(parsed.scalar(), key.scalar());
+    }
+
+    #[test]
+    fn subject_public_key_info() {
+        let key = PrivateKey::from_hex(D, curve()).unwrap().public_key();
+        let fixture = format!("3066{}0343000440{}", ALGORITHM, key.to_hex()).parse_bytes();
+
+        assert_eq!(key.to_spki_der().unwrap(), fixture);
+        assert_eq!(PublicKey::from_spki_der(&fixture), Ok(key));
+    }
+
+    #[test]
+    fn round_trip() {
+        // No digest parameters for the TC26 sets.
+        let sets = [
+            (
+                oid::TC26_256_A,
+                "3017 06082a85030701010101 300b 06092a8503070102010101",
+            ),
+            (
+                oid::TC26_512_A,
+                "3017 06082a85030701010102 300b 06092a8503070102010201",
+            ),
+            (
+                oid::TC26_512_B,
+                "3017 06082a85030701010102 300b 06092a8503070102010202",
+            ),
+            (
+                oid::TC26_512_C,
+                "3017 06082a85030701010102 300b 06092a8503070102010203",
+            ),
+        ];
+
+        for (seed, (param_set, algorithm)) in sets.into_iter().enumerate() {
+            let curve = Curve::from_oid(param_set).unwrap();
+            let key = PrivateKey::generate_with_rng(&mut StdRng::seed_from_u64(seed as u64), curve);
+
+            let der = key.to_pkcs8_der().unwrap();
+            assert_eq!(
+                der[5..30],
+                algorithm
+                    .split_whitespace()
+                    .collect::<String>()
+                    .parse_bytes(),
+                "{}",
+                param_set
+            );
+            let parsed = PrivateKey::from_pkcs8_der(&der).unwrap();
+            assert_eq!(parsed.scalar(), key.scalar(), "{}", param_set);
+            assert_eq!(parsed.curve(), key.curve(), "{}", param_set);
+
+            let public = key.public_key();
+            let der = public.to_spki_der().unwrap();
+            assert_eq!(PublicKey::from_spki_der(&der), Ok(public), "{}", param_set);
+        }
+    }
+
+    #[test]
+    fn encrypted() {
+        let key = PrivateKey::from_hex(D, curve()).unwrap();
+        let mut rng = StdRng::seed_from_u64(34);
+        let der = key.encrypt_with_rng(&mut rng, b"password", 3).unwrap();
+
+        let parsed = PrivateKey::decrypt(&der, b"password").unwrap();
+        assert_eq!(parsed.scalar(), key.scalar());
+        for password in [&b"passw0rd"[..], b"", b"password1", b"Password"] {
+            assert_eq!(
+                PrivateKey::decrypt(&der, password).err(),
+                Some(Error::WrongPassword)
+            );
+        }
+
+        let header = der::parse(&der).unwrap().reader().read().unwrap();
+        assert_eq!(
+            header.reader().expect(der::OID).unwrap().oid().unwrap(),
+            oid::PBES2
+        );
+        assert_eq!(
+            PrivateKey::decrypt(&der[..der.len() - 1], b"password").err(),
+            Some(Error::Asn1("unexpected end"))
+        );
+
+        let again = key.encrypt_with_rng(&mut rng, b"password", 3).unwrap();
+        assert_ne!(again, der);
+    }
+
+    /// Data longer than a 4 KiB section is encrypted under the next ACPKM
+    /// key, not by plain CTR running on.
+    #[test]
+    fn sections() {
+        let parameters = Pbes2 {
+            salt: vec![1; 32],
+            iterations: 2,
+            ukm: vec![2; KUZNYECHIK_BLOCK],
+        };
+        let mut data = vec![0u8; 4096 + 32];
+        parameters.apply(b"password", &mut data);
+
+        let key = pbkdf2_streebog_512(b"password", &[1; 32], 2, 32);
+        let mut ctr = vec![0u8; 4096 + 32];
+        Ctr::new(
+            Kuznyechik::new(key[..].try_into().unwrap()),
+            &[2; 8],
+            KUZNYECHIK_BLOCK,
+        )
+        .unwrap()
+        .apply(&mut ctr);
+
+        assert_eq!(data[..4096], ctr[..4096]);
+        assert_ne!(data[4096..4112], ctr[4096..4112]);
+    }
+
+    #[test]
+    fn rejects() {
+        let fixture = format!("3048020100{}04220420{}", ALGORITHM, D).parse_bytes();
+
+        let mut trailing = fixture.clone();
+        trailing[1] += 2;
+        trailing.extend([0x05, 0x00]);
+        assert_eq!(
+            PrivateKey::from_pkcs8_der(&trailing).err(),
+            Some(Error::Asn1("trailing data"))
+        );
+
+        let mut algorithm = fixture.clone();
+        algorithm[16] = 0x02;
+        assert_eq!(
+            PrivateKey::from_pkcs8_der(&algorithm).err(),
+            Some(Error::UnsupportedAlgorithm("1.2.643.7.1.1.1.2".to_string()))
+        );
+
+        let mut param_set = fixture.clone();
+        param_set[27] = 0x09;
+        assert_eq!(
+            PrivateKey::from_pkcs8_der(&param_set).err(),
+            Some(Error::UnknownCurve("1.2.643.2.2.35.9".to_string()))
+        );
+
+        assert!(PrivateKey::from_pkcs8_der(&fixture[..40]).is_err());
+
+        // A v2 key info whose public key is not that of the private key.
+        let public_key = PrivateKey::from_hex(D, curve()).unwrap().public_key();
+        let mut bits = public_key.to_hex();
+        let v2 = |bits: &str| {
+            format!("30818d0201 01{}04220420{}814300 0440{}", ALGORITHM, D, bits)
+                .split_whitespace()
+                .collect::<String>()
+                .parse_bytes()
+        };
+        assert_eq!(
+            PrivateKey::from_pkcs8_der(&v2(&bits)).unwrap().public_key(),
+            public_key
+        );
+        bits.replace_range(..2, "00");
+        assert_eq!(
+            PrivateKey::from_pkcs8_der(&v2(&bits)).err(),
+            Some(Error::KeyMismatch)
+        );
+    }
+}
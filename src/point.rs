@@ -1,7 +1,8 @@
 use num_bigint_dig::{BigInt, ModInverse};
 use num_traits::{Pow, Zero};
+use zeroize::{Zeroize, Zeroizing};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Point {
     pub x: BigInt,
     pub y: BigInt,
@@ -19,7 +20,17 @@ impl Point {
         }
     }
 
+    pub fn is_identity(&self) -> bool {
+        self.x.is_zero() && self.y.is_zero()
+    }
+
+    /// Twice this point; a point with `y == 0` has order 2 and doubles to
+    /// the identity.
     pub fn double(&self, p: &BigInt, a: &BigInt) -> Self {
+        if self.y.is_zero() {
+            return Point::identity();
+        }
+
         let dy = make_positive((BigInt::from(2) * &self.y).mod_inverse(p).unwrap(), p);
         let dx = make_positive(BigInt::from(3) * self.x.pow(2u8) + a, p);
         let lamda = (dx * dy) % p;
@@ -54,17 +65,21 @@ impl Point {
         }
     }
 
+    /// `n` times this point by double-and-add. The bits of `n` are read from
+    /// a byte copy, and both are wiped; the intermediate points are not.
     pub fn multiply(mut self, mut n: BigInt, p: &BigInt, a: &BigInt) -> Self {
-        let mut output = Point::identity();
+        let bits = if n > BigInt::zero() { n.bits() } else { 0 };
+        let bytes = Zeroizing::new(n.to_bytes_le().1);
+        n.zeroize();
 
-        while n > BigInt::zero() {
-            if &n % 2 != BigInt::zero() {
+        let mut output = Point::identity();
+        for i in 0..bits {
+            if bytes[i / 8] >> (i % 8) & 1 == 1 {
                 output = output.add(&self, p, a);
             }
-
-            self = self.double(p, a);
-
-            n >>= 1;
+            if i + 1 < bits {
+                self = self.double(p, a);
+            }
         }
 
         output
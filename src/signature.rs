@@ -1,10 +1,13 @@
 use crate::curve::Curve;
-use crate::hash_512;
+use crate::error::Error;
+use crate::key::{check_length, PrivateKey, PublicKey};
+use crate::nonce::{DeterministicK, Nonce};
 use crate::point::{make_positive, Point};
-use lazy_static::lazy_static;
-use num_bigint_dig::{BigInt, ModInverse, RandBigInt, Sign};
+use crate::Streebog;
+use num_bigint_dig::{BigInt, ModInverse, Sign};
 use num_traits::{One, Zero};
-use std::str::FromStr;
+use rand::{CryptoRng, RngCore};
+use zeroize::{Zeroize, Zeroizing};
 
 pub struct Signature {
     pub sign: BigInt,
@@ -12,56 +15,154 @@ pub struct Signature {
     pub s: BigInt,
 }
 
-lazy_static! {
-    static ref P: Point = Point::new(
-        BigInt::from_str("2").unwrap(),
-        BigInt::from_str(
-            "4018974056539037503335449422937059775635739389905545080690979365213431566280",
-        )
-        .unwrap(),
-    );
-    static ref Q: Point = Point::new(
-        BigInt::from_str(
-            "57520216126176808443631405023338071176630104906313632182896741342206604859403",
-        )
-        .unwrap(),
-        BigInt::from_str(
-            "17614944419213781543809391949654080031942662045363639260709847859438286763994",
-        )
-        .unwrap(),
-    );
-}
-
 impl Signature {
-    pub fn sign(message: &[u8], key: BigInt, curve: Curve) -> Self {
-        let hash = hash_512(message);
-        let hash = BigInt::from_bytes_le(Sign::Plus, &hash);
-        let mut e = hash % &curve.q;
-        if e == BigInt::zero() {
-            e = BigInt::one();
-        }
+    /// Hashes `message` with `Curve::digest`: Streebog-256 or Streebog-512
+    /// by the size of the curve, over the bytes in memory order.
+    ///
+    /// This breaks compatibility with earlier releases, which hashed every
+    /// message with `hash_512` over the standard's big-endian notation.
+    /// Their signatures fail `verify`, but still pass `verify_prehashed`
+    /// over `hash_512(message)` reduced mod `q` to `curve.size()` bytes.
+    pub fn sign(message: &[u8], key: &PrivateKey) -> Self {
+        Self::sign_with_rng(&mut rand::thread_rng(), message, key)
+    }
 
-        let (r, s) = loop {
-            let k = Self::rand_k(&curve.q);
-            let big_c = P.clone().multiply(k.clone(), &curve.p, &curve.a);
-            let r = big_c.x % &curve.q;
-            if r == BigInt::zero() {
-                continue;
-            }
-            let s = (&r * &key + &k * &e) % &curve.q;
-            if s == BigInt::zero() {
-                continue;
+    pub fn sign_with_rng<R: CryptoRng + RngCore>(
+        rng: &mut R,
+        message: &[u8],
+        key: &PrivateKey,
+    ) -> Self {
+        let digest = key.curve().digest(message);
+        Self::sign_digest(rng, &digest, key, Nonce::Random)
+    }
+
+    pub fn sign_with_nonce(message: &[u8], key: &PrivateKey, nonce: Nonce) -> Self {
+        let digest = key.curve().digest(message);
+        Self::sign_digest(&mut rand::thread_rng(), &digest, key, nonce)
+    }
+
+    /// Signs a Streebog digest computed elsewhere, in memory byte order as
+    /// returned by `streebog_256`/`streebog_512`. The digest must be
+    /// `curve.size()` bytes.
+    pub fn sign_prehashed(digest: &[u8], key: &PrivateKey) -> Result<Self, Error> {
+        check_length(digest, key.curve().size())?;
+
+        Ok(Self::sign_digest(
+            &mut rand::thread_rng(),
+            digest,
+            key,
+            Nonce::Random,
+        ))
+    }
+
+    fn sign_digest<R: CryptoRng + RngCore>(
+        rng: &mut R,
+        digest: &[u8],
+        key: &PrivateKey,
+        nonce: Nonce,
+    ) -> Self {
+        let curve = key.curve();
+        let key = key.scalar();
+        let e = Self::digest_to_e(digest, &curve.q);
+
+        let mut generator = match nonce {
+            Nonce::Random => None,
+            Nonce::Deterministic => Some(DeterministicK::new(key, digest, &[], &curve.q)),
+            Nonce::Hedged => {
+                let mut extra = [0u8; 64];
+                rng.fill_bytes(&mut extra);
+                Some(DeterministicK::new(key, digest, &extra, &curve.q))
             }
-            break (r, s);
         };
 
+        loop {
+            let k = Zeroizing::new(match generator.as_mut() {
+                Some(generator) => generator.next_k(),
+                None => Self::rand_k(rng, &curve.q),
+            });
+            if let Some(signature) = Self::sign_e(&e, &k, key, curve) {
+                break signature;
+            }
+        }
+    }
+
+    pub(crate) fn sign_e(e: &BigInt, k: &BigInt, key: &BigInt, curve: &Curve) -> Option<Self> {
+        let big_c = curve.point.clone().multiply(k.clone(), &curve.p, &curve.a);
+        let r = big_c.x % &curve.q;
+        if r == BigInt::zero() {
+            return None;
+        }
+        let rd = Zeroizing::new(&r * key);
+        let ke = Zeroizing::new(k * e);
+        let s = (&*rd + &*ke) % &curve.q;
+        if s == BigInt::zero() {
+            return None;
+        }
+
+        Some(Self::new(r, s))
+    }
+
+    pub fn new(r: BigInt, s: BigInt) -> Self {
         let bytes = [r.to_bytes_le().1, s.to_bytes_le().1].concat();
         let sign = BigInt::from_bytes_le(Sign::Plus, &bytes);
 
         Self { sign, r, s }
     }
 
-    pub fn verify(&self, message: &[u8], curve: Curve) -> bool {
+    /// `s || r`, each a big-endian integer of `curve.size()` bytes: the
+    /// layout of gost-engine, CryptoPro and the X.509 signature BIT STRING.
+    pub fn to_bytes(&self, curve: &Curve) -> Vec<u8> {
+        let size = curve.size();
+
+        [to_be_bytes(&self.s, size), to_be_bytes(&self.r, size)].concat()
+    }
+
+    /// The 64-byte signatures of 256-bit curves or the 128-byte ones of
+    /// 512-bit curves; a length error names the nearer of the two.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != 64 && bytes.len() != 128 {
+            return Err(Error::InvalidLength {
+                expected: if bytes.len() > 96 { 128 } else { 64 },
+                actual: bytes.len(),
+            });
+        }
+
+        let (s, r) = bytes.split_at(bytes.len() / 2);
+
+        Ok(Self::new(
+            BigInt::from_bytes_be(Sign::Plus, r),
+            BigInt::from_bytes_be(Sign::Plus, s),
+        ))
+    }
+
+    /// Checks a signature over `Curve::digest(message)`; see `sign` for
+    /// signatures made by earlier releases.
+    pub fn verify(&self, message: &[u8], key: &PublicKey) -> bool {
+        self.verify_prehashed(&key.curve().digest(message), key) == Ok(true)
+    }
+
+    /// Checks a signature over a digest of `curve.size()` bytes, as for
+    /// `sign_prehashed`.
+    pub fn verify_prehashed(&self, digest: &[u8], key: &PublicKey) -> Result<bool, Error> {
+        let curve = key.curve();
+        check_length(digest, curve.size())?;
+
+        Ok(self.verify_e(&Self::digest_to_e(digest, &curve.q), key.point(), curve))
+    }
+
+    /// The digest is the little-endian encoding of the standard's `alpha`,
+    /// so `e = alpha mod q`, replaced by one when it vanishes.
+    pub(crate) fn digest_to_e(digest: &[u8], q: &BigInt) -> BigInt {
+        let alpha = BigInt::from_bytes_le(Sign::Plus, digest);
+        let e = alpha % q;
+        if e.is_zero() {
+            BigInt::one()
+        } else {
+            e
+        }
+    }
+
+    pub(crate) fn verify_e(&self, e: &BigInt, key: &Point, curve: &Curve) -> bool {
         if !(self.r > BigInt::zero()
             && (self.r < curve.q)
             && self.s > BigInt::zero()
@@ -70,19 +171,12 @@ impl Signature {
             return false;
         }
 
-        let hash = hash_512(message);
-        let hash = BigInt::from_bytes_le(Sign::Plus, &hash);
-        let mut e = hash % &curve.q;
-        if e.is_zero() {
-            e = BigInt::one();
-        }
-
         let v = e.mod_inverse(&curve.q).unwrap();
 
         let z1 = (&self.s * &v) % &curve.q;
         let z2 = make_positive(-&self.r * &v % &curve.q, &curve.q);
-        let big_c = P.clone().multiply(z1, &curve.p, &curve.a).add(
-            &Q.clone().multiply(z2, &curve.p, &curve.a),
+        let big_c = curve.point.clone().multiply(z1, &curve.p, &curve.a).add(
+            &key.clone().multiply(z2, &curve.p, &curve.a),
             &curve.p,
             &curve.a,
         );
@@ -92,7 +186,246 @@ impl Signature {
         big_r == self.r
     }
 
-    fn rand_k(upper: &BigInt) -> BigInt {
-        rand::thread_rng().gen_bigint_range(&BigInt::zero(), upper)
+    /// Draws a uniform scalar in `[1, upper)` by rejection sampling over
+    /// big-endian bytes, so a fixed byte stream yields a known scalar.
+    pub(crate) fn rand_k<R: CryptoRng + RngCore>(rng: &mut R, upper: &BigInt) -> BigInt {
+        let bits = upper.bits();
+        let mut bytes = Zeroizing::new(vec![0u8; bits.div_ceil(8)]);
+
+        loop {
+            rng.fill_bytes(&mut bytes);
+            bytes[0] &= 0xff >> (bytes.len() * 8 - bits);
+
+            let mut k = BigInt::from_bytes_be(Sign::Plus, &bytes);
+            if k > BigInt::zero() && &k < upper {
+                break k;
+            }
+            k.zeroize();
+        }
+    }
+}
+
+fn to_be_bytes(value: &BigInt, size: usize) -> Vec<u8> {
+    let bytes = value.to_bytes_be().1;
+
+    [vec![0u8; size.saturating_sub(bytes.len())], bytes].concat()
+}
+
+/// Signs a message fed in parts, with a borrowed key.
+pub struct Signer<'a> {
+    hasher: Streebog,
+    key: &'a PrivateKey,
+    nonce: Nonce,
+}
+
+impl<'a> Signer<'a> {
+    pub fn new(key: &'a PrivateKey) -> Self {
+        Self::with_nonce(key, Nonce::Random)
+    }
+
+    pub fn with_nonce(key: &'a PrivateKey, nonce: Nonce) -> Self {
+        Self {
+            hasher: key.curve().hasher(),
+            key,
+            nonce,
+        }
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.hasher.update(chunk);
+    }
+
+    pub fn finalize(self) -> Signature {
+        self.finalize_with_rng(&mut rand::thread_rng())
+    }
+
+    pub fn finalize_with_rng<R: CryptoRng + RngCore>(self, rng: &mut R) -> Signature {
+        let digest = self.hasher.finalize();
+        Signature::sign_digest(rng, &digest, self.key, self.nonce)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Signature, Signer};
+    use crate::curve::Curve;
+    use crate::error::Error;
+    use crate::key::PrivateKey;
+    use crate::nonce::Nonce;
+    use crate::point::Point;
+    use crate::{streebog_256, streebog_512};
+    use num_bigint_dig::{BigInt, Sign};
+    use num_traits::One;
+    use rand::rngs::StdRng;
+    use rand::{CryptoRng, RngCore, SeedableRng};
+
+    struct FixedRng(Vec<u8>);
+
+    impl RngCore for FixedRng {
+        fn next_u32(&mut self) -> u32 {
+            let mut bytes = [0u8; 4];
+            self.fill_bytes(&mut bytes);
+            u32::from_le_bytes(bytes)
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            let mut bytes = [0u8; 8];
+            self.fill_bytes(&mut bytes);
+            u64::from_le_bytes(bytes)
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            let rest = self.0.split_off(dest.len());
+            dest.copy_from_slice(&self.0);
+            self.0 = rest;
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    impl CryptoRng for FixedRng {}
+
+    fn hex(value: &str) -> BigInt {
+        BigInt::parse_bytes(value.as_bytes(), 16).unwrap()
+    }
+
+    fn example_curve() -> Curve {
+        Curve::new(
+            BigInt::from(7),
+            hex("5FBFF498AA938CE739B8E022FBAFEF40563F6E6A3472FC2A514C0CE9DAE23B7E"),
+            hex("8000000000000000000000000000000000000000000000000000000000000431"),
+            hex("8000000000000000000000000000000150FE8A1892976154C59CFC193ACCF5B3"),
+            hex("8000000000000000000000000000000150FE8A1892976154C59CFC193ACCF5B3"),
+            Point::new(
+                BigInt::from(2),
+                hex("8E2A8A0E65147D4BD6316030E16D19C85C97F0A9CA267122B96ABBCEA7E8FC8"),
+            ),
+        )
+    }
+
+    #[test]
+    fn appendix_a_example_1() {
+        let curve = example_curve();
+        let d = hex("7A929ADE789BB9BE10ED359DD39A72C11B60961F49397EEE1D19CE9891EC3B28");
+        let e = hex("2DFBC1B372D89A1188C09C52E0EEC61FCE52032AB1022E8E67ECE6672B043EE5");
+        let k = hex("77105C9B20BCD3122823C8CF6FCC7B956DE33814E95B7FE64FED924594DCEAB3");
+
+        let mut rng = FixedRng(k.to_bytes_be().1);
+        let k = Signature::rand_k(&mut rng, &curve.q);
+        let sign = Signature::sign_e(&e, &k, &d, &curve).unwrap();
+        let key = PrivateKey::new(d, curve.clone()).unwrap().public_key();
+
+        assert_eq!(
+            sign.r,
+            hex("41AA28D2F1AB148280CD9ED56FEDA41974053554A42767B83AD043FD39DC0493")
+        );
+        assert_eq!(
+            sign.s,
+            hex("1456C64BA4642A1653C235A98A60249BCD6D3F746B631DF928014F6C5BF9C40")
+        );
+        assert!(sign.verify_e(&e, key.point(), &curve));
+
+        let bytes = sign.to_bytes(&curve);
+        assert_eq!(&bytes[..2], &[0x01, 0x45]);
+        assert_eq!(&bytes[32..34], &[0x41, 0xaa]);
+        let parsed = Signature::from_bytes(&bytes).unwrap();
+        assert_eq!((parsed.r, parsed.s), (sign.r, sign.s));
+        assert_eq!(
+            Signature::from_bytes(&bytes[1..]).err(),
+            Some(Error::InvalidLength {
+                expected: 64,
+                actual: 63
+            })
+        );
+        assert_eq!(
+            Signature::from_bytes(&[0; 127]).err(),
+            Some(Error::InvalidLength {
+                expected: 128,
+                actual: 127
+            })
+        );
+    }
+
+    #[test]
+    fn seeded_rng() {
+        let curve = example_curve();
+
+        let key = PrivateKey::generate_with_rng(&mut StdRng::seed_from_u64(7), curve.clone());
+        let again = PrivateKey::generate_with_rng(&mut StdRng::seed_from_u64(7), curve.clone());
+        assert_eq!(key.scalar(), again.scalar());
+
+        let d = hex("7A929ADE789BB9BE10ED359DD39A72C11B60961F49397EEE1D19CE9891EC3B28");
+        let key = PrivateKey::new(d, curve).unwrap();
+        let sign = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            Signature::sign_with_rng(&mut rng, b"message", &key)
+        };
+
+        assert_eq!(sign(1).r, sign(1).r);
+        assert_ne!(sign(1).r, sign(2).r);
+        assert!(sign(1).verify(b"message", &key.public_key()));
+    }
+
+    #[test]
+    fn prehashed_and_streaming() {
+        let d = hex("7A929ADE789BB9BE10ED359DD39A72C11B60961F49397EEE1D19CE9891EC3B28");
+        let key = PrivateKey::new(d, example_curve()).unwrap();
+        let public = key.public_key();
+        let message = (0..200u8).collect::<Vec<u8>>();
+        let digest = streebog_256(&message);
+
+        let sign = Signature::sign_prehashed(&digest, &key).unwrap();
+        assert!(sign.verify(&message, &public));
+        assert_eq!(sign.verify_prehashed(&digest, &public), Ok(true));
+        assert_eq!(
+            sign.verify_prehashed(&streebog_256(b"other"), &public),
+            Ok(false)
+        );
+
+        let long = streebog_512(&message);
+        let wrong = Error::InvalidLength {
+            expected: 32,
+            actual: 64,
+        };
+        assert_eq!(
+            Signature::sign_prehashed(&long, &key).err(),
+            Some(wrong.clone())
+        );
+        assert_eq!(sign.verify_prehashed(&long, &public), Err(wrong));
+
+        let mut signer = Signer::with_nonce(&key, Nonce::Deterministic);
+        for chunk in message.chunks(33) {
+            signer.update(chunk);
+        }
+        let streamed = signer.finalize();
+        let whole = Signature::sign_with_nonce(&message, &key, Nonce::Deterministic);
+        assert_eq!((&streamed.r, &streamed.s), (&whole.r, &whole.s));
+        assert_eq!(streamed.verify_prehashed(&digest, &public), Ok(true));
+    }
+
+    #[test]
+    fn digest_to_e() {
+        let q = example_curve().q;
+        let e = hex("2DFBC1B372D89A1188C09C52E0EEC61FCE52032AB1022E8E67ECE6672B043EE5");
+        let mut digest = e.to_bytes_be().1;
+        digest.reverse();
+
+        assert_eq!(Signature::digest_to_e(&digest, &q), e);
+        assert_eq!(
+            Signature::digest_to_e(&q.to_bytes_le().1, &q),
+            BigInt::one()
+        );
+    }
+
+    #[test]
+    fn rand_k_rejects_out_of_range() {
+        let upper = BigInt::from(0x0100);
+        let mut rng = FixedRng(vec![0x01, 0x00, 0x00, 0x00, 0x00, 0x2a]);
+
+        let k = Signature::rand_k(&mut rng, &upper);
+        assert_eq!(k, BigInt::from_bytes_be(Sign::Plus, &[0x2a]));
     }
 }
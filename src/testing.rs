@@ -0,0 +1,89 @@
+//! Fixtures shared by the unit tests: seeded keys and certificates
+//! assembled field by field rather than by `CertificateBuilder`.
+
+use crate::curve::Curve;
+use crate::der;
+use crate::key::{PrivateKey, PublicKey};
+use crate::nonce::Nonce;
+use crate::oid;
+use crate::signature::Signature;
+use crate::x509::{signature_algorithm, Certificate, KeyUsage};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub fn at(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+pub fn key(curve: &str, seed: u64) -> PrivateKey {
+    let curve = Curve::from_oid(curve).unwrap();
+    PrivateKey::generate_with_rng(&mut StdRng::seed_from_u64(seed), curve)
+}
+
+/// `C=RU, CN=common_name`, the country as a PrintableString.
+pub fn name(common_name: &str) -> Vec<u8> {
+    der::sequence(&[
+        der::set_of(&[der::sequence(&[
+            der::oid(oid::COUNTRY).unwrap(),
+            der::encode(der::PRINTABLE_STRING, b"RU"),
+        ])]),
+        der::set_of(&[der::sequence(&[
+            der::oid(oid::COMMON_NAME).unwrap(),
+            der::encode(der::UTF8_STRING, common_name.as_bytes()),
+        ])]),
+    ])
+}
+
+pub fn extension(oid: &str, critical: bool, value: Vec<u8>) -> Vec<u8> {
+    let mut fields = vec![der::oid(oid).unwrap()];
+    if critical {
+        fields.push(der::boolean(true));
+    }
+    fields.push(der::octet_string(&value));
+
+    der::sequence(&fields)
+}
+
+/// Critical basic constraints of a CA.
+pub fn ca(path_len: Option<u64>) -> Vec<u8> {
+    let mut fields = vec![der::boolean(true)];
+    fields.extend(path_len.map(der::small_integer));
+
+    extension(oid::BASIC_CONSTRAINTS, true, der::sequence(&fields))
+}
+
+pub fn usage(usage: KeyUsage) -> Vec<u8> {
+    extension(oid::KEY_USAGE, true, der::named_bits(usage.0))
+}
+
+/// A v3 certificate valid from `at(1_700_000_000)` to `at(1_800_000_000)`,
+/// signed with a deterministic nonce.
+pub fn certificate(
+    subject: &str,
+    key: &PublicKey,
+    issuer: &str,
+    signer: &PrivateKey,
+    serial: u64,
+    extensions: &[Vec<u8>],
+) -> Certificate {
+    let algorithm = signature_algorithm(signer.curve()).unwrap();
+    let tbs = der::sequence(&[
+        der::encode(der::context(0), &der::small_integer(2)),
+        der::small_integer(serial),
+        algorithm.clone(),
+        name(issuer),
+        der::sequence(&[der::time(at(1_700_000_000)), der::time(at(1_800_000_000))]),
+        name(subject),
+        key.to_spki_der().unwrap(),
+        der::encode(der::context(3), &der::sequence(extensions)),
+    ]);
+    let signature = Signature::sign_with_nonce(&tbs, signer, Nonce::Deterministic);
+
+    Certificate::from_der(&der::sequence(&[
+        tbs,
+        algorithm,
+        der::bit_string(&signature.to_bytes(signer.curve())),
+    ]))
+    .unwrap()
+}
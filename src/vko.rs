@@ -0,0 +1,137 @@
+//! VKO GOST R 34.10-2012 key agreement (R 50.1.113-2016): both sides
+//! derive `H(x || y)` of `(m / q · UKM · d) · Q` for their own `d` and the
+//! peer's `Q`.
+
+use crate::error::Error;
+use crate::key::{to_le_bytes, PrivateKey, PublicKey};
+use crate::{streebog_256, streebog_512, Block};
+use num_bigint_dig::{BigInt, Sign};
+use num_traits::Zero;
+use zeroize::{Zeroize, Zeroizing};
+
+/// The 256-bit key encryption key shared with the owner of `peer`.
+pub fn vko_gostr3410_2012_256(
+    key: &PrivateKey,
+    peer: &PublicKey,
+    ukm: &[u8],
+) -> Result<Zeroizing<[u8; 32]>, Error> {
+    let point = shared_point(key, peer, ukm)?;
+
+    Ok(Zeroizing::new(streebog_256(&point)))
+}
+
+/// The 512-bit key encryption key shared with the owner of `peer`.
+pub fn vko_gostr3410_2012_512(
+    key: &PrivateKey,
+    peer: &PublicKey,
+    ukm: &[u8],
+) -> Result<Zeroizing<Block>, Error> {
+    let point = shared_point(key, peer, ukm)?;
+
+    Ok(Zeroizing::new(streebog_512(&point)))
+}
+
+/// `x || y` of the shared point, each little-endian. `ukm` is a
+/// little-endian integer; zero is replaced by one as in RFC 4357.
+fn shared_point(
+    key: &PrivateKey,
+    peer: &PublicKey,
+    ukm: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let curve = key.curve();
+    // `PublicKey::new` has checked that the point is in the subgroup.
+    if peer.curve() != curve {
+        return Err(Error::CurveMismatch);
+    }
+
+    let mut ukm = BigInt::from_bytes_le(Sign::Plus, ukm);
+    if ukm.is_zero() {
+        ukm = BigInt::from(1);
+    }
+
+    let mut scalar = &curve.m / &curve.q * &ukm * key.scalar();
+    let point = peer
+        .point()
+        .clone()
+        .multiply(scalar.clone(), &curve.p, &curve.a);
+    scalar.zeroize();
+    ukm.zeroize();
+    if point.is_identity() {
+        return Err(Error::PointNotInSubgroup);
+    }
+
+    let size = curve.size();
+    let mut bytes = Zeroizing::new(to_le_bytes(&point.x, size));
+    bytes.extend_from_slice(&to_le_bytes(&point.y, size));
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{vko_gostr3410_2012_256, vko_gostr3410_2012_512};
+    use crate::curve::Curve;
+    use crate::error::Error;
+    use crate::key::PrivateKey;
+    use crate::oid;
+    use crate::ByteParse;
+
+    /// The example of R 50.1.113-2016, Appendix A (also RFC 7836).
+    #[test]
+    fn agreement() {
+        let curve = Curve::from_oid(oid::TC26_512_A).unwrap();
+        let a = PrivateKey::from_hex(
+            "c990ecd972fce84ec4db022778f50fcac726f46708384b8d458304962d7147f8\
+             c2db41cef22c90b102f2968404f9b9be6d47c79692d81826b32b8daca43cb667",
+            curve.clone(),
+        )
+        .unwrap();
+        let b = PrivateKey::from_hex(
+            "48c859f7b6f11585887cc05ec6ef1390cfea739b1a18c0d4662293ef63b79e3b\
+             8014070b44918590b4b996acfea4edfbbbcccc8c06edd8bf5bda92a51392d0db",
+            curve,
+        )
+        .unwrap();
+        let ukm = "1d80603c8544c727".parse_bytes();
+
+        assert_eq!(
+            a.public_key().to_hex(),
+            "aab0eda4abff21208d18799fb9a8556654ba783070eba10cb9abb253ec56dcf5\
+             d3ccba6192e464e6e5bcb6dea137792f2431f6c897eb1b3c0cc14327b1adc0a7\
+             914613a3074e363aedb204d38d3563971bd8758e878c9db11403721b48002d38\
+             461f92472d40ea92f9958c0ffa4c93756401b97f89fdbe0b5e46e4a4631cdb5a"
+        );
+
+        let kek = vko_gostr3410_2012_256(&a, &b.public_key(), &ukm).unwrap();
+        assert_eq!(
+            kek.to_vec(),
+            "c9a9a77320e2cc559ed72dce6f47e2192ccea95fa648670582c054c0ef36c221".parse_bytes()
+        );
+        assert_eq!(
+            *vko_gostr3410_2012_256(&b, &a.public_key(), &ukm).unwrap(),
+            *kek
+        );
+
+        let kek = vko_gostr3410_2012_512(&a, &b.public_key(), &ukm).unwrap();
+        assert_eq!(
+            kek.to_vec(),
+            "79f002a96940ce7bde3259a52e015297adaad84597a0d205b50e3e1719f97bfa\
+             7ee1d2661fa9979a5aa235b558a7e6d9f88f982dd63fc35a8ec0dd5e242d3bdf"
+                .parse_bytes()
+        );
+        assert_eq!(
+            *vko_gostr3410_2012_512(&b, &a.public_key(), &ukm).unwrap(),
+            *kek
+        );
+
+        let other = PrivateKey::from_hex(
+            "7a929ade789bb9be10ed359dd39a72c11b60961f49397eee1d19ce9891ec3b28",
+            Curve::from_oid(oid::CRYPTOPRO_A).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            vko_gostr3410_2012_256(&a, &other.public_key(), &ukm).err(),
+            Some(Error::CurveMismatch)
+        );
+    }
+}
@@ -0,0 +1,920 @@
+//! X.509 certificates with GOST R 34.10-2012 keys and signatures, as
+//! profiled by RFC 9215.
+
+use crate::curve::Curve;
+use crate::der::{self, Reader, Tlv};
+use crate::error::Error;
+use crate::key::{PrivateKey, PublicKey};
+use crate::oid;
+use crate::pem::{Pem, CERTIFICATE};
+use crate::signature::Signature;
+use num_bigint_dig::{BigInt, Sign};
+use num_traits::ToPrimitive;
+use rand::{CryptoRng, Rng, RngCore};
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Name {
+    raw: Vec<u8>,
+    attributes: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension {
+    pub oid: String,
+    pub critical: bool,
+    /// The DER inside the `extnValue` OCTET STRING.
+    pub value: Vec<u8>,
+}
+
+/// The `KeyUsage` bits, bit `i` of the extension in bit `i` of the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyUsage(pub u16);
+
+impl KeyUsage {
+    pub const DIGITAL_SIGNATURE: Self = Self(1 << 0);
+    pub const NON_REPUDIATION: Self = Self(1 << 1);
+    pub const KEY_ENCIPHERMENT: Self = Self(1 << 2);
+    pub const DATA_ENCIPHERMENT: Self = Self(1 << 3);
+    pub const KEY_AGREEMENT: Self = Self(1 << 4);
+    pub const KEY_CERT_SIGN: Self = Self(1 << 5);
+    pub const CRL_SIGN: Self = Self(1 << 6);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for KeyUsage {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BasicConstraints {
+    pub ca: bool,
+    pub path_len: Option<u32>,
+}
+
+/// The `GeneralName` forms used in subject alternative names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AltName {
+    Email(String),
+    Dns(String),
+    Uri(String),
+    Ip(IpAddr),
+}
+
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    raw: Vec<u8>,
+    tbs: Vec<u8>,
+    version: u8,
+    serial: BigInt,
+    signature_algorithm: String,
+    issuer: Name,
+    subject: Name,
+    not_before: SystemTime,
+    not_after: SystemTime,
+    public_key: PublicKey,
+    extensions: Vec<Extension>,
+    signature: Vec<u8>,
+}
+
+impl Name {
+    /// One attribute per RDN, in order. Country codes are PrintableString,
+    /// everything else UTF8String. Fails if a type is not a dotted OID.
+    pub fn new(attributes: &[(&str, &str)]) -> Result<Self, Error> {
+        let rdns = attributes
+            .iter()
+            .map(|(kind, value)| {
+                let tag = if *kind == oid::COUNTRY {
+                    der::PRINTABLE_STRING
+                } else {
+                    der::UTF8_STRING
+                };
+                Ok(der::set_of(&[der::sequence(&[
+                    der::oid(kind)?,
+                    der::encode(tag, value.as_bytes()),
+                ])]))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Self {
+            raw: der::sequence(&rdns),
+            attributes: attributes
+                .iter()
+                .map(|(kind, value)| (kind.to_string(), value.to_string()))
+                .collect(),
+        })
+    }
+
+    pub(crate) fn parse(name: Tlv) -> Result<Self, Error> {
+        let mut attributes = vec![];
+        let mut reader = name.expect(der::SEQUENCE)?.reader();
+        while !reader.is_empty() {
+            let mut set = reader.expect(der::SET)?.reader();
+            while !set.is_empty() {
+                let mut attribute = set.expect(der::SEQUENCE)?.reader();
+                let kind = attribute.expect(der::OID)?.oid()?;
+                let value = attribute.read()?;
+                attribute.finish()?;
+
+                // Values that are not strings are shown as in RFC 4514.
+                let value = value
+                    .string()
+                    .unwrap_or_else(|_| format!("#{}", crate::to_hex(value.raw)));
+                attributes.push((kind, value));
+            }
+        }
+
+        Ok(Self {
+            raw: name.raw.to_vec(),
+            attributes,
+        })
+    }
+
+    pub fn from_der(bytes: &[u8]) -> Result<Self, Error> {
+        Self::parse(der::parse(bytes)?)
+    }
+
+    pub fn to_der(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// `(type OID, value)` pairs in encoding order.
+    pub fn attributes(&self) -> &[(String, String)] {
+        &self.attributes
+    }
+
+    pub fn common_name(&self) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(kind, _)| kind == oid::COMMON_NAME)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (kind, value)) in self.attributes.iter().enumerate() {
+            let label = match kind.as_str() {
+                oid::COMMON_NAME => "CN",
+                oid::COUNTRY => "C",
+                oid::LOCALITY => "L",
+                oid::STATE => "ST",
+                oid::ORGANIZATION => "O",
+                oid::ORGANIZATIONAL_UNIT => "OU",
+                other => other,
+            };
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}={}", label, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Extension {
+    pub fn new(oid: &str, critical: bool, value: Vec<u8>) -> Self {
+        Self {
+            oid: oid.to_string(),
+            critical,
+            value,
+        }
+    }
+
+    pub fn to_der(&self) -> Result<Vec<u8>, Error> {
+        let mut fields = vec![der::oid(&self.oid)?];
+        if self.critical {
+            fields.push(der::boolean(true));
+        }
+        fields.push(der::octet_string(&self.value));
+
+        Ok(der::sequence(&fields))
+    }
+}
+
+impl KeyUsage {
+    pub fn to_extension(self) -> Extension {
+        Extension::new(oid::KEY_USAGE, true, der::named_bits(self.0))
+    }
+}
+
+impl BasicConstraints {
+    pub fn to_extension(self) -> Extension {
+        let mut fields = vec![];
+        if self.ca {
+            fields.push(der::boolean(true));
+        }
+        if let Some(len) = self.path_len {
+            fields.push(der::small_integer(len.into()));
+        }
+
+        Extension::new(oid::BASIC_CONSTRAINTS, true, der::sequence(&fields))
+    }
+}
+
+impl AltName {
+    pub fn to_extension(names: &[AltName]) -> Extension {
+        let names = names
+            .iter()
+            .map(|name| match name {
+                AltName::Email(email) => der::encode(der::context_primitive(1), email.as_bytes()),
+                AltName::Dns(dns) => der::encode(der::context_primitive(2), dns.as_bytes()),
+                AltName::Uri(uri) => der::encode(der::context_primitive(6), uri.as_bytes()),
+                AltName::Ip(IpAddr::V4(ip)) => der::encode(der::context_primitive(7), &ip.octets()),
+                AltName::Ip(IpAddr::V6(ip)) => der::encode(der::context_primitive(7), &ip.octets()),
+            })
+            .collect::<Vec<_>>();
+
+        Extension::new(oid::SUBJECT_ALT_NAME, false, der::sequence(&names))
+    }
+
+    /// The names of a `SubjectAltName` value. Other `GeneralName` forms,
+    /// such as directory names, are skipped.
+    pub fn parse_extension(value: &[u8]) -> Result<Vec<AltName>, Error> {
+        let mut names = vec![];
+        let mut reader = der::parse(value)?.expect(der::SEQUENCE)?.reader();
+        while !reader.is_empty() {
+            let name = reader.read()?;
+            let text = || {
+                String::from_utf8(name.content.to_vec())
+                    .map_err(|_| Error::Asn1("invalid IA5String"))
+            };
+            names.push(match name.tag {
+                0x81 => AltName::Email(text()?),
+                0x82 => AltName::Dns(text()?),
+                0x86 => AltName::Uri(text()?),
+                0x87 => match name.content.len() {
+                    4 => AltName::Ip(<[u8; 4]>::try_from(name.content).unwrap().into()),
+                    16 => AltName::Ip(<[u8; 16]>::try_from(name.content).unwrap().into()),
+                    _ => return Err(Error::Asn1("invalid IP address length")),
+                },
+                _ => continue,
+            });
+        }
+
+        Ok(names)
+    }
+}
+
+impl Certificate {
+    pub fn from_der(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = der::parse(bytes)?.expect(der::SEQUENCE)?.reader();
+        let tbs = reader.expect(der::SEQUENCE)?;
+        let signature_algorithm = parse_algorithm(reader.read()?)?;
+        let signature = reader.expect(der::BIT_STRING)?.bit_string()?.to_vec();
+        reader.finish()?;
+
+        let mut fields = tbs.reader();
+        let version = match fields.optional(der::context(0))? {
+            Some(version) => {
+                let mut version = version.reader();
+                let value = version.expect(der::INTEGER)?.integer()?;
+                version.finish()?;
+                match value.to_u8() {
+                    Some(1) => 2,
+                    Some(2) => 3,
+                    _ => return Err(Error::Asn1("unsupported certificate version")),
+                }
+            }
+            None => 1,
+        };
+        let serial = fields.expect(der::INTEGER)?.integer()?;
+        if parse_algorithm(fields.read()?)? != signature_algorithm {
+            return Err(Error::Asn1("signature algorithm mismatch"));
+        }
+        let issuer = Name::parse(fields.read()?)?;
+
+        let mut validity = fields.expect(der::SEQUENCE)?.reader();
+        let not_before = validity.read()?.time()?;
+        let not_after = validity.read()?.time()?;
+        validity.finish()?;
+
+        let subject = Name::parse(fields.read()?)?;
+        let public_key = PublicKey::from_spki(fields.read()?)?;
+        fields.optional(der::context_primitive(1))?;
+        fields.optional(der::context_primitive(2))?;
+
+        let extensions = match fields.optional(der::context(3))? {
+            Some(extensions) => {
+                let mut wrapper = extensions.reader();
+                let list = parse_extensions(wrapper.expect(der::SEQUENCE)?)?;
+                wrapper.finish()?;
+                list
+            }
+            None => vec![],
+        };
+        fields.finish()?;
+
+        Ok(Self {
+            raw: bytes.to_vec(),
+            tbs: tbs.raw.to_vec(),
+            version,
+            serial,
+            signature_algorithm,
+            issuer,
+            subject,
+            not_before,
+            not_after,
+            public_key,
+            extensions,
+            signature,
+        })
+    }
+
+    /// The first certificate in `text`.
+    pub fn from_pem(text: &str) -> Result<Self, Error> {
+        Self::from_der(&Pem::find(text, CERTIFICATE)?.contents)
+    }
+
+    /// Every certificate in `text`, skipping other objects.
+    pub fn all_from_pem(text: &str) -> Result<Vec<Self>, Error> {
+        Pem::parse_all(text)?
+            .into_iter()
+            .filter(|pem| pem.label == CERTIFICATE)
+            .map(|pem| Self::from_der(&pem.contents))
+            .collect()
+    }
+
+    pub fn to_der(&self) -> &[u8] {
+        &self.raw
+    }
+
+    pub fn to_pem(&self) -> String {
+        Pem::new(CERTIFICATE, self.raw.clone()).encode()
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn serial(&self) -> &BigInt {
+        &self.serial
+    }
+
+    pub fn signature_algorithm(&self) -> &str {
+        &self.signature_algorithm
+    }
+
+    pub fn issuer(&self) -> &Name {
+        &self.issuer
+    }
+
+    pub fn subject(&self) -> &Name {
+        &self.subject
+    }
+
+    pub fn not_before(&self) -> SystemTime {
+        self.not_before
+    }
+
+    pub fn not_after(&self) -> SystemTime {
+        self.not_after
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    pub fn extensions(&self) -> &[Extension] {
+        &self.extensions
+    }
+
+    pub fn extension(&self, oid: &str) -> Option<&Extension> {
+        self.extensions
+            .iter()
+            .find(|extension| extension.oid == oid)
+    }
+
+    pub fn key_usage(&self) -> Result<Option<KeyUsage>, Error> {
+        self.extension(oid::KEY_USAGE)
+            .map(|extension| Ok(KeyUsage(der::parse(&extension.value)?.named_bits()?)))
+            .transpose()
+    }
+
+    pub fn basic_constraints(&self) -> Result<Option<BasicConstraints>, Error> {
+        self.extension(oid::BASIC_CONSTRAINTS)
+            .map(|extension| {
+                let mut reader = der::parse(&extension.value)?
+                    .expect(der::SEQUENCE)?
+                    .reader();
+                let ca = match reader.optional(der::BOOLEAN)? {
+                    Some(ca) => ca.boolean()?,
+                    None => false,
+                };
+                let path_len = match reader.optional(der::INTEGER)? {
+                    Some(len) => Some(
+                        len.unsigned()?
+                            .to_u32()
+                            .ok_or(Error::Asn1("path length too large"))?,
+                    ),
+                    None => None,
+                };
+                reader.finish()?;
+
+                Ok(BasicConstraints { ca, path_len })
+            })
+            .transpose()
+    }
+
+    pub fn subject_key_identifier(&self) -> Result<Option<Vec<u8>>, Error> {
+        self.extension(oid::SUBJECT_KEY_IDENTIFIER)
+            .map(|extension| Ok(der::parse(&extension.value)?.octet_string()?.to_vec()))
+            .transpose()
+    }
+
+    /// The `keyIdentifier` field; issuer name and serial are ignored.
+    pub fn authority_key_identifier(&self) -> Result<Option<Vec<u8>>, Error> {
+        let Some(extension) = self.extension(oid::AUTHORITY_KEY_IDENTIFIER) else {
+            return Ok(None);
+        };
+        let mut reader = der::parse(&extension.value)?
+            .expect(der::SEQUENCE)?
+            .reader();
+
+        Ok(reader
+            .optional(der::context_primitive(0))?
+            .map(|id| id.content.to_vec()))
+    }
+
+    pub fn alt_names(&self) -> Result<Vec<AltName>, Error> {
+        match self.extension(oid::SUBJECT_ALT_NAME) {
+            Some(extension) => AltName::parse_extension(&extension.value),
+            None => Ok(vec![]),
+        }
+    }
+
+    pub fn is_self_issued(&self) -> bool {
+        self.issuer == self.subject
+    }
+
+    pub fn verify_signature(&self, key: &PublicKey) -> Result<(), Error> {
+        verify_signed(&self.signature_algorithm, &self.tbs, &self.signature, key)
+    }
+
+    /// Checks that `issuer` names this certificate's issuer and signed it.
+    pub fn verify_issued_by(&self, issuer: &Certificate) -> Result<(), Error> {
+        if self.issuer != issuer.subject {
+            return Err(Error::IssuerMismatch);
+        }
+
+        self.verify_signature(&issuer.public_key)
+    }
+}
+
+/// Builds and signs X.509 v3 certificates. Without `issuer` the
+/// certificate is self-signed.
+#[derive(Debug, Clone)]
+pub struct CertificateBuilder {
+    subject: Name,
+    public_key: PublicKey,
+    issuer: Option<Certificate>,
+    serial: Option<BigInt>,
+    not_before: SystemTime,
+    not_after: SystemTime,
+    basic_constraints: Option<BasicConstraints>,
+    key_usage: Option<KeyUsage>,
+    alt_names: Vec<AltName>,
+    extensions: Vec<Extension>,
+}
+
+impl CertificateBuilder {
+    /// Valid for a year from now, with a random serial number.
+    pub fn new(subject: Name, public_key: PublicKey) -> Self {
+        let now = SystemTime::now();
+        Self {
+            subject,
+            public_key,
+            issuer: None,
+            serial: None,
+            not_before: now,
+            not_after: now + Duration::from_secs(365 * 24 * 60 * 60),
+            basic_constraints: None,
+            key_usage: None,
+            alt_names: vec![],
+            extensions: vec![],
+        }
+    }
+
+    /// Subject, key and requested subject alternative names of a request
+    /// whose signature verifies. Other requested extensions are left to
+    /// the caller.
+    pub fn from_request(request: &crate::csr::CertificateRequest) -> Result<Self, Error> {
+        request.verify_signature()?;
+
+        let mut builder = Self::new(request.subject().clone(), request.public_key().clone());
+        if let Some(extension) = request.extension(oid::SUBJECT_ALT_NAME) {
+            builder.alt_names = AltName::parse_extension(&extension.value)?;
+        }
+
+        Ok(builder)
+    }
+
+    pub fn issuer(mut self, issuer: &Certificate) -> Self {
+        self.issuer = Some(issuer.clone());
+        self
+    }
+
+    pub fn serial(mut self, serial: BigInt) -> Self {
+        self.serial = Some(serial);
+        self
+    }
+
+    pub fn validity(mut self, not_before: SystemTime, not_after: SystemTime) -> Self {
+        self.not_before = not_before;
+        self.not_after = not_after;
+        self
+    }
+
+    pub fn basic_constraints(mut self, basic_constraints: BasicConstraints) -> Self {
+        self.basic_constraints = Some(basic_constraints);
+        self
+    }
+
+    pub fn key_usage(mut self, key_usage: KeyUsage) -> Self {
+        self.key_usage = Some(key_usage);
+        self
+    }
+
+    pub fn alt_name(mut self, name: AltName) -> Self {
+        self.alt_names.push(name);
+        self
+    }
+
+    /// Any other extension, added after the standard ones.
+    pub fn extension(mut self, extension: Extension) -> Self {
+        self.extensions.push(extension);
+        self
+    }
+
+    pub fn sign(&self, key: &PrivateKey) -> Result<Certificate, Error> {
+        self.sign_with_rng(&mut rand::thread_rng(), key)
+    }
+
+    /// Signs with `key`, which must belong to the issuer, or to the
+    /// subject of a self-signed certificate.
+    pub fn sign_with_rng<R: CryptoRng + RngCore>(
+        &self,
+        rng: &mut R,
+        key: &PrivateKey,
+    ) -> Result<Certificate, Error> {
+        let (issuer, issuer_key, authority_key_id) = match &self.issuer {
+            Some(issuer) => (
+                issuer.subject(),
+                issuer.public_key(),
+                match issuer.subject_key_identifier()? {
+                    Some(id) => id,
+                    None => key_identifier(issuer.public_key()),
+                },
+            ),
+            None => (
+                &self.subject,
+                &self.public_key,
+                key_identifier(&self.public_key),
+            ),
+        };
+        if &key.public_key() != issuer_key {
+            return Err(Error::KeyMismatch);
+        }
+
+        let serial = match &self.serial {
+            Some(serial) => serial.clone(),
+            None => {
+                let mut bytes = [0u8; 16];
+                rng.fill(&mut bytes);
+                bytes[0] = bytes[0] & 0x7f | 0x40;
+                BigInt::from_bytes_be(Sign::Plus, &bytes)
+            }
+        };
+
+        let mut extensions = vec![];
+        extensions.extend(self.basic_constraints.map(BasicConstraints::to_extension));
+        extensions.extend(self.key_usage.map(KeyUsage::to_extension));
+        if !self.alt_names.is_empty() {
+            extensions.push(AltName::to_extension(&self.alt_names));
+        }
+        extensions.push(Extension::new(
+            oid::SUBJECT_KEY_IDENTIFIER,
+            false,
+            der::octet_string(&key_identifier(&self.public_key)),
+        ));
+        extensions.push(Extension::new(
+            oid::AUTHORITY_KEY_IDENTIFIER,
+            false,
+            der::sequence(&[der::encode(der::context_primitive(0), &authority_key_id)]),
+        ));
+        extensions.extend(self.extensions.iter().cloned());
+
+        let algorithm = signature_algorithm(key.curve())?;
+        let tbs = der::sequence(&[
+            der::encode(der::context(0), &der::small_integer(2)),
+            der::integer(&serial),
+            algorithm.clone(),
+            issuer.to_der().to_vec(),
+            der::sequence(&[der::time(self.not_before), der::time(self.not_after)]),
+            self.subject.to_der().to_vec(),
+            self.public_key.to_spki_der()?,
+            der::encode(
+                der::context(3),
+                &der::sequence(
+                    &extensions
+                        .iter()
+                        .map(Extension::to_der)
+                        .collect::<Result<Vec<_>, _>>()?,
+                ),
+            ),
+        ]);
+        let signature = Signature::sign_with_rng(rng, &tbs, key);
+
+        Certificate::from_der(&der::sequence(&[
+            tbs,
+            algorithm,
+            der::bit_string(&signature.to_bytes(key.curve())),
+        ]))
+    }
+}
+
+/// Streebog-256 of the `subjectPublicKey` bits cut to 160 bits, method 1
+/// of RFC 5280 with the hash swapped as RFC 7093 allows.
+pub fn key_identifier(key: &PublicKey) -> Vec<u8> {
+    crate::streebog_256(&der::octet_string(&key.to_bytes()))[..20].to_vec()
+}
+
+/// The `signatureAlgorithm` for keys on `curve`, without parameters.
+pub fn signature_algorithm(curve: &Curve) -> Result<Vec<u8>, Error> {
+    let algorithm = if curve.size() == 32 {
+        oid::SIGNWITHDIGEST_GOST3410_12_256
+    } else {
+        oid::SIGNWITHDIGEST_GOST3410_12_512
+    };
+
+    Ok(der::sequence(&[der::oid(algorithm)?]))
+}
+
+/// The algorithm OID; absent and NULL parameters are both accepted.
+pub(crate) fn parse_algorithm(algorithm: Tlv) -> Result<String, Error> {
+    let mut reader = algorithm.expect(der::SEQUENCE)?.reader();
+    let name = reader.expect(der::OID)?.oid()?;
+    reader.optional(der::NULL)?;
+    reader.finish()?;
+
+    Ok(name)
+}
+
+/// Verifies `signature`, laid out as `s || r` big-endian, over `data`. The
+/// algorithm fixes Streebog-256 or Streebog-512 and so the key size.
+pub(crate) fn verify_signed(
+    algorithm: &str,
+    data: &[u8],
+    signature: &[u8],
+    key: &PublicKey,
+) -> Result<(), Error> {
+    let size = match algorithm {
+        oid::SIGNWITHDIGEST_GOST3410_12_256 => 32,
+        oid::SIGNWITHDIGEST_GOST3410_12_512 => 64,
+        _ => return Err(Error::UnsupportedAlgorithm(algorithm.to_string())),
+    };
+    if key.curve().size() != size || signature.len() != 2 * size {
+        return Err(Error::InvalidSignature);
+    }
+
+    if Signature::from_bytes(signature)?.verify(data, key) {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignature)
+    }
+}
+
+pub(crate) fn parse_extensions(list: Tlv) -> Result<Vec<Extension>, Error> {
+    let mut extensions = vec![];
+    let mut reader: Reader = list.reader();
+    while !reader.is_empty() {
+        let mut extension = reader.expect(der::SEQUENCE)?.reader();
+        let oid = extension.expect(der::OID)?.oid()?;
+        let critical = match extension.optional(der::BOOLEAN)? {
+            Some(critical) => critical.boolean()?,
+            None => false,
+        };
+        let value = extension.expect(der::OCTET_STRING)?.content.to_vec();
+        extension.finish()?;
+
+        if extensions.iter().any(|other: &Extension| other.oid == oid) {
+            return Err(Error::Asn1("duplicate extension"));
+        }
+        extensions.push(Extension {
+            oid,
+            critical,
+            value,
+        });
+    }
+
+    Ok(extensions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        key_identifier, AltName, BasicConstraints, Certificate, CertificateBuilder, Extension,
+        KeyUsage, Name,
+    };
+    use crate::csr::RequestBuilder;
+    use crate::der;
+    use crate::error::Error;
+    use crate::oid;
+    use crate::testing::{ca, certificate, key};
+    use num_bigint_dig::BigInt;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn parse() {
+        let key = key(oid::CRYPTOPRO_A, 1);
+        let certificate = certificate("Root", &key.public_key(), "Root", &key, 0x1234, &[ca(None)]);
+        let der = certificate.to_der().to_vec();
+
+        assert_eq!(certificate.version(), 3);
+        assert_eq!(certificate.serial(), &BigInt::from(0x1234));
+        assert_eq!(
+            certificate.signature_algorithm(),
+            oid::SIGNWITHDIGEST_GOST3410_12_256
+        );
+        assert_eq!(certificate.subject().to_string(), "C=RU, CN=Root");
+        assert_eq!(certificate.subject().common_name(), Some("Root"));
+        assert_eq!(certificate.issuer(), certificate.subject());
+        assert_eq!(
+            certificate.not_before(),
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+        );
+        assert_eq!(certificate.public_key(), &key.public_key());
+
+        let extension = certificate.extension(oid::BASIC_CONSTRAINTS).unwrap();
+        assert!(extension.critical);
+        assert_eq!(extension.value, [0x30, 0x03, 0x01, 0x01, 0xff]);
+
+        let pem = format!("{}{}", certificate.to_pem(), certificate.to_pem());
+        assert_eq!(Certificate::all_from_pem(&pem).unwrap().len(), 2);
+        assert_eq!(Certificate::from_pem(&pem).unwrap().to_der(), &der[..]);
+
+        assert!(Certificate::from_der(&der[..der.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn verify() {
+        let root = key(oid::TC26_512_A, 2);
+        let leaf = key(oid::CRYPTOPRO_A, 3);
+
+        let root_certificate = certificate(
+            "Root",
+            &root.public_key(),
+            "Root",
+            &root,
+            0x1234,
+            &[ca(None)],
+        );
+        let leaf_certificate = certificate(
+            "Leaf",
+            &leaf.public_key(),
+            "Root",
+            &root,
+            0x1234,
+            &[ca(None)],
+        );
+        let leaf_der = leaf_certificate.to_der().to_vec();
+
+        assert_eq!(
+            leaf_certificate.signature_algorithm(),
+            oid::SIGNWITHDIGEST_GOST3410_12_512
+        );
+        assert_eq!(root_certificate.verify_issued_by(&root_certificate), Ok(()));
+        assert_eq!(leaf_certificate.verify_issued_by(&root_certificate), Ok(()));
+        assert_eq!(
+            root_certificate.verify_issued_by(&leaf_certificate),
+            Err(Error::IssuerMismatch)
+        );
+        assert_eq!(
+            leaf_certificate.verify_signature(&leaf.public_key()),
+            Err(Error::InvalidSignature)
+        );
+
+        // `r || s` instead of `s || r`.
+        let mut reversed = leaf_der.clone();
+        let (len, size) = (reversed.len(), root.curve().size());
+        reversed[len - 2 * size..].rotate_left(size);
+        assert_eq!(
+            Certificate::from_der(&reversed)
+                .unwrap()
+                .verify_issued_by(&root_certificate),
+            Err(Error::InvalidSignature)
+        );
+
+        let mut tampered = leaf_der.clone();
+        let serial = tampered
+            .windows(4)
+            .position(|window| window == [0x02, 0x02, 0x12, 0x34])
+            .unwrap();
+        tampered[serial + 3] ^= 1;
+        assert_eq!(
+            Certificate::from_der(&tampered)
+                .unwrap()
+                .verify_issued_by(&root_certificate),
+            Err(Error::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn names() {
+        let name = Name::from_der(&der::sequence(&[der::set_of(&[der::sequence(&[
+            der::oid("1.2.643.3.131.1.1").unwrap(),
+            der::encode(der::NUMERIC_STRING, b"007710474375"),
+        ])])]))
+        .unwrap();
+        assert_eq!(name.to_string(), "1.2.643.3.131.1.1=007710474375");
+        assert_eq!(name.common_name(), None);
+
+        assert_eq!(
+            Name::new(&[("CN", "Root")]),
+            Err(Error::Asn1("invalid OBJECT IDENTIFIER"))
+        );
+        assert!(Extension::new("2.5.29.x", false, vec![]).to_der().is_err());
+    }
+
+    #[test]
+    fn build() {
+        let root_key = key(oid::CRYPTOPRO_A, 4);
+        let leaf_key = key(oid::CRYPTOPRO_A, 5);
+        let not_before = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let not_after = UNIX_EPOCH + Duration::from_secs(1_800_000_000);
+
+        let root = CertificateBuilder::new(
+            Name::new(&[(oid::COUNTRY, "RU"), (oid::COMMON_NAME, "Root")]).unwrap(),
+            root_key.public_key(),
+        )
+        .serial(BigInt::from(1))
+        .validity(not_before, not_after)
+        .basic_constraints(BasicConstraints {
+            ca: true,
+            path_len: Some(0),
+        })
+        .key_usage(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN)
+        .sign(&root_key)
+        .unwrap();
+
+        assert_eq!(root.version(), 3);
+        assert_eq!(root.serial(), &BigInt::from(1));
+        assert!(root.is_self_issued());
+        assert_eq!(root.verify_issued_by(&root), Ok(()));
+        assert_eq!(root.not_after(), not_after);
+        assert_eq!(
+            root.basic_constraints(),
+            Ok(Some(BasicConstraints {
+                ca: true,
+                path_len: Some(0)
+            }))
+        );
+        assert_eq!(
+            root.key_usage(),
+            Ok(Some(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN))
+        );
+        let root_id = key_identifier(&root_key.public_key());
+        assert_eq!(root.subject_key_identifier(), Ok(Some(root_id.clone())));
+        assert_eq!(root.authority_key_identifier(), Ok(Some(root_id.clone())));
+
+        let request = RequestBuilder::new(Name::new(&[(oid::COMMON_NAME, "Leaf")]).unwrap())
+            .alt_name(AltName::Dns("leaf.example.ru".to_string()))
+            .alt_name(AltName::Ip([10, 0, 0, 1].into()))
+            .sign(&leaf_key)
+            .unwrap();
+        let builder = CertificateBuilder::from_request(&request)
+            .unwrap()
+            .issuer(&root)
+            .key_usage(KeyUsage::DIGITAL_SIGNATURE);
+
+        assert_eq!(builder.sign(&leaf_key).err(), Some(Error::KeyMismatch));
+        let leaf = builder.sign(&root_key).unwrap();
+
+        assert_eq!(leaf.issuer(), root.subject());
+        assert_eq!(leaf.subject().to_string(), "CN=Leaf");
+        assert_eq!(leaf.public_key(), &leaf_key.public_key());
+        assert_eq!(leaf.verify_issued_by(&root), Ok(()));
+        assert_eq!(leaf.basic_constraints(), Ok(None));
+        assert_eq!(leaf.authority_key_identifier(), Ok(Some(root_id)));
+        assert_eq!(
+            leaf.alt_names(),
+            Ok(vec![
+                AltName::Dns("leaf.example.ru".to_string()),
+                AltName::Ip([10, 0, 0, 1].into()),
+            ])
+        );
+        assert!(!leaf.extension(oid::SUBJECT_ALT_NAME).unwrap().critical);
+        assert!(leaf.extension(oid::KEY_USAGE).unwrap().critical);
+        assert_ne!(leaf.serial(), root.serial());
+    }
+}